@@ -0,0 +1,131 @@
+// Scalar vs SIMD math benchmarks
+//
+// Run with `cargo bench --features simd` to compare both paths; without the
+// feature only the scalar ones are measured.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use gears::batch;
+use gears::component::{Transform, Velocity};
+use gears::matrix::Matrix4x4;
+use gears::quaternion::Quaternion;
+use gears::vector::Vector3;
+
+const ENTITIES: usize = 10_000;
+
+fn transforms() -> Vec<Transform> {
+    (0..ENTITIES)
+        .map(|i| {
+            let i = i as f32;
+            Transform {
+                position: Vector3::new(i, i * 0.5, -i),
+                rotation: Quaternion::angle_axis(i, Vector3::new(1.0, 2.0, 3.0)),
+                scale: Vector3::one(),
+            }
+        })
+        .collect()
+}
+
+fn velocities() -> Vec<Velocity> {
+    (0..ENTITIES)
+        .map(|i| Velocity::new(1.0, i as f32 * 0.01, -1.0))
+        .collect()
+}
+
+fn integrate_positions(c: &mut Criterion) {
+    let mut group = c.benchmark_group("integrate_positions");
+    let velocities = velocities();
+    let mut transforms = transforms();
+    group.bench_function(BenchmarkId::new("scalar", ENTITIES), |b| {
+        b.iter(|| {
+            batch::scalar::integrate_positions(&mut transforms, &velocities, black_box(0.016))
+        })
+    });
+    #[cfg(feature = "simd")]
+    group.bench_function(BenchmarkId::new("simd", ENTITIES), |b| {
+        b.iter(|| batch::simd::integrate_positions(&mut transforms, &velocities, black_box(0.016)))
+    });
+    group.finish();
+}
+
+fn rotate_all(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rotate_all");
+    let rotation = Quaternion::angle_axis(1.0, Vector3::up());
+    let mut transforms = transforms();
+    group.bench_function(BenchmarkId::new("scalar", ENTITIES), |b| {
+        b.iter(|| batch::scalar::rotate_all(&mut transforms, black_box(rotation)))
+    });
+    #[cfg(feature = "simd")]
+    group.bench_function(BenchmarkId::new("simd", ENTITIES), |b| {
+        b.iter(|| batch::simd::rotate_all(&mut transforms, black_box(rotation)))
+    });
+    group.finish();
+}
+
+fn transform_points(c: &mut Criterion) {
+    let mut group = c.benchmark_group("transform_points");
+    let matrix = Matrix4x4::from_trs(
+        Vector3::new(1.0, 2.0, 3.0),
+        Quaternion::angle_axis(45.0, Vector3::up()),
+        Vector3::one(),
+    );
+    let mut points: Vec<Vector3> = transforms().iter().map(|t| t.position).collect();
+    group.bench_function(BenchmarkId::new("scalar", ENTITIES), |b| {
+        b.iter(|| batch::scalar::transform_points(black_box(&matrix), &mut points))
+    });
+    #[cfg(feature = "simd")]
+    group.bench_function(BenchmarkId::new("simd", ENTITIES), |b| {
+        b.iter(|| batch::simd::transform_points(black_box(&matrix), &mut points))
+    });
+    group.finish();
+}
+
+fn to_matrices(c: &mut Criterion) {
+    let mut group = c.benchmark_group("to_matrices");
+    let transforms = transforms();
+    let mut matrices = vec![Matrix4x4::IDENTITY; ENTITIES];
+    group.bench_function(BenchmarkId::new("scalar", ENTITIES), |b| {
+        b.iter(|| batch::scalar::to_matrices(black_box(&transforms), &mut matrices))
+    });
+    #[cfg(feature = "simd")]
+    group.bench_function(BenchmarkId::new("simd", ENTITIES), |b| {
+        b.iter(|| batch::simd::to_matrices(black_box(&transforms), &mut matrices))
+    });
+    group.finish();
+}
+
+// Unlike the batch functions, the operators pick their path at compile time,
+// so these measure whichever path the suite was built with.
+fn operators(c: &mut Criterion) {
+    let a = Matrix4x4::from_trs(
+        Vector3::new(1.0, 2.0, 3.0),
+        Quaternion::angle_axis(30.0, Vector3::up()),
+        Vector3::new(2.0, 2.0, 2.0),
+    );
+    let b = Matrix4x4::perspective(60.0, 16.0 / 9.0, 0.1, 100.0);
+    let p = Quaternion::angle_axis(30.0, Vector3::up());
+    let q = Quaternion::angle_axis(45.0, Vector3::right());
+    let v = Vector3::new(1.0, 2.0, 3.0);
+
+    c.bench_function("matrix4x4_mul", |bench| {
+        bench.iter(|| black_box(a) * black_box(b))
+    });
+    c.bench_function("quaternion_mul", |bench| {
+        bench.iter(|| black_box(p) * black_box(q))
+    });
+    c.bench_function("quaternion_rotate", |bench| {
+        bench.iter(|| black_box(p) * black_box(v))
+    });
+    c.bench_function("vector3_cross", |bench| {
+        bench.iter(|| Vector3::cross(black_box(v), black_box(Vector3::up())))
+    });
+}
+
+criterion_group!(
+    benches,
+    integrate_positions,
+    rotate_all,
+    transform_points,
+    to_matrices,
+    operators
+);
+criterion_main!(benches);
@@ -0,0 +1,547 @@
+// Actions
+//
+// Gameplay asks about named actions ("jump", "move_x") rather than physical
+// keys, so controls can be rebound without touching the systems that use
+// them. An `ActionMap` binds each action to keys, mouse buttons and gamepad
+// input and is stored as RON. Every frame `GameManager` works out the
+// `ActionState` from the map and the `Input` resource.
+//
+// A button action is bound to chords, sets of buttons that must all be held,
+// which is also how modifiers are written: `[Key(LControl), Key(S)]`. While a
+// chord is held, chords made of some of its buttons are not, so saving with
+// Ctrl+S doesn't also trigger an action bound to S. A chord pressed and let go
+// again between two ticks still counts as just pressed and just released.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::input_manager::{GamepadAxis, GamepadButton, Input};
+
+/// A physical button an action can be bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Button {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    /// A button on any connected gamepad.
+    Gamepad(GamepadButton),
+}
+
+impl Button {
+    fn pressed(self, input: &Input) -> bool {
+        match self {
+            Button::Key(key) => input.keys.pressed(key),
+            Button::Mouse(button) => input.mouse_buttons.pressed(button),
+            Button::Gamepad(button) => input
+                .gamepads()
+                .iter()
+                .any(|&gamepad| input.gamepad_buttons.pressed((gamepad, button))),
+        }
+    }
+
+    /// Returns true if the button went down and came back up since the last tick.
+    fn tapped(self, input: &Input) -> bool {
+        match self {
+            Button::Key(key) => input.keys.just_pressed(key) && input.keys.just_released(key),
+            Button::Mouse(button) => {
+                input.mouse_buttons.just_pressed(button)
+                    && input.mouse_buttons.just_released(button)
+            }
+            Button::Gamepad(button) => input.gamepads().iter().any(|&gamepad| {
+                input.gamepad_buttons.just_pressed((gamepad, button))
+                    && input.gamepad_buttons.just_released((gamepad, button))
+            }),
+        }
+    }
+}
+
+/// Buttons that must all be held at once.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Chord(pub Vec<Button>);
+
+impl Chord {
+    fn pressed(&self, input: &Input) -> bool {
+        !self.0.is_empty() && self.0.iter().all(|button| button.pressed(input))
+    }
+
+    /// Returns true if the chord was completed by a button tapped since the
+    /// last tick, with the rest of its buttons held.
+    fn tapped(&self, input: &Input) -> bool {
+        let (tapped, held): (Vec<Button>, Vec<Button>) =
+            self.0.iter().partition(|button| button.tapped(input));
+        !tapped.is_empty() && held.iter().all(|button| button.pressed(input))
+    }
+
+    /// Returns true if `other` holds every button of this chord and more.
+    fn within(&self, other: &Chord) -> bool {
+        other.0.len() > self.0.len() && self.0.iter().all(|b| other.0.contains(b))
+    }
+}
+
+impl From<Button> for Chord {
+    fn from(button: Button) -> Chord {
+        Chord(vec![button])
+    }
+}
+
+/// An input giving an axis action a value in the range [-1, 1].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AxisBinding {
+    /// An axis on any connected gamepad. Movement within `dead_zone` of the
+    /// centre is ignored, and the rest is scaled back up to the full range.
+    Gamepad { axis: GamepadAxis, dead_zone: f32 },
+    /// -1 while `negative` is held and 1 while `positive` is.
+    Buttons { negative: Button, positive: Button },
+}
+
+impl AxisBinding {
+    fn value(self, input: &Input) -> f32 {
+        match self {
+            AxisBinding::Gamepad { axis, dead_zone } => input
+                .gamepads()
+                .iter()
+                .map(|&gamepad| outside_dead_zone(input.axis(gamepad, axis), dead_zone))
+                .fold(0.0, |best: f32, value| {
+                    if value.abs() > best.abs() {
+                        value
+                    } else {
+                        best
+                    }
+                }),
+            AxisBinding::Buttons { negative, positive } => {
+                let held = |button: Button| if button.pressed(input) { 1.0 } else { 0.0 };
+                held(positive) - held(negative)
+            }
+        }
+    }
+}
+
+fn outside_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    let dead_zone = dead_zone.clamp(0.0, 0.99);
+    if value.abs() <= dead_zone {
+        0.0
+    } else {
+        value.signum() * ((value.abs() - dead_zone) / (1.0 - dead_zone)).min(1.0)
+    }
+}
+
+/// Bindings of every action, loaded from and saved to RON.
+///
+/// ```text
+/// (
+///     buttons: {
+///         "jump": [[Key(Space)], [Gamepad(South)]],
+///         "save": [[Key(LControl), Key(S)]],
+///     },
+///     axes: {
+///         "move_x": [
+///             Buttons(negative: Key(A), positive: Key(D)),
+///             Gamepad(axis: LeftStickX, dead_zone: 0.2),
+///         ],
+///     },
+/// )
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActionMap {
+    /// Button actions, pressed while any of their chords is.
+    #[serde(default)]
+    pub buttons: BTreeMap<String, Vec<Chord>>,
+    /// Axis actions, the sum of their bindings clamped to [-1, 1].
+    #[serde(default)]
+    pub axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+impl ActionMap {
+    pub fn new() -> ActionMap {
+        ActionMap::default()
+    }
+
+    pub fn from_ron(ron: &str) -> Result<ActionMap, ActionMapError> {
+        ron::de::from_str(ron).map_err(ActionMapError::Parse)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ActionMapError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())
+            .map_err(ActionMapError::Serialize)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ActionMap, ActionMapError> {
+        let path = path.as_ref();
+        let ron = fs::read_to_string(path).map_err(|source| ActionMapError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        ActionMap::from_ron(&ron)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ActionMapError> {
+        let path = path.as_ref();
+        fs::write(path, self.to_ron()?).map_err(|source| ActionMapError::Write {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Add a chord that presses the button action `action`.
+    pub fn bind_button<C: Into<Chord>>(&mut self, action: &str, chord: C) -> &mut Self {
+        self.buttons
+            .entry(action.to_string())
+            .or_default()
+            .push(chord.into());
+        self
+    }
+
+    /// Add an input to the axis action `action`.
+    pub fn bind_axis(&mut self, action: &str, binding: AxisBinding) -> &mut Self {
+        self.axes
+            .entry(action.to_string())
+            .or_default()
+            .push(binding);
+        self
+    }
+
+    /// Replace every binding of the button action `action` with `chord`.
+    pub fn rebind_button<C: Into<Chord>>(&mut self, action: &str, chord: C) -> &mut Self {
+        self.buttons.insert(action.to_string(), vec![chord.into()]);
+        self
+    }
+
+    /// Replace every binding of the axis action `action` with `binding`.
+    pub fn rebind_axis(&mut self, action: &str, binding: AxisBinding) -> &mut Self {
+        self.axes.insert(action.to_string(), vec![binding]);
+        self
+    }
+
+    /// Remove the action and its bindings.
+    pub fn unbind(&mut self, action: &str) -> &mut Self {
+        self.buttons.remove(action);
+        self.axes.remove(action);
+        self
+    }
+}
+
+/// Errors raised while loading or saving an `ActionMap`.
+#[derive(Debug)]
+pub enum ActionMapError {
+    /// The file couldn't be read.
+    Read { path: PathBuf, source: io::Error },
+    /// The file couldn't be written.
+    Write { path: PathBuf, source: io::Error },
+    /// The RON isn't a valid action map.
+    Parse(ron::Error),
+    /// The map couldn't be written as RON.
+    Serialize(ron::Error),
+}
+
+impl fmt::Display for ActionMapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ActionMapError::Read { path, source } => {
+                write!(f, "Couldn't read action map {}: {}", path.display(), source)
+            }
+            ActionMapError::Write { path, source } => {
+                write!(
+                    f,
+                    "Couldn't write action map {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+            ActionMapError::Parse(source) => write!(f, "Invalid action map: {}", source),
+            ActionMapError::Serialize(source) => {
+                write!(f, "Couldn't serialize action map: {}", source)
+            }
+        }
+    }
+}
+
+impl Error for ActionMapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ActionMapError::Read { source, .. } | ActionMapError::Write { source, .. } => {
+                Some(source)
+            }
+            ActionMapError::Parse(source) | ActionMapError::Serialize(source) => Some(source),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Action {
+    value: f32,
+    pressed: bool,
+    just_pressed: bool,
+    just_released: bool,
+}
+
+/// Resource holding the state of every action as of this frame. Like `Input`,
+/// it keeps what changed until a simulation tick has seen it.
+///
+/// Unknown actions are never pressed and have a value of 0.
+#[derive(Clone, Debug, Default)]
+pub struct ActionState {
+    actions: HashMap<String, Action>,
+}
+
+impl ActionState {
+    /// Returns true while the action is held, or its axis is off centre.
+    pub fn pressed(&self, action: &str) -> bool {
+        self.get(action).pressed
+    }
+
+    /// Returns true if the action was pressed since the last tick.
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.get(action).just_pressed
+    }
+
+    /// Returns true if the action was released since the last tick.
+    pub fn just_released(&self, action: &str) -> bool {
+        self.get(action).just_released
+    }
+
+    /// Value of an axis action in the range [-1, 1], or 1 while a button
+    /// action is held.
+    pub fn value(&self, action: &str) -> f32 {
+        self.get(action).value
+    }
+
+    /// Work out this frame's state of every action in `map` from `input`.
+    pub fn update(&mut self, map: &ActionMap, input: &Input) {
+        let held: Vec<(&String, &Chord)> = map
+            .buttons
+            .iter()
+            .flat_map(|(name, chords)| chords.iter().map(move |chord| (name, chord)))
+            .filter(|(_, chord)| chord.pressed(input))
+            .collect();
+        let mut values: HashMap<&String, f32> = HashMap::new();
+        for (name, chord) in &held {
+            if !held.iter().any(|(_, other)| chord.within(other)) {
+                values.insert(name, 1.0);
+            }
+        }
+        for (name, bindings) in &map.axes {
+            let value: f32 = bindings.iter().map(|binding| binding.value(input)).sum();
+            values.insert(name, value.clamp(-1.0, 1.0));
+        }
+        let taps: Vec<(&String, &Chord)> = map
+            .buttons
+            .iter()
+            .flat_map(|(name, chords)| chords.iter().map(move |chord| (name, chord)))
+            .filter(|(_, chord)| chord.tapped(input))
+            .collect();
+        let tapped: HashSet<&String> = taps
+            .iter()
+            .filter(|(_, chord)| !taps.iter().any(|(_, other)| chord.within(other)))
+            .map(|(name, _)| *name)
+            .collect();
+
+        let mut previous = std::mem::take(&mut self.actions);
+        for name in map.buttons.keys().chain(map.axes.keys()) {
+            let value = values.get(name).copied().unwrap_or(0.0);
+            let was = previous.remove(name).unwrap_or_default();
+            let pressed = value != 0.0;
+            // Pressed and released again without either showing up in `pressed`
+            let tap = tapped.contains(name) && !pressed && !was.pressed;
+            self.actions.insert(
+                name.clone(),
+                Action {
+                    value,
+                    pressed,
+                    just_pressed: was.just_pressed || (pressed && !was.pressed) || tap,
+                    just_released: was.just_released || (was.pressed && !pressed) || tap,
+                },
+            );
+        }
+    }
+
+    /// Forget what changed, once a simulation tick has seen it.
+    pub fn clear_changes(&mut self) {
+        for action in self.actions.values_mut() {
+            action.just_pressed = false;
+            action.just_released = false;
+        }
+    }
+
+    fn get(&self, action: &str) -> Action {
+        self.actions.get(action).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+    use crate::input_manager::{Gamepad, InputEvent};
+    use winit::event::ElementState;
+
+    fn key(input: &mut Input, key: VirtualKeyCode, state: ElementState) {
+        input.apply(&InputEvent::Key { key, state });
+    }
+
+    #[test]
+    fn button_actions_follow_their_bindings() {
+        let mut map = ActionMap::new();
+        map.bind_button("jump", Button::Key(VirtualKeyCode::Space))
+            .bind_button("jump", Button::Gamepad(GamepadButton::South));
+        let mut input = Input::default();
+        let mut actions = ActionState::default();
+
+        key(&mut input, VirtualKeyCode::Space, ElementState::Pressed);
+        actions.update(&map, &input);
+        assert!(actions.pressed("jump") && actions.just_pressed("jump"));
+
+        actions.clear_changes();
+        actions.update(&map, &input);
+        assert!(actions.pressed("jump") && !actions.just_pressed("jump"));
+
+        key(&mut input, VirtualKeyCode::Space, ElementState::Released);
+        actions.update(&map, &input);
+        assert!(!actions.pressed("jump") && actions.just_released("jump"));
+        assert!(!actions.pressed("unbound"));
+    }
+
+    #[test]
+    fn buttons_tapped_within_a_frame_press_and_release_the_action() {
+        let mut map = ActionMap::new();
+        map.bind_button("jump", Button::Key(VirtualKeyCode::Space))
+            .bind_button("back", Button::Key(VirtualKeyCode::S))
+            .bind_button(
+                "save",
+                Chord(vec![
+                    Button::Key(VirtualKeyCode::LControl),
+                    Button::Key(VirtualKeyCode::S),
+                ]),
+            );
+        let mut input = Input::default();
+        let mut actions = ActionState::default();
+
+        key(&mut input, VirtualKeyCode::Space, ElementState::Pressed);
+        key(&mut input, VirtualKeyCode::Space, ElementState::Released);
+        actions.update(&map, &input);
+        assert!(!actions.pressed("jump"));
+        assert!(actions.just_pressed("jump") && actions.just_released("jump"));
+
+        input.clear_changes();
+        actions.clear_changes();
+        actions.update(&map, &input);
+        assert!(!actions.just_pressed("jump") && !actions.just_released("jump"));
+
+        key(&mut input, VirtualKeyCode::LControl, ElementState::Pressed);
+        key(&mut input, VirtualKeyCode::S, ElementState::Pressed);
+        key(&mut input, VirtualKeyCode::S, ElementState::Released);
+        actions.update(&map, &input);
+        assert!(actions.just_pressed("save") && !actions.pressed("save"));
+        assert!(!actions.just_pressed("back"));
+    }
+
+    #[test]
+    fn chords_hide_the_chords_inside_them() {
+        let mut map = ActionMap::new();
+        map.bind_button("back", Button::Key(VirtualKeyCode::S))
+            .bind_button(
+                "save",
+                Chord(vec![
+                    Button::Key(VirtualKeyCode::LControl),
+                    Button::Key(VirtualKeyCode::S),
+                ]),
+            );
+        let mut input = Input::default();
+        let mut actions = ActionState::default();
+
+        key(&mut input, VirtualKeyCode::S, ElementState::Pressed);
+        actions.update(&map, &input);
+        assert!(actions.pressed("back") && !actions.pressed("save"));
+
+        key(&mut input, VirtualKeyCode::LControl, ElementState::Pressed);
+        actions.update(&map, &input);
+        assert!(actions.just_pressed("save"));
+        assert!(actions.just_released("back"));
+    }
+
+    #[test]
+    fn axes_combine_buttons_and_sticks_past_the_dead_zone() {
+        let mut map = ActionMap::new();
+        map.bind_axis(
+            "move_x",
+            AxisBinding::Buttons {
+                negative: Button::Key(VirtualKeyCode::A),
+                positive: Button::Key(VirtualKeyCode::D),
+            },
+        )
+        .bind_axis(
+            "move_x",
+            AxisBinding::Gamepad {
+                axis: GamepadAxis::LeftStickX,
+                dead_zone: 0.2,
+            },
+        );
+        let mut input = Input::default();
+        let mut actions = ActionState::default();
+        let pad = Gamepad(0);
+        input.apply(&InputEvent::GamepadConnected { gamepad: pad });
+        let stick = |input: &mut Input, value| {
+            input.apply(&InputEvent::GamepadAxis {
+                gamepad: pad,
+                axis: GamepadAxis::LeftStickX,
+                value,
+            })
+        };
+
+        stick(&mut input, 0.1);
+        actions.update(&map, &input);
+        assert!(!actions.pressed("move_x"));
+
+        stick(&mut input, -0.6);
+        actions.update(&map, &input);
+        assert_approx_eq!(actions.value("move_x"), -0.5);
+
+        key(&mut input, VirtualKeyCode::A, ElementState::Pressed);
+        actions.update(&map, &input);
+        assert_approx_eq!(actions.value("move_x"), -1.0);
+    }
+
+    #[test]
+    fn maps_round_trip_through_ron() {
+        let map = ActionMap::from_ron(
+            r#"(
+                buttons: {
+                    "jump": [[Key(Space)], [Gamepad(South)]],
+                    "save": [[Key(LControl), Key(S)]],
+                },
+                axes: {
+                    "move_x": [Gamepad(axis: LeftStickX, dead_zone: 0.2)],
+                },
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(map.buttons["jump"].len(), 2);
+        assert_eq!(ActionMap::from_ron(&map.to_ron().unwrap()).unwrap(), map);
+        assert!(matches!(
+            ActionMap::from_ron("(buttons: {\"jump\": [[Key(NotAKey)]]})"),
+            Err(ActionMapError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn rebinding_replaces_earlier_bindings() {
+        let mut map = ActionMap::new();
+        map.bind_button("jump", Button::Key(VirtualKeyCode::Space))
+            .bind_button("jump", Button::Key(VirtualKeyCode::W))
+            .rebind_button("jump", Button::Mouse(MouseButton::Right));
+
+        assert_eq!(
+            map.buttons["jump"],
+            vec![Chord(vec![Button::Mouse(MouseButton::Right)])]
+        );
+        map.unbind("jump");
+        assert!(map.buttons.is_empty());
+    }
+}
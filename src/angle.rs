@@ -0,0 +1,38 @@
+// Angles
+//
+// Newtypes that make the unit of an angle explicit at API boundaries, so a
+// value typed into a level file in degrees can't be read as radians.
+
+use serde::{Deserialize, Serialize};
+
+/// An angle in degrees.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Degrees(pub f32);
+
+/// An angle in radians.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Radians(pub f32);
+
+impl From<Degrees> for Radians {
+    fn from(angle: Degrees) -> Radians {
+        Radians(angle.0.to_radians())
+    }
+}
+
+impl From<Radians> for Degrees {
+    fn from(angle: Radians) -> Degrees {
+        Degrees(angle.0.to_degrees())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn degrees_and_radians_convert_both_ways() {
+        assert_eq!(Radians::from(Degrees(180.0)), Radians(PI));
+        assert_eq!(Degrees::from(Radians(PI)), Degrees(180.0));
+    }
+}
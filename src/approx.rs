@@ -0,0 +1,160 @@
+// Approximate equality
+//
+// Float results from the math types rarely match a literal bit for bit, so
+// comparisons pass if each component is within an absolute epsilon (useful
+// near zero) or within a number of ULPs, i.e. representable floats, of the
+// other (useful for large magnitudes).
+
+use super::matrix::{Matrix3x3, Matrix4x4};
+use super::quaternion::Quaternion;
+use super::vector::{Vector2, Vector3, Vector4};
+
+/// Absolute tolerance used by `approx_eq`.
+pub const DEFAULT_EPSILON: f32 = 1e-5;
+/// ULP tolerance used by `approx_eq`.
+pub const DEFAULT_MAX_ULPS: u32 = 4;
+
+/// Equality within a tolerance, component by component.
+///
+/// Note that `Quaternion`s are compared component-wise, so `q` and `-q`, which
+/// represent the same rotation, are not approximately equal.
+pub trait ApproxEq {
+    /// Returns true if every component is within `epsilon` of the other's, or
+    /// at most `max_ulps` representable floats away from it.
+    fn approx_eq_with(&self, other: &Self, epsilon: f32, max_ulps: u32) -> bool;
+
+    /// Compare using `DEFAULT_EPSILON` and `DEFAULT_MAX_ULPS`.
+    fn approx_eq(&self, other: &Self) -> bool {
+        self.approx_eq_with(other, DEFAULT_EPSILON, DEFAULT_MAX_ULPS)
+    }
+
+    /// Compare using an absolute tolerance only.
+    fn approx_eq_eps(&self, other: &Self, epsilon: f32) -> bool {
+        self.approx_eq_with(other, epsilon, 0)
+    }
+
+    /// Compare using a ULP tolerance only.
+    fn approx_eq_ulps(&self, other: &Self, max_ulps: u32) -> bool {
+        self.approx_eq_with(other, 0.0, max_ulps)
+    }
+}
+
+impl ApproxEq for f32 {
+    fn approx_eq_with(&self, other: &Self, epsilon: f32, max_ulps: u32) -> bool {
+        if self == other || (self - other).abs() <= epsilon {
+            return true;
+        }
+        // Floats of the same sign are ordered like their bit patterns, so the
+        // difference between the bits counts the floats in between.
+        if self.is_nan() || other.is_nan() || self.is_sign_positive() != other.is_sign_positive() {
+            return false;
+        }
+        let ulps = (i64::from(self.to_bits()) - i64::from(other.to_bits())).abs();
+        ulps <= i64::from(max_ulps)
+    }
+}
+
+macro_rules! impl_approx_eq {
+    ($name:ident, $($field:ident),+) => {
+        impl ApproxEq for $name {
+            fn approx_eq_with(&self, other: &Self, epsilon: f32, max_ulps: u32) -> bool {
+                $(self.$field.approx_eq_with(&other.$field, epsilon, max_ulps))&&+
+            }
+        }
+    };
+}
+
+impl_approx_eq!(Vector2, x, y);
+impl_approx_eq!(Vector3, x, y, z);
+impl_approx_eq!(Vector4, x, y, z, w);
+impl_approx_eq!(Quaternion, w, x, y, z);
+impl_approx_eq!(Matrix3x3, m);
+impl_approx_eq!(Matrix4x4, m);
+
+impl<T: ApproxEq, const N: usize> ApproxEq for [T; N] {
+    fn approx_eq_with(&self, other: &Self, epsilon: f32, max_ulps: u32) -> bool {
+        self.iter()
+            .zip(other.iter())
+            .all(|(a, b)| a.approx_eq_with(b, epsilon, max_ulps))
+    }
+}
+
+/// Asserts that two values are approximately equal using `ApproxEq`.
+///
+/// Takes an optional absolute epsilon, which replaces the default tolerances.
+///
+/// ```ignore
+/// assert_approx_eq!(v.magnitude(), 1.0);
+/// assert_approx_eq!(q * Vector3::forward(), Vector3::right(), 1e-4);
+/// ```
+#[macro_export]
+macro_rules! assert_approx_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !$crate::approx::ApproxEq::approx_eq(left, right) {
+                    panic!(
+                        "assertion failed: `left ≈ right`\n  left: `{:?}`\n right: `{:?}`",
+                        left, right
+                    );
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $epsilon:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !$crate::approx::ApproxEq::approx_eq_eps(left, right, $epsilon) {
+                    panic!(
+                        "assertion failed: `left ≈ right` (epsilon: `{}`)\n  left: `{:?}`\n right: `{:?}`",
+                        $epsilon, left, right
+                    );
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floats_within_epsilon_are_equal() {
+        assert!(1.0f32.approx_eq(&1.000_001));
+        assert!(0.0f32.approx_eq(&-0.000_001));
+        assert!(!1.0f32.approx_eq(&1.001));
+        assert!(1.0f32.approx_eq_eps(&1.001, 0.01));
+    }
+
+    #[test]
+    fn large_floats_are_compared_by_ulps() {
+        let a = 1.0e6f32;
+        let b = f32::from_bits(a.to_bits() + 2);
+
+        assert!(!a.approx_eq_eps(&b, DEFAULT_EPSILON));
+        assert!(a.approx_eq_ulps(&b, 2));
+        assert!(!a.approx_eq_ulps(&b, 1));
+        assert!(a.approx_eq(&b));
+    }
+
+    #[test]
+    fn nan_and_opposite_signs_are_never_ulps_equal() {
+        assert!(!f32::NAN.approx_eq(&f32::NAN));
+        assert!(!1.0e-30f32.approx_eq_ulps(&-1.0e-30, u32::MAX));
+    }
+
+    #[test]
+    fn math_types_compare_component_wise() {
+        assert!(Vector3::new(1.0, 2.0, 3.0).approx_eq(&Vector3::new(1.0, 2.000_001, 3.0)));
+        assert!(!Vector3::new(1.0, 2.0, 3.0).approx_eq(&Vector3::new(1.0, 2.1, 3.0)));
+        assert!(Matrix4x4::IDENTITY.approx_eq(&Matrix4x4::IDENTITY));
+        assert!(!Quaternion::identity().approx_eq(&Quaternion::new(-1.0, 0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "assertion failed: `left ≈ right`")]
+    fn assert_approx_eq_panics_when_values_differ() {
+        crate::assert_approx_eq!(1.0f32, 2.0);
+    }
+}
@@ -0,0 +1,232 @@
+// Batch operations
+//
+// Transform math over whole slices, e.g. the component columns of a legion
+// chunk. The top-level functions use the SIMD path when the `simd` feature is
+// enabled and the scalar path otherwise; both are exposed so they can be
+// compared directly. Slices of different lengths are processed up to the
+// shorter one.
+
+use super::component::{Transform, Velocity};
+use super::matrix::Matrix4x4;
+use super::quaternion::Quaternion;
+use super::vector::Vector3;
+
+#[cfg(not(feature = "simd"))]
+pub use self::scalar::*;
+#[cfg(feature = "simd")]
+pub use self::simd::*;
+
+/// One entity at a time, in plain `f32` arithmetic on the fields. Doesn't use
+/// the math types' operators, which are SIMD too with the `simd` feature, so
+/// the two paths can be compared in the same build.
+pub mod scalar {
+    use super::*;
+
+    /// Move each transform by its velocity over `dt` seconds.
+    pub fn integrate_positions(transforms: &mut [Transform], velocities: &[Velocity], dt: f32) {
+        for (transform, velocity) in transforms.iter_mut().zip(velocities) {
+            let position = &mut transform.position;
+            position.x += velocity.dx * dt;
+            position.y += velocity.dy * dt;
+            position.z += velocity.dz * dt;
+        }
+    }
+
+    /// Apply `rotation` after each transform's current rotation.
+    pub fn rotate_all(transforms: &mut [Transform], rotation: Quaternion) {
+        let Quaternion { w, x, y, z } = rotation;
+        for transform in transforms.iter_mut() {
+            let q = transform.rotation;
+            transform.rotation = Quaternion {
+                w: w * q.w - x * q.x - y * q.y - z * q.z,
+                x: w * q.x + x * q.w + y * q.z - z * q.y,
+                y: w * q.y - x * q.z + y * q.w + z * q.x,
+                z: w * q.z + x * q.y - y * q.x + z * q.w,
+            };
+        }
+    }
+
+    /// Transform each point by an affine matrix, as `Matrix4x4::multiply_point3x4`.
+    pub fn transform_points(matrix: &Matrix4x4, points: &mut [Vector3]) {
+        let m = &matrix.m;
+        for point in points.iter_mut() {
+            let Vector3 { x, y, z } = *point;
+            *point = Vector3 {
+                x: m[0][0] * x + m[1][0] * y + m[2][0] * z + m[3][0],
+                y: m[0][1] * x + m[1][1] * y + m[2][1] * z + m[3][1],
+                z: m[0][2] * x + m[1][2] * y + m[2][2] * z + m[3][2],
+            };
+        }
+    }
+
+    /// Write the local-to-world matrix of each transform into `matrices`.
+    pub fn to_matrices(transforms: &[Transform], matrices: &mut [Matrix4x4]) {
+        for (transform, matrix) in transforms.iter().zip(matrices.iter_mut()) {
+            let Quaternion { w, x, y, z } = transform.rotation;
+            let (p, s) = (transform.position, transform.scale);
+            matrix.m = [
+                [
+                    (1.0 - 2.0 * (y * y + z * z)) * s.x,
+                    2.0 * (x * y + w * z) * s.x,
+                    2.0 * (x * z - w * y) * s.x,
+                    0.0,
+                ],
+                [
+                    2.0 * (x * y - w * z) * s.y,
+                    (1.0 - 2.0 * (x * x + z * z)) * s.y,
+                    2.0 * (y * z + w * x) * s.y,
+                    0.0,
+                ],
+                [
+                    2.0 * (x * z + w * y) * s.z,
+                    2.0 * (y * z - w * x) * s.z,
+                    (1.0 - 2.0 * (x * x + y * y)) * s.z,
+                    0.0,
+                ],
+                [p.x, p.y, p.z, 1.0],
+            ];
+        }
+    }
+}
+
+/// One entity at a time, each in a single SIMD register, with anything that
+/// is the same for the whole slice set up once.
+#[cfg(feature = "simd")]
+pub mod simd {
+    use super::*;
+    use crate::simd::{self as kernels, Columns, Lanes};
+    use wide::f32x4;
+
+    /// Move each transform by its velocity over `dt` seconds.
+    pub fn integrate_positions(transforms: &mut [Transform], velocities: &[Velocity], dt: f32) {
+        let dt = f32x4::splat(dt);
+        for (transform, velocity) in transforms.iter_mut().zip(velocities) {
+            let velocity = f32x4::from([velocity.dx, velocity.dy, velocity.dz, 0.0]);
+            transform.position = Vector3::from_lanes(transform.position.to_lanes() + velocity * dt);
+        }
+    }
+
+    /// Apply `rotation` after each transform's current rotation.
+    pub fn rotate_all(transforms: &mut [Transform], rotation: Quaternion) {
+        let rotation = Columns::from_rotation(rotation);
+        for transform in transforms.iter_mut() {
+            transform.rotation = rotation.rotate(transform.rotation);
+        }
+    }
+
+    /// Transform each point by an affine matrix, as `Matrix4x4::multiply_point3x4`.
+    pub fn transform_points(matrix: &Matrix4x4, points: &mut [Vector3]) {
+        let matrix = Columns::from_matrix(matrix);
+        for point in points.iter_mut() {
+            *point = matrix.transform_point(*point);
+        }
+    }
+
+    /// Write the local-to-world matrix of each transform into `matrices`.
+    pub fn to_matrices(transforms: &[Transform], matrices: &mut [Matrix4x4]) {
+        for (transform, matrix) in transforms.iter().zip(matrices.iter_mut()) {
+            *matrix = kernels::trs(transform.position, transform.rotation, transform.scale);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    fn transforms(count: usize) -> Vec<Transform> {
+        (0..count)
+            .map(|i| {
+                let i = i as f32;
+                Transform {
+                    position: Vector3::new(i, -i, 2.0 * i),
+                    rotation: Quaternion::angle_axis(10.0 * i, Vector3::new(1.0, i, 2.0)),
+                    scale: Vector3::new(1.0 + i, 1.0, 0.5),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn integrate_positions_moves_by_velocity_times_step() {
+        let mut moved = transforms(7);
+        let velocities: Vec<Velocity> =
+            (0..7).map(|i| Velocity::new(i as f32, 1.0, -1.0)).collect();
+        integrate_positions(&mut moved, &velocities, 0.5);
+
+        for (i, (before, after)) in transforms(7).iter().zip(moved.iter()).enumerate() {
+            let expected = before.position + Vector3::new(i as f32, 1.0, -1.0) * 0.5;
+            assert_approx_eq!(after.position, expected);
+        }
+    }
+
+    #[test]
+    fn batch_operations_match_the_math_types() {
+        let rotation = Quaternion::angle_axis(30.0, Vector3::up());
+        let mut rotated = transforms(7);
+        rotate_all(&mut rotated, rotation);
+
+        let mut matrices = vec![Matrix4x4::ZERO; 7];
+        to_matrices(&rotated, &mut matrices);
+
+        let mut points: Vec<Vector3> = rotated.iter().map(|t| t.scale).collect();
+        transform_points(&matrices[3], &mut points);
+
+        for (i, transform) in transforms(7).iter().enumerate() {
+            let expected = rotation * transform.rotation;
+            assert_approx_eq!(rotated[i].rotation, expected);
+            assert_approx_eq!(
+                matrices[i],
+                Matrix4x4::from_trs(transform.position, expected, transform.scale),
+                1e-4
+            );
+            assert_approx_eq!(
+                points[i],
+                matrices[3].multiply_point3x4(rotated[i].scale),
+                1e-4
+            );
+        }
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn simd_paths_match_scalar() {
+        let velocities: Vec<Velocity> = (0..9)
+            .map(|i| Velocity::new(i as f32 * 0.3, -1.0, 2.5))
+            .collect();
+        let rotation = Quaternion::angle_axis(30.0, Vector3::new(1.0, 2.0, 3.0));
+        let mut simd_moved = transforms(9);
+        let mut scalar_moved = transforms(9);
+        simd::integrate_positions(&mut simd_moved, &velocities, 0.016);
+        scalar::integrate_positions(&mut scalar_moved, &velocities, 0.016);
+        simd::rotate_all(&mut simd_moved, rotation);
+        scalar::rotate_all(&mut scalar_moved, rotation);
+
+        let mut simd_matrices = vec![Matrix4x4::ZERO; 9];
+        let mut scalar_matrices = vec![Matrix4x4::ZERO; 9];
+        simd::to_matrices(&simd_moved, &mut simd_matrices);
+        scalar::to_matrices(&scalar_moved, &mut scalar_matrices);
+
+        let mut simd_points: Vec<Vector3> = simd_moved.iter().map(|t| t.scale).collect();
+        let mut scalar_points = simd_points.clone();
+        simd::transform_points(&simd_matrices[4], &mut simd_points);
+        scalar::transform_points(&scalar_matrices[4], &mut scalar_points);
+
+        for i in 0..9 {
+            assert_approx_eq!(simd_moved[i].position, scalar_moved[i].position);
+            assert_approx_eq!(simd_moved[i].rotation, scalar_moved[i].rotation);
+            assert_approx_eq!(simd_matrices[i], scalar_matrices[i], 1e-4);
+            assert_approx_eq!(simd_points[i], scalar_points[i], 1e-4);
+        }
+    }
+
+    #[test]
+    fn mismatched_slices_stop_at_the_shorter_one() {
+        let mut moved = transforms(6);
+        integrate_positions(&mut moved, &[Velocity::new(1.0, 0.0, 0.0); 5], 1.0);
+
+        assert_eq!(moved[4].position, Vector3::new(5.0, -4.0, 8.0));
+        assert_eq!(moved[5].position, transforms(6)[5].position);
+    }
+}
@@ -1,6 +1,9 @@
+use crate::matrix::Matrix4x4;
 use crate::quaternion::Quaternion;
 use crate::vector::Vector3;
 
+/// Position, rotation and scale of an entity, relative to its `Parent` if it
+/// has one.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Transform {
     pub position: Vector3,
@@ -8,9 +11,228 @@ pub struct Transform {
     pub scale: Vector3,
 }
 
+// "Static Properties"
+impl Transform {
+    /// At the origin, unrotated and at unit scale.
+    pub fn identity() -> Transform {
+        Transform {
+            position: Vector3::ZERO,
+            rotation: Quaternion::identity(),
+            scale: Vector3::ONE,
+        }
+    }
+}
+
+// Constructors
+impl Transform {
+    /// An unrotated, unit scale transform at `position`.
+    pub fn from_position(position: Vector3) -> Transform {
+        Transform::identity().with_position(position)
+    }
+
+    /// A transform at the origin rotated by `rotation`.
+    pub fn from_rotation(rotation: Quaternion) -> Transform {
+        Transform::identity().with_rotation(rotation)
+    }
+
+    /// An unrotated transform at the origin scaled by `scale`.
+    pub fn from_scale(scale: Vector3) -> Transform {
+        Transform::identity().with_scale(scale)
+    }
+
+    /// Split a local-to-parent matrix into position, rotation and scale.
+    ///
+    /// Shear, which a parent with a non-uniform scale can introduce, is lost.
+    pub fn from_matrix(matrix: &Matrix4x4) -> Transform {
+        let (position, rotation, scale) = matrix.decompose();
+        Transform {
+            position,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn with_position(mut self, position: Vector3) -> Transform {
+        self.position = position;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: Quaternion) -> Transform {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vector3) -> Transform {
+        self.scale = scale;
+        self
+    }
+}
+
+// "Properties"
+impl Transform {
+    /// The local z axis, in the parent's space.
+    pub fn forward(&self) -> Vector3 {
+        self.rotation * Vector3::FORWARD
+    }
+
+    /// The local x axis, in the parent's space.
+    pub fn right(&self) -> Vector3 {
+        self.rotation * Vector3::RIGHT
+    }
+
+    /// The local y axis, in the parent's space.
+    pub fn up(&self) -> Vector3 {
+        self.rotation * Vector3::UP
+    }
+
+    /// Local-to-parent matrix, applying scale, then rotation, then position.
+    pub fn to_matrix(&self) -> Matrix4x4 {
+        Matrix4x4::from_trs(self.position, self.rotation, self.scale)
+    }
+}
+
+// Public Methods
+impl Transform {
+    /// Move by `translation`, given in the parent's space.
+    pub fn translate(&mut self, translation: Vector3) {
+        self.position += translation;
+    }
+
+    /// Move by `translation`, given along the transform's own axes.
+    pub fn translate_local(&mut self, translation: Vector3) {
+        self.position += self.rotation * translation;
+    }
+
+    /// Apply `rotation` about the parent's axes, after the current rotation.
+    pub fn rotate(&mut self, rotation: Quaternion) {
+        self.rotation = (rotation * self.rotation).normalized();
+    }
+
+    /// Apply `rotation` about the transform's own axes.
+    pub fn rotate_local(&mut self, rotation: Quaternion) {
+        self.rotation = (self.rotation * rotation).normalized();
+    }
+
+    /// Turn so `forward()` points at `target`, keeping `up()` as close to `up`
+    /// as possible. Does nothing if `target` is at `position`.
+    pub fn look_at(&mut self, target: Vector3, up: Vector3) {
+        let direction = target - self.position;
+        if direction != Vector3::ZERO {
+            self.rotation = Quaternion::look_rotation_with_up(direction, up);
+        }
+    }
+
+    /// Transforms a position from local space to the parent's space.
+    pub fn transform_point(&self, point: Vector3) -> Vector3 {
+        self.position + self.rotation * (point * self.scale)
+    }
+
+    /// Transforms a position from the parent's space to local space.
+    ///
+    /// The result is infinite on any axis with a zero scale.
+    pub fn inverse_transform_point(&self, point: Vector3) -> Vector3 {
+        (self.rotation.inverse() * (point - self.position)) / self.scale
+    }
+
+    /// Transforms a direction from local space to the parent's space,
+    /// ignoring position and scale.
+    pub fn transform_direction(&self, direction: Vector3) -> Vector3 {
+        self.rotation * direction
+    }
+
+    /// Transforms a direction from the parent's space to local space,
+    /// ignoring position and scale.
+    pub fn inverse_transform_direction(&self, direction: Vector3) -> Vector3 {
+        self.rotation.inverse() * direction
+    }
+}
+
+// Std Trait Implementations
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Velocity {
     pub(crate) dx: f32,
     pub(crate) dy: f32,
     pub(crate) dz: f32,
 }
+
+impl Velocity {
+    pub fn new(dx: f32, dy: f32, dz: f32) -> Velocity {
+        Velocity { dx, dy, dz }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    #[test]
+    fn default_transform_is_the_identity() {
+        let transform = Transform::default();
+
+        assert_eq!(transform, Transform::identity());
+        assert!(transform.to_matrix().is_identity());
+        assert_eq!(transform.forward(), Vector3::FORWARD);
+    }
+
+    #[test]
+    fn builder_sets_each_part() {
+        let rotation = Quaternion::angle_axis(90.0, Vector3::UP);
+        let transform = Transform::from_position(Vector3::new(1.0, 2.0, 3.0))
+            .with_rotation(rotation)
+            .with_scale(Vector3::new(2.0, 2.0, 2.0));
+
+        assert_eq!(transform.position, Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(transform.rotation, rotation);
+        assert_eq!(transform.scale, Vector3::new(2.0, 2.0, 2.0));
+        assert_approx_eq!(transform.forward(), Vector3::RIGHT);
+        assert_approx_eq!(transform.right(), Vector3::BACK);
+        assert_approx_eq!(transform.up(), Vector3::UP);
+    }
+
+    #[test]
+    fn translate_local_moves_along_the_transforms_own_axes() {
+        let mut transform = Transform::from_rotation(Quaternion::angle_axis(90.0, Vector3::UP));
+        transform.translate_local(Vector3::FORWARD);
+        assert_approx_eq!(transform.position, Vector3::RIGHT);
+
+        transform.translate(Vector3::FORWARD);
+        assert_approx_eq!(transform.position, Vector3::new(1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn look_at_points_forward_at_the_target() {
+        let mut transform = Transform::from_position(Vector3::new(0.0, 0.0, -5.0));
+        transform.look_at(Vector3::new(5.0, 0.0, -5.0), Vector3::UP);
+        assert_approx_eq!(transform.forward(), Vector3::RIGHT);
+
+        let before = transform.rotation;
+        transform.look_at(transform.position, Vector3::UP);
+        assert_eq!(transform.rotation, before);
+    }
+
+    #[test]
+    fn inverse_transform_point_undoes_transform_point() {
+        let transform = Transform::from_position(Vector3::new(1.0, -2.0, 3.0))
+            .with_rotation(Quaternion::angle_axis(30.0, Vector3::new(1.0, 1.0, 0.0)))
+            .with_scale(Vector3::new(2.0, 0.5, 1.0));
+        let point = Vector3::new(4.0, 5.0, 6.0);
+
+        assert_approx_eq!(
+            transform.transform_point(point),
+            transform.to_matrix().multiply_point3x4(point),
+            1e-4
+        );
+        assert_approx_eq!(
+            transform.inverse_transform_point(transform.transform_point(point)),
+            point,
+            1e-4
+        );
+    }
+}
@@ -0,0 +1,367 @@
+// Display backends
+//
+// `DisplayManager` drives the display through a `DisplayBackend`, picked at
+// startup. `WinitBackend` opens a real window, while `HeadlessBackend` needs
+// no display at all, so CI machines and dedicated servers run the game through
+// the same `GameManager` loop. Headless, frames are either drawn into an
+// in-memory `Framebuffer`, lent to the render systems as a resource, or not
+// drawn at all.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use winit::dpi::LogicalSize;
+use winit::error::OsError;
+use winit::event::Event;
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::platform::desktop::EventLoopExtDesktop;
+use winit::window::{Fullscreen, Window, WindowBuilder};
+
+use crate::display_manager::{DisplayConfig, DisplayEvent};
+use crate::input_manager::InputManager;
+
+/// Which `DisplayBackend` `DisplayManager` uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackendKind {
+    /// A winit window. Needs a display to connect to.
+    Winit,
+    /// No window, with frames drawn into a `Framebuffer` if `framebuffer` is
+    /// set and skipped otherwise.
+    Headless { framebuffer: bool },
+}
+
+impl BackendKind {
+    pub fn create(self) -> Box<dyn DisplayBackend> {
+        match self {
+            BackendKind::Winit => Box::new(WinitBackend::new()),
+            BackendKind::Headless { framebuffer } => Box::new(HeadlessBackend::new(framebuffer)),
+        }
+    }
+}
+
+/// Parses the values of `GEARS_DISPLAY`: `winit`, `headless`, or
+/// `framebuffer` for headless with a framebuffer.
+impl FromStr for BackendKind {
+    type Err = DisplayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "winit" => Ok(BackendKind::Winit),
+            "headless" => Ok(BackendKind::Headless { framebuffer: false }),
+            "framebuffer" => Ok(BackendKind::Headless { framebuffer: true }),
+            _ => Err(DisplayError::UnknownBackend(String::from(s))),
+        }
+    }
+}
+
+/// Errors raised while opening a display.
+#[derive(Debug)]
+pub enum DisplayError {
+    /// The name doesn't match any `BackendKind`.
+    UnknownBackend(String),
+    /// The window couldn't be opened.
+    Window(OsError),
+}
+
+impl fmt::Display for DisplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DisplayError::UnknownBackend(name) => write!(f, "Unknown display backend {:?}", name),
+            DisplayError::Window(source) => write!(f, "Couldn't open window: {}", source),
+        }
+    }
+}
+
+impl Error for DisplayError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DisplayError::UnknownBackend(_) => None,
+            DisplayError::Window(source) => Some(source),
+        }
+    }
+}
+
+/// Somewhere for the game to be displayed.
+pub trait DisplayBackend {
+    fn kind(&self) -> BackendKind;
+
+    /// Open the display described by `config`.
+    fn open(&mut self, config: &DisplayConfig) -> Result<(), DisplayError>;
+
+    /// Size of the display in physical pixels, 0 by 0 if it's not open.
+    fn size(&self) -> (u32, u32);
+
+    /// Handle every event waiting, passing input on to `input` and returning
+    /// what happened to the display. Must not block.
+    fn pump_events(&mut self, input: Option<&mut InputManager<'_>>) -> Vec<DisplayEvent>;
+
+    /// Show the frame just drawn.
+    fn present(&mut self) {}
+
+    /// How many times a second the display refreshes, for displays that do.
+    fn refresh_rate(&self) -> Option<u32> {
+        None
+    }
+
+    /// The open window, for backends that have one.
+    fn window(&self) -> Option<&Window> {
+        None
+    }
+
+    /// The in-memory framebuffer, for backends that draw into one.
+    fn framebuffer(&mut self) -> Option<&mut Framebuffer> {
+        None
+    }
+}
+
+/// Displays the game in a winit window.
+#[derive(Default)]
+pub struct WinitBackend {
+    event_loop: Option<EventLoop<()>>,
+    window: Option<Window>,
+}
+
+impl WinitBackend {
+    pub fn new() -> WinitBackend {
+        WinitBackend::default()
+    }
+}
+
+impl DisplayBackend for WinitBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Winit
+    }
+
+    /// Panics if there's no display to connect to, as winit does.
+    fn open(&mut self, config: &DisplayConfig) -> Result<(), DisplayError> {
+        let event_loop = EventLoop::new();
+        let fullscreen = if config.fullscreen {
+            Some(Fullscreen::Borderless(event_loop.primary_monitor()))
+        } else {
+            None
+        };
+        let built = WindowBuilder::new()
+            .with_title(config.title.clone())
+            .with_inner_size(LogicalSize::new(config.width, config.height))
+            .with_resizable(config.resizable)
+            .with_fullscreen(fullscreen)
+            .build(&event_loop);
+        // Keep the event loop either way, so events can still be pumped
+        self.event_loop = Some(event_loop);
+        self.window = Some(built.map_err(DisplayError::Window)?);
+        Ok(())
+    }
+
+    fn size(&self) -> (u32, u32) {
+        self.window
+            .as_ref()
+            .map(|w| {
+                let size = w.inner_size();
+                (size.width, size.height)
+            })
+            .unwrap_or((0, 0))
+    }
+
+    fn pump_events(&mut self, mut input: Option<&mut InputManager<'_>>) -> Vec<DisplayEvent> {
+        let mut events = Vec::new();
+        let event_loop = match self.event_loop.as_mut() {
+            Some(event_loop) => event_loop,
+            None => return events,
+        };
+        event_loop.run_return(|event, _, control_flow| match event {
+            Event::WindowEvent { event, .. } => {
+                if let Some(input) = input.as_mut() {
+                    input.handle_window_event(&event);
+                }
+                events.extend(DisplayEvent::from_window_event(&event));
+            }
+            // Every event waiting has been handled, so hand control back
+            Event::MainEventsCleared => *control_flow = ControlFlow::Exit,
+            _ => {}
+        });
+        events
+    }
+
+    /// The fastest rate the monitor showing the window runs at its current
+    /// size, since winit doesn't say which of its video modes is in use.
+    fn refresh_rate(&self) -> Option<u32> {
+        let monitor = self.window.as_ref()?.current_monitor();
+        monitor
+            .video_modes()
+            .filter(|mode| mode.size() == monitor.size())
+            .map(|mode| u32::from(mode.refresh_rate()))
+            .filter(|&hz| hz > 0)
+            .max()
+    }
+
+    fn window(&self) -> Option<&Window> {
+        self.window.as_ref()
+    }
+}
+
+/// Displays nothing, and so never has any events.
+#[derive(Debug, Default)]
+pub struct HeadlessBackend {
+    size: (u32, u32),
+    framebuffer: Option<Framebuffer>,
+    keep_framebuffer: bool,
+}
+
+impl HeadlessBackend {
+    /// A backend drawing into a `Framebuffer` if `framebuffer` is set.
+    pub fn new(framebuffer: bool) -> HeadlessBackend {
+        HeadlessBackend {
+            keep_framebuffer: framebuffer,
+            ..HeadlessBackend::default()
+        }
+    }
+}
+
+impl DisplayBackend for HeadlessBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Headless {
+            framebuffer: self.keep_framebuffer,
+        }
+    }
+
+    fn open(&mut self, config: &DisplayConfig) -> Result<(), DisplayError> {
+        self.size = (config.width, config.height);
+        if self.keep_framebuffer {
+            self.framebuffer = Some(Framebuffer::new(config.width, config.height));
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    fn pump_events(&mut self, _input: Option<&mut InputManager<'_>>) -> Vec<DisplayEvent> {
+        Vec::new()
+    }
+
+    fn framebuffer(&mut self) -> Option<&mut Framebuffer> {
+        self.framebuffer.as_mut()
+    }
+}
+
+/// Pixels held in memory, as 0xAARRGGBB, row by row from the top left.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<u32>,
+}
+
+impl Framebuffer {
+    /// A `width` by `height` framebuffer, cleared to 0.
+    pub fn new(width: u32, height: u32) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [u32] {
+        &mut self.pixels
+    }
+
+    /// The pixel at `x`, `y`, or `None` outside the framebuffer.
+    pub fn get(&self, x: u32, y: u32) -> Option<u32> {
+        self.index(x, y).map(|i| self.pixels[i])
+    }
+
+    /// Set the pixel at `x`, `y`, ignoring pixels outside the framebuffer.
+    pub fn set(&mut self, x: u32, y: u32, color: u32) {
+        if let Some(i) = self.index(x, y) {
+            self.pixels[i] = color;
+        }
+    }
+
+    pub fn clear(&mut self, color: u32) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = color;
+        }
+    }
+
+    /// Change the size of the framebuffer, clearing it to 0.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        *self = Framebuffer::new(width, height);
+    }
+
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(y as usize * self.width as usize + x as usize)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backend_names_parse() {
+        assert_eq!("winit".parse::<BackendKind>().unwrap(), BackendKind::Winit);
+        assert_eq!(
+            " Headless\n".parse::<BackendKind>().unwrap(),
+            BackendKind::Headless { framebuffer: false }
+        );
+        assert_eq!(
+            "framebuffer".parse::<BackendKind>().unwrap(),
+            BackendKind::Headless { framebuffer: true }
+        );
+        assert!(matches!(
+            "vulkan".parse::<BackendKind>(),
+            Err(DisplayError::UnknownBackend(_))
+        ));
+    }
+
+    #[test]
+    fn headless_backends_draw_into_a_framebuffer() {
+        let config = DisplayConfig {
+            width: 4,
+            height: 2,
+            ..DisplayConfig::default()
+        };
+        let mut backend = HeadlessBackend::new(true);
+        backend.open(&config).unwrap();
+        let framebuffer = backend.framebuffer().unwrap();
+        framebuffer.set(3, 1, 0xff00_ff00);
+        framebuffer.set(4, 1, 0xffff_ffff);
+
+        assert_eq!(framebuffer.get(3, 1), Some(0xff00_ff00));
+        assert_eq!(framebuffer.get(4, 1), None);
+        assert_eq!(framebuffer.pixels().iter().filter(|&&p| p != 0).count(), 1);
+        framebuffer.resize(8, 8);
+        assert_eq!(framebuffer.pixels(), &[0; 64][..]);
+        assert_eq!(backend.size(), (4, 2));
+        assert!(backend.pump_events(None).is_empty());
+    }
+
+    #[test]
+    fn headless_backends_can_skip_the_framebuffer() {
+        let mut backend = HeadlessBackend::new(false);
+        backend.open(&DisplayConfig::default()).unwrap();
+
+        assert_eq!(backend.size(), (1280, 720));
+        assert!(backend.framebuffer().is_none());
+        assert!(backend.window().is_none());
+    }
+}
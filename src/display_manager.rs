@@ -1,11 +1,95 @@
-use super::log_manager::LogManager;
+// Display
+//
+// `DisplayManager` owns the game's display, through the `DisplayBackend`
+// picked at startup: a winit window, or a headless display for machines
+// without one. Rather than handing control to `EventLoop::run`, which never
+// returns, the game loop pumps the backend once a frame: window events are
+// turned into `DisplayEvent`s for the engine, and input is passed on to the
+// `InputManager`.
+
+use std::env;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+use super::log_manager::{LogManager, LogRecord};
 use super::manager::Manager;
+use crate::display_backend::{BackendKind, DisplayBackend, Framebuffer};
+use crate::input_manager::InputManager;
+
+/// Environment variable picking the backend when `DisplayConfig::backend`
+/// isn't set, e.g. `GEARS_DISPLAY=headless` on CI. See `BackendKind` for the
+/// values.
+pub const DISPLAY_ENV: &str = "GEARS_DISPLAY";
+
+/// How the window is opened.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisplayConfig {
+    pub title: String,
+    /// Size of the window's contents, in logical pixels.
+    pub width: u32,
+    pub height: u32,
+    /// Cover the primary monitor instead, without changing its video mode.
+    pub fullscreen: bool,
+    /// Wait for the display to refresh before presenting a frame, so frames
+    /// come no faster than its refresh rate. Headless displays never wait.
+    pub vsync: bool,
+    pub resizable: bool,
+    /// The backend to display with. Left unset, `GEARS_DISPLAY` picks it,
+    /// falling back to winit.
+    pub backend: Option<BackendKind>,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        DisplayConfig {
+            title: String::from("gears"),
+            width: 1280,
+            height: 720,
+            fullscreen: false,
+            vsync: true,
+            resizable: true,
+            backend: None,
+        }
+    }
+}
+
+/// Something that happened to the window, sent as an event by `GameManager`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisplayEvent {
+    /// The window's contents are now `width` by `height` physical pixels.
+    Resized { width: u32, height: u32 },
+    /// The window gained or lost keyboard focus.
+    Focused(bool),
+    /// The user asked to close the window. `GameManager` stops the game loop.
+    CloseRequested,
+}
+
+impl DisplayEvent {
+    pub(crate) fn from_window_event(event: &WindowEvent) -> Option<DisplayEvent> {
+        match event {
+            WindowEvent::Resized(size) => Some(DisplayEvent::Resized {
+                width: size.width,
+                height: size.height,
+            }),
+            WindowEvent::Focused(focused) => Some(DisplayEvent::Focused(*focused)),
+            WindowEvent::CloseRequested => Some(DisplayEvent::CloseRequested),
+            _ => None,
+        }
+    }
+}
 
 pub struct DisplayManager<'a> {
     started: bool,
-    max_x: i8,
-    max_y: i8,
+    config: DisplayConfig,
     logger: &'a LogManager,
+    backend: Option<Box<dyn DisplayBackend>>,
+    injected: Vec<DisplayEvent>,
+    frames: u64,
+    last_present: Option<Instant>,
 }
 
 impl Manager for DisplayManager<'_> {
@@ -15,24 +99,270 @@ impl Manager for DisplayManager<'_> {
 }
 
 impl DisplayManager<'_> {
-    pub fn new(log_manager: &LogManager) -> DisplayManager {
+    pub fn new(log_manager: &LogManager) -> DisplayManager<'_> {
+        DisplayManager::with_config(log_manager, DisplayConfig::default())
+    }
+    pub fn with_config(log_manager: &LogManager, config: DisplayConfig) -> DisplayManager<'_> {
         DisplayManager {
             started: false,
-            max_x: 8,
-            max_y: 8,
-            logger: &log_manager,
+            config,
+            logger: log_manager,
+            backend: None,
+            injected: Vec::new(),
+            frames: 0,
+            last_present: None,
         }
     }
     pub fn startup(&mut self) {
-        self.logger
-            .info(String::from("DisplayManager.startup(): Current window set"));
-        self.logger.info(format!(
-            "DisplayManager.startup(): max X is {}, max Y is {}",
-            self.max_x, self.max_y
-        ));
+        self.open(self.backend_kind());
         self.started = true
     }
     pub fn shutdown(mut self) {
+        self.backend = None;
         self.started = false
     }
+
+    /// Open the display with a `kind` of backend.
+    fn open(&mut self, kind: BackendKind) {
+        let mut backend = kind.create();
+        match backend.open(&self.config) {
+            Ok(()) => {
+                let (width, height) = backend.size();
+                self.logger.log(
+                    LogRecord::info("DisplayManager.startup(): Current window set")
+                        .tag(self.m_type())
+                        .field("backend", format!("{:?}", kind))
+                        .field("width", width)
+                        .field("height", height),
+                );
+            }
+            Err(e) => self.logger.log(
+                LogRecord::error(format!("DisplayManager.startup(): {}", e)).tag(self.m_type()),
+            ),
+        }
+        self.backend = Some(backend);
+    }
+
+    /// The configured backend, or else the one named by `GEARS_DISPLAY`.
+    fn backend_kind(&self) -> BackendKind {
+        self.backend_kind_from(env::var(DISPLAY_ENV).ok().as_deref())
+    }
+
+    /// The configured backend, or else the one named by `env`, the value of
+    /// `GEARS_DISPLAY`.
+    fn backend_kind_from(&self, env: Option<&str>) -> BackendKind {
+        if let Some(kind) = self.config.backend {
+            return kind;
+        }
+        let name = match env {
+            Some(name) => name,
+            None => return BackendKind::Winit,
+        };
+        match name.parse() {
+            Ok(kind) => kind,
+            Err(e) => {
+                self.logger.log(
+                    LogRecord::warn(format!("DisplayManager.startup(): {}", e))
+                        .tag(self.m_type())
+                        .field("variable", DISPLAY_ENV),
+                );
+                BackendKind::Winit
+            }
+        }
+    }
+
+    pub fn config(&self) -> &DisplayConfig {
+        &self.config
+    }
+
+    /// The kind of backend in use, once started.
+    pub fn backend(&self) -> Option<BackendKind> {
+        self.backend.as_ref().map(|b| b.kind())
+    }
+
+    /// The open window, if the backend has one.
+    pub fn window(&self) -> Option<&Window> {
+        self.backend.as_ref().and_then(|b| b.window())
+    }
+
+    /// The in-memory framebuffer, if the backend draws into one.
+    pub fn framebuffer(&mut self) -> Option<&mut Framebuffer> {
+        self.backend.as_mut().and_then(|b| b.framebuffer())
+    }
+
+    /// Size of the display in physical pixels, 0 by 0 if it's not open.
+    pub fn size(&self) -> (u32, u32) {
+        self.backend.as_ref().map(|b| b.size()).unwrap_or((0, 0))
+    }
+
+    /// Time between refreshes of the display, if `vsync` is on and the
+    /// display refreshes at all.
+    pub fn frame_interval(&self) -> Option<Duration> {
+        if !self.config.vsync {
+            return None;
+        }
+        self.backend
+            .as_ref()
+            .and_then(|b| b.refresh_rate())
+            .map(|hz| Duration::from_secs(1) / hz)
+    }
+
+    /// Number of frames presented so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Queue `event` as if it came from the display, e.g. to close a headless
+    /// display from a test.
+    pub fn inject(&mut self, event: DisplayEvent) {
+        self.injected.push(event);
+    }
+
+    /// Handle every event waiting, passing input on to `input` and returning
+    /// what happened to the display, followed by any injected events. Never
+    /// blocks.
+    pub fn pump_events(&mut self, input: Option<&mut InputManager<'_>>) -> Vec<DisplayEvent> {
+        let mut events = match self.backend.as_mut() {
+            Some(backend) => backend.pump_events(input),
+            None => Vec::new(),
+        };
+        events.append(&mut self.injected);
+        events
+    }
+
+    /// Show the frame just drawn, first waiting for the next refresh of the
+    /// display if `vsync` is on.
+    pub fn present(&mut self) {
+        if let (Some(interval), Some(last)) = (self.frame_interval(), self.last_present) {
+            let elapsed = last.elapsed();
+            if elapsed < interval {
+                thread::sleep(interval - elapsed);
+            }
+        }
+        if let Some(backend) = self.backend.as_mut() {
+            backend.present();
+            self.frames += 1;
+            self.last_present = Some(Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display_backend::DisplayError;
+    use winit::dpi::PhysicalSize;
+
+    #[test]
+    fn headless_displays_pump_injected_events() {
+        let log_manager = LogManager::new();
+        let mut display = DisplayManager::new(&log_manager);
+        assert_eq!(display.size(), (0, 0));
+        display.present();
+        assert_eq!(display.frames(), 0);
+
+        display.open(BackendKind::Headless { framebuffer: true });
+        display.inject(DisplayEvent::CloseRequested);
+        display.present();
+
+        assert_eq!(display.size(), (1280, 720));
+        assert_eq!(display.framebuffer().map(|f| f.width()), Some(1280));
+        assert!(display.window().is_none());
+        assert_eq!(display.frames(), 1);
+        assert_eq!(
+            display.pump_events(None),
+            vec![DisplayEvent::CloseRequested]
+        );
+        assert!(display.pump_events(None).is_empty());
+    }
+
+    /// A display refreshing the given number of times a second.
+    struct Refreshing(u32);
+
+    impl DisplayBackend for Refreshing {
+        fn kind(&self) -> BackendKind {
+            BackendKind::Winit
+        }
+
+        fn open(&mut self, _config: &DisplayConfig) -> Result<(), DisplayError> {
+            Ok(())
+        }
+
+        fn size(&self) -> (u32, u32) {
+            (0, 0)
+        }
+
+        fn pump_events(&mut self, _input: Option<&mut InputManager<'_>>) -> Vec<DisplayEvent> {
+            Vec::new()
+        }
+
+        fn refresh_rate(&self) -> Option<u32> {
+            Some(self.0)
+        }
+    }
+
+    #[test]
+    fn vsync_waits_for_the_display_to_refresh() {
+        let log_manager = LogManager::new();
+        let mut display = DisplayManager::new(&log_manager);
+        display.backend = Some(Box::new(Refreshing(50)));
+        assert_eq!(display.frame_interval(), Some(Duration::from_millis(20)));
+
+        let start = Instant::now();
+        for _ in 0..3 {
+            display.present();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(40));
+
+        display.config.vsync = false;
+        assert_eq!(display.frame_interval(), None);
+        display.open(BackendKind::Headless { framebuffer: false });
+        display.config.vsync = true;
+        assert_eq!(display.frame_interval(), None);
+    }
+
+    #[test]
+    fn configured_backends_win_over_the_environment() {
+        let log_manager = LogManager::new();
+        let unset = DisplayManager::new(&log_manager);
+        let config = DisplayConfig {
+            backend: Some(BackendKind::Winit),
+            ..DisplayConfig::default()
+        };
+        let configured = DisplayManager::with_config(&log_manager, config);
+
+        assert_eq!(
+            unset.backend_kind_from(Some("framebuffer")),
+            BackendKind::Headless { framebuffer: true }
+        );
+        assert_eq!(
+            configured.backend_kind_from(Some("framebuffer")),
+            BackendKind::Winit
+        );
+        assert_eq!(unset.backend_kind_from(None), BackendKind::Winit);
+        assert_eq!(unset.backend_kind_from(Some("vulkan")), BackendKind::Winit);
+    }
+
+    #[test]
+    fn window_events_become_display_events() {
+        assert_eq!(
+            DisplayEvent::from_window_event(&WindowEvent::Resized(PhysicalSize::new(800, 600))),
+            Some(DisplayEvent::Resized {
+                width: 800,
+                height: 600
+            })
+        );
+        assert_eq!(
+            DisplayEvent::from_window_event(&WindowEvent::Focused(false)),
+            Some(DisplayEvent::Focused(false))
+        );
+        assert_eq!(
+            DisplayEvent::from_window_event(&WindowEvent::CloseRequested),
+            Some(DisplayEvent::CloseRequested)
+        );
+        assert_eq!(
+            DisplayEvent::from_window_event(&WindowEvent::Destroyed),
+            None
+        );
+    }
 }
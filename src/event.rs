@@ -0,0 +1,222 @@
+// Events
+//
+// Systems tell each other what happened by sending events through an
+// `Events<T>` resource, rather than by mutating components the other side has
+// to poll. Events are double buffered: `update` runs at the start of every
+// simulation tick and drops whatever was sent two updates ago, so an event
+// stays readable for a full tick whichever side of the sender a system runs.
+// Each `EventReader` keeps its own cursor, so any number of systems can read
+// the same events without taking them from each other.
+
+use std::marker::PhantomData;
+
+use legion::systems::{ParallelRunnable, Resource, SystemBuilder};
+
+/// Resource holding the events of type `T` sent this update and the last.
+///
+/// Register an event type with `GameManager::add_event`, which also clears it
+/// each tick. Systems in `Render` run once per frame, so they can miss events
+/// if the simulation runs more than one tick in a frame.
+#[derive(Debug)]
+pub struct Events<T> {
+    previous: Vec<T>,
+    current: Vec<T>,
+    /// Number of events sent before the first one in `previous`.
+    previous_start: usize,
+    /// Number of events sent before the first one in `current`.
+    current_start: usize,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Events {
+            previous: Vec::new(),
+            current: Vec::new(),
+            previous_start: 0,
+            current_start: 0,
+        }
+    }
+}
+
+impl<T> Events<T> {
+    pub fn new() -> Events<T> {
+        Events::default()
+    }
+
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    /// A reader that only sees events sent from now on.
+    pub fn reader(&self) -> EventReader<T> {
+        EventReader {
+            last: self.count(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Number of events still held.
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop the events sent before the last update.
+    pub fn update(&mut self) {
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+        self.previous_start = self.current_start;
+        self.current_start = self.previous_start + self.previous.len();
+    }
+
+    /// Drop every event held, whether readers have seen it or not.
+    pub fn clear(&mut self) {
+        self.current_start = self.count();
+        self.previous_start = self.current_start;
+        self.previous.clear();
+        self.current.clear();
+    }
+
+    /// Number of events sent since the resource was created.
+    fn count(&self) -> usize {
+        self.current_start + self.current.len()
+    }
+}
+
+impl<T: Resource + Send + Sync> Events<T> {
+    /// A system calling `update` on the `Events<T>` resource.
+    pub fn update_system() -> impl ParallelRunnable {
+        SystemBuilder::new(std::any::type_name::<Events<T>>())
+            .write_resource::<Events<T>>()
+            .build(|_, _, events, _| events.update())
+    }
+}
+
+/// Cursor into an `Events<T>`, remembering which events it has read.
+///
+/// Give each system its own reader as `#[state]`:
+///
+/// ```ignore
+/// #[system]
+/// fn score(#[state] hits: &mut EventReader<CollisionStarted>, #[resource] events: &Events<CollisionStarted>) {
+///     for hit in hits.iter(events) { ... }
+/// }
+///
+/// game_manager.add_system(Stage::Update, score_system(EventReader::new()));
+/// ```
+#[derive(Debug)]
+pub struct EventReader<T> {
+    last: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for EventReader<T> {
+    fn default() -> Self {
+        EventReader {
+            last: 0,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for EventReader<T> {
+    fn clone(&self) -> Self {
+        EventReader {
+            last: self.last,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> EventReader<T> {
+    /// A reader that starts with the oldest events still held.
+    pub fn new() -> EventReader<T> {
+        EventReader::default()
+    }
+
+    /// The events this reader hasn't seen yet, oldest first.
+    pub fn iter<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> + 'a {
+        let last = self.last;
+        self.last = events.count();
+        let previous = events
+            .previous
+            .iter()
+            .skip(last.saturating_sub(events.previous_start));
+        let current = events
+            .current
+            .iter()
+            .skip(last.saturating_sub(events.current_start));
+        previous.chain(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use legion::{Resources, Schedule, World};
+
+    #[test]
+    fn readers_see_each_event_once() {
+        let mut events = Events::new();
+        let mut first = EventReader::new();
+        let mut second = EventReader::new();
+        events.send(1);
+        events.send(2);
+
+        assert_eq!(first.iter(&events).collect::<Vec<_>>(), vec![&1, &2]);
+        events.send(3);
+        assert_eq!(first.iter(&events).collect::<Vec<_>>(), vec![&3]);
+        assert_eq!(second.iter(&events).count(), 3);
+        assert_eq!(first.iter(&events).count(), 0);
+    }
+
+    #[test]
+    fn events_are_kept_for_two_updates() {
+        let mut events = Events::new();
+        let mut reader = EventReader::new();
+        events.send("hit");
+
+        events.update();
+        assert_eq!(events.len(), 1);
+        events.send("miss");
+        events.update();
+        assert_eq!(reader.iter(&events).collect::<Vec<_>>(), vec![&"miss"]);
+        events.update();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn readers_from_events_skip_earlier_ones() {
+        let mut events = Events::new();
+        events.send(1);
+        events.update();
+        events.send(2);
+
+        let mut reader = events.reader();
+        events.send(3);
+
+        assert_eq!(reader.iter(&events).collect::<Vec<_>>(), vec![&3]);
+        events.clear();
+        events.send(4);
+        assert_eq!(reader.iter(&events).collect::<Vec<_>>(), vec![&4]);
+    }
+
+    #[test]
+    fn update_system_drops_old_events() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Events::<u32>::new());
+        let mut schedule = Schedule::builder()
+            .add_system(Events::<u32>::update_system())
+            .build();
+
+        resources.get_mut::<Events<u32>>().unwrap().send(7);
+        schedule.execute(&mut world, &mut resources);
+        assert_eq!(resources.get::<Events<u32>>().unwrap().len(), 1);
+        schedule.execute(&mut world, &mut resources);
+        assert!(resources.get::<Events<u32>>().unwrap().is_empty());
+    }
+}
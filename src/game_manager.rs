@@ -1,50 +1,70 @@
+use std::collections::HashMap;
 use std::thread::sleep;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
-use super::log_manager::LogManager;
+use super::log_manager::{LogManager, LogRecord};
 use super::manager::Manager;
-use super::world::World;
+use crate::action::ActionState;
+use crate::batch;
 use crate::component::{Transform, Velocity};
-use crate::quaternion::Quaternion;
-use crate::vector::Vector3;
+use crate::display_backend::Framebuffer;
+use crate::display_manager::{DisplayEvent, DisplayManager};
+use crate::event::Events;
+use crate::game_state::{GameControl, GameState, GameStateChanged, TransitionError};
+use crate::hierarchy;
+use crate::input_manager::{Input, InputEvent, InputManager};
+use crate::physics::collision::{
+    self, CollisionEnded, CollisionOngoing, CollisionStarted, Collisions,
+};
+use crate::physics::query::SpatialQuery;
+use crate::physics::{self, Gravity, PhysicsConfig, RigidBody};
+use crate::random::Random;
+use crate::replay::{self, Recording, Replay, ReplayError, ReplayMode};
+use crate::stage::{ScheduleBuilder, ScheduleError, Stage, SystemDescriptor};
+use crate::time::{FixedTime, Time};
+use legion::systems::{Builder, ParallelRunnable, Resource, Runnable};
+use legion::world::SubWorld;
 use legion::*;
 
-enum GameState {
-    PreStart = 0,
-    Running = 1,
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum StateHook {
+    Enter,
+    Exit,
 }
 
-pub struct Time {
-    _delta: Instant,
-}
-
-impl Time {
-    pub fn delta(&mut self) -> Duration {
-        let elapsed = self._delta.elapsed();
-        self._delta = Instant::now();
-        return elapsed;
-    }
-
-    pub fn split(&self) -> Duration {
-        self._delta.elapsed()
+/// Move entities by their velocity, leaving rigid bodies to `integrate_bodies`.
+#[system]
+#[write_component(Transform)]
+#[read_component(Velocity)]
+fn update_positions(world: &mut SubWorld, #[resource] time: &FixedTime) {
+    let mut query = <(&mut Transform, &Velocity)>::query().filter(!component::<RigidBody>());
+    // Whole chunks at a time, handing the component slices to the batch operations
+    for chunk in query.iter_chunks_mut(world) {
+        let (transforms, velocities) = chunk.into_components();
+        batch::integrate_positions(transforms, velocities, time.step_secs());
     }
 }
 
-#[system(for_each)]
-fn update_positions(transform: &mut Transform, velocity: &Velocity, #[resource] time: &Time) {
-    let split = time.split().as_secs_f32();
-    transform.position.x += velocity.dx * split;
-    transform.position.y += velocity.dy * split;
-    transform.position.z += velocity.dz * split;
-}
-
 pub struct GameManager<'a> {
     started: bool,
     logger: &'a LogManager,
     state: GameState,
-    schedule: Schedule,
+    schedule_builder: ScheduleBuilder,
+    event_updates: Builder,
+    events: Schedule,
+    simulation: Schedule,
+    render: Schedule,
+    state_hooks: HashMap<(GameState, StateHook), Builder>,
+    state_schedules: HashMap<(GameState, StateHook), Schedule>,
     resources: Resources,
+    display: Option<DisplayManager<'a>>,
+    input: Option<InputManager<'a>>,
+    replay: Replay,
+    /// Seed of the `Random` resource, random unless set before `startup()`.
+    pub seed: u64,
     pub target_time: Duration,
+    pub fixed_step: Duration,
+    pub max_catch_up_steps: u32,
     pub world: &'a mut World,
 }
 
@@ -56,102 +76,578 @@ impl Manager for GameManager<'_> {
 
 impl GameManager<'_> {
     pub fn new<'a>(log_manager: &'a LogManager, world: &'a mut World) -> GameManager<'a> {
-        GameManager {
+        let mut game_manager = GameManager {
             started: false,
-            logger: &log_manager,
-            state: GameState::PreStart,
-            schedule: Schedule::builder()
-                .add_system(update_positions_system())
-                .build(),
+            logger: log_manager,
+            state: GameState::Loading,
+            schedule_builder: ScheduleBuilder::new(),
+            event_updates: Schedule::builder(),
+            events: Schedule::builder().build(),
+            simulation: Schedule::builder().build(),
+            render: Schedule::builder().build(),
+            state_hooks: HashMap::new(),
+            state_schedules: HashMap::new(),
             resources: Resources::default(),
+            display: None,
+            input: None,
+            replay: Replay::Off,
+            seed: rand::random(),
             target_time: Duration::new(0, 16666666_u32),
+            fixed_step: Duration::new(0, 16666666_u32),
+            max_catch_up_steps: 5,
             world,
-        }
+        };
+        game_manager.add_system_with(
+            Stage::Update,
+            SystemDescriptor::new(update_positions_system()).label("update_positions"),
+        );
+        game_manager.add_system_with(
+            Stage::Update,
+            SystemDescriptor::new(physics::integrate_bodies_system()).label("integrate_bodies"),
+        );
+        game_manager.add_system_with(
+            Stage::PostUpdate,
+            SystemDescriptor::new(collision::detect_collisions_system())
+                .label("detect_collisions")
+                .before("propagate_transforms"),
+        );
+        game_manager.add_system_with(
+            Stage::PostUpdate,
+            SystemDescriptor::new(hierarchy::propagate_transforms_system())
+                .label("propagate_transforms"),
+        );
+        game_manager
+            .add_event::<GameStateChanged>()
+            .add_event::<DisplayEvent>()
+            .add_event::<InputEvent>()
+            .add_event::<CollisionStarted>()
+            .add_event::<CollisionOngoing>()
+            .add_event::<CollisionEnded>();
+        // Present from the start, so requests made before startup() aren't lost
+        let mut control = GameControl::default();
+        control.set_state(game_manager.state);
+        game_manager.resources.insert(control);
+        game_manager
     }
-    pub fn startup(&mut self) {
+    /// Start the game, scheduling every system added so far. Fails if the
+    /// ordering constraints of a stage can't be satisfied, since the game
+    /// would run without that stage's systems.
+    pub fn startup(&mut self) -> Result<(), ScheduleError> {
+        self.simulation = self.build_schedule(&Stage::SIMULATION)?;
+        self.render = self.build_schedule(&[Stage::Render])?;
         self.logger
-            .info(String::from("GameManager.startup(): Game started"));
+            .log(LogRecord::info("GameManager.startup(): Game started").tag(self.m_type()));
         self.started = true;
-        let entity: Entity = self.world.push((
-            Transform {
-                position: Vector3 {
-                    x: 0.0,
-                    y: 0.0,
-                    z: 0.0,
-                },
-                rotation: Quaternion {
-                    x: 0.0,
-                    y: 0.0,
-                    z: 0.0,
-                    w: 0.0,
-                },
-                scale: Vector3 {
-                    x: 0.0,
-                    y: 0.0,
-                    z: 0.0,
-                },
-            },
-            Velocity {
-                dx: 1.0,
-                dy: 1.0,
-                dz: 1.0,
-            },
-        ));
+        self.resources.insert(Time::new());
+        if !self.resources.contains::<Gravity>() {
+            self.resources.insert(Gravity::default());
+        }
+        if !self.resources.contains::<PhysicsConfig>() {
+            self.resources.insert(PhysicsConfig::default());
+        }
+        self.resources.insert(Collisions::default());
+        self.resources.insert(Input::default());
+        self.resources.insert(ActionState::default());
+        self.resources.insert(Random::new(self.seed));
+        if let Replay::Recording { recording, .. } = &mut self.replay {
+            *recording = Recording::new(self.seed, self.fixed_step, self.max_catch_up_steps);
+        }
+        self.resources.insert(SpatialQuery::default());
+        self.resources
+            .insert(FixedTime::new(self.fixed_step, self.max_catch_up_steps));
 
-        self.resources.insert(Time {
-            _delta: Instant::now(),
-        });
+        self.events = self.event_updates.build();
+        for (key, mut builder) in self.state_hooks.drain() {
+            self.state_schedules.insert(key, builder.build());
+        }
+
+        self.run_state_hook(self.state, StateHook::Enter);
+        Ok(())
     }
     pub fn shutdown(mut self) {
+        if let Some(input) = self.input.take() {
+            input.shutdown();
+        }
+        if let Some(display) = self.display.take() {
+            display.shutdown();
+        }
         self.started = false
     }
     pub fn run(&mut self) {
-        self.state = GameState::Running;
+        if let Err(e) = self.transition(GameState::Running) {
+            self.logger
+                .log(LogRecord::error(format!("GameManager.run(): {}", e)).tag(self.m_type()));
+            return;
+        }
 
-        let mut e = 0;
         loop {
-            e += 1;
-            match self.state {
-                GameState::Running => {
-                    // self.logger.info(String::from("Running loop"));
-                    // Reset DeltaTime
-                    self.resources.get_mut::<Time>().map(|mut d| d.delta());
-
-                    // Get input // e.g., keyboard/mouse
-
-                    // Update game world state
-                    self.schedule.execute(&mut self.world, &mut self.resources);
-
-                    let mut query = <&Transform>::query();
-
-                    // you can then iterate through the components found in the world
-                    for position in query.iter(self.world) {
-                        println!("{:?}", position);
-                    }
-
-                    // Draw current scene to back buffer
-                    // self.logger.debug(String::from("Draw current scene to back buffer"));
-
-                    // Swap back buffer to current buffer
-                    // self.logger.debug(String::from("Swap back buffer to current buffer"));
-
-                    // Measure loop_time // i.e., how long above steps took
-                    let loop_time = self
-                        .resources
-                        .get::<Time>()
-                        .map(|d| d.split())
-                        .unwrap_or(Duration::new(0, 0));
-                    // Sleep for (target_time - loop_time)
-                    sleep(self.target_time - loop_time);
-
-                    if e == 60 {
-                        self.state = GameState::PreStart;
-                    }
+            // Reset DeltaTime
+            let frame_time = self
+                .resources
+                .get_mut::<Time>()
+                .map(|mut d| d.delta())
+                .unwrap_or(Duration::new(0, 0));
+
+            // Handle window events, passing keyboard and mouse on to input
+            let pumped = self.pump_display();
+
+            // Get input // e.g., keyboard/mouse, or the next frame of a replay
+            let polled = self.input.as_mut().map(|i| i.poll()).unwrap_or_default();
+            let (frame_time, display, input) = match self.replay.frame(frame_time, pumped, polled) {
+                Some(frame) => frame,
+                None => {
+                    self.logger.log(
+                        LogRecord::info("GameManager.run(): Replay finished").tag(self.m_type()),
+                    );
+                    self.request_stop();
+                    (Duration::new(0, 0), Vec::new(), Vec::new())
                 }
-                _ => {
-                    break;
+            };
+            self.process_display(display);
+            self.process_input(input);
+
+            // Update game world state in fixed steps, the world is frozen while paused
+            if self.state == GameState::Running {
+                self.step_simulation(frame_time);
+            } else {
+                // Events still rotate once a frame, so they don't pile up until it resumes
+                self.events.execute(self.world, &mut self.resources);
+            }
+            self.check_replay();
+
+            // Draw current scene to back buffer, interpolating by FixedTime::alpha()
+            self.render_frame();
+
+            // Swap back buffer to current buffer
+            if let Some(display) = self.display.as_mut() {
+                display.present();
+            }
+
+            // Nothing ticks while the world is frozen, so changes of input
+            // last the frame
+            if self.state != GameState::Running {
+                self.clear_input_changes();
+            }
+
+            // Apply any state change requested during the frame
+            let requested = self
+                .resources
+                .get_mut::<GameControl>()
+                .and_then(|mut c| c.take_request());
+            if let Some(next) = requested {
+                if let Err(e) = self.transition(next) {
+                    self.logger.log(
+                        LogRecord::warn(format!("GameManager.run(): {}", e)).tag(self.m_type()),
+                    );
+                }
+            }
+            if self.state == GameState::Stopping {
+                if let Err(e) = self.transition(GameState::Stopped) {
+                    self.logger.log(
+                        LogRecord::error(format!("GameManager.run(): {}", e)).tag(self.m_type()),
+                    );
+                }
+                break;
+            }
+
+            // Measure loop_time // i.e., how long above steps took
+            let loop_time = self
+                .resources
+                .get::<Time>()
+                .map(|d| d.split())
+                .unwrap_or(Duration::new(0, 0));
+            // Sleep for (target_time - loop_time)
+            match self.target_time.checked_sub(loop_time) {
+                Some(remaining) => sleep(remaining),
+                None => self.logger.log(
+                    LogRecord::warn("GameManager.run(): Frame over budget")
+                        .tag(self.m_type())
+                        .field("loop_ms", loop_time.as_millis())
+                        .field("target_ms", self.target_time.as_millis()),
+                ),
+            }
+        }
+        self.finish_replay();
+    }
+
+    /// Add a system to `stage`. Systems must be added before `startup()`.
+    pub fn add_system<S: ParallelRunnable + 'static>(
+        &mut self,
+        stage: Stage,
+        system: S,
+    ) -> &mut Self {
+        self.add_system_with(stage, SystemDescriptor::new(system))
+    }
+
+    /// Add a system that must run on the main thread to `stage`.
+    pub fn add_thread_local_system<S: Runnable + 'static>(
+        &mut self,
+        stage: Stage,
+        system: S,
+    ) -> &mut Self {
+        self.add_system_with(stage, SystemDescriptor::thread_local(system))
+    }
+
+    /// Add a system with ordering constraints to `stage`.
+    pub fn add_system_with(&mut self, stage: Stage, descriptor: SystemDescriptor) -> &mut Self {
+        if self.started {
+            self.logger.log(
+                LogRecord::error(format!(
+                    "GameManager.add_system(): {} systems must be added before startup()",
+                    stage
+                ))
+                .tag(self.m_type()),
+            );
+            return self;
+        }
+        self.schedule_builder.add_system_with(stage, descriptor);
+        self
+    }
+
+    /// Add a flush point to `stage`, applying the command buffers of the systems before it.
+    pub fn add_flush(&mut self, stage: Stage) -> &mut Self {
+        if self.started {
+            self.logger.log(
+                LogRecord::error(format!(
+                    "GameManager.add_flush(): {} flushes must be added before startup()",
+                    stage
+                ))
+                .tag(self.m_type()),
+            );
+            return self;
+        }
+        self.schedule_builder.add_flush(stage);
+        self
+    }
+
+    /// Add a resource for systems to read, replacing any of the same type.
+    pub fn insert_resource<T: Resource>(&mut self, resource: T) -> &mut Self {
+        self.resources.insert(resource);
+        self
+    }
+
+    /// Add an `Events<T>` resource for systems to send and read `T` through,
+    /// cleared at the start of every simulation tick. Events must be added
+    /// before `startup()`.
+    pub fn add_event<T: Resource + Send + Sync>(&mut self) -> &mut Self {
+        if self.started {
+            self.logger.log(
+                LogRecord::error(format!(
+                    "GameManager.add_event(): {} must be added before startup()",
+                    std::any::type_name::<T>()
+                ))
+                .tag(self.m_type()),
+            );
+            return self;
+        }
+        if !self.resources.contains::<Events<T>>() {
+            self.resources.insert(Events::<T>::new());
+            self.event_updates.add_system(Events::<T>::update_system());
+        }
+        self
+    }
+
+    /// Record the session, play one back or neither. Playing back also takes
+    /// the seed and timestep settings of the recording. The mode must be set before
+    /// `startup()`, and `ReplayError::AlreadyStarted` is returned otherwise.
+    pub fn set_replay_mode(&mut self, mode: ReplayMode) -> Result<(), ReplayError> {
+        if self.started {
+            self.logger.log(
+                LogRecord::error("GameManager.set_replay_mode(): Must be set before startup()")
+                    .tag(self.m_type()),
+            );
+            return Err(ReplayError::AlreadyStarted);
+        }
+        self.replay = match mode {
+            ReplayMode::Off => Replay::Off,
+            ReplayMode::Record(path) => Replay::Recording {
+                path,
+                recording: Recording::new(self.seed, self.fixed_step, self.max_catch_up_steps),
+            },
+            ReplayMode::Playback(path) => {
+                let recording = Recording::load(&path)?;
+                self.seed = recording.seed;
+                self.fixed_step = recording.fixed_step;
+                self.max_catch_up_steps = recording.max_catch_up_steps;
+                self.logger.log(
+                    LogRecord::info(format!(
+                        "GameManager.set_replay_mode(): Playing back {}",
+                        path.display()
+                    ))
+                    .tag(self.m_type())
+                    .field("frames", recording.frames.len()),
+                );
+                Replay::Playing {
+                    recording,
+                    frame: 0,
+                    desync: None,
                 }
             }
+        };
+        Ok(())
+    }
+
+    /// The first frame at which playback diverged from the recording.
+    pub fn replay_desync(&self) -> Option<usize> {
+        self.replay.desync()
+    }
+
+    /// Ask the game loop to stop at the end of the current frame, or at the end
+    /// of the first one if it hasn't started yet.
+    pub fn request_stop(&mut self) {
+        if let Some(mut control) = self.resources.get_mut::<GameControl>() {
+            control.request_stop();
         }
     }
+
+    /// Current state of the game loop.
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    /// Move to `next`, running the exit systems of the current state and the
+    /// enter systems of the new one.
+    pub fn transition(&mut self, next: GameState) -> Result<(), TransitionError> {
+        if !self.state.can_transition_to(next) {
+            return Err(TransitionError {
+                from: self.state,
+                to: next,
+            });
+        }
+
+        self.run_state_hook(self.state, StateHook::Exit);
+        self.logger.log(
+            LogRecord::info(format!(
+                "GameManager.transition(): {} -> {}",
+                self.state, next
+            ))
+            .tag(self.m_type()),
+        );
+        let from = std::mem::replace(&mut self.state, next);
+        if let Some(mut control) = self.resources.get_mut::<GameControl>() {
+            control.set_state(next);
+        }
+        if let Some(mut events) = self.resources.get_mut::<Events<GameStateChanged>>() {
+            events.send(GameStateChanged { from, to: next });
+        }
+        self.run_state_hook(next, StateHook::Enter);
+        Ok(())
+    }
+
+    /// Add a system to run once each time the game enters `state`.
+    pub fn add_enter_system<S: ParallelRunnable + 'static>(
+        &mut self,
+        state: GameState,
+        system: S,
+    ) -> &mut Self {
+        self.add_state_hook(state, StateHook::Enter, system)
+    }
+
+    /// Add a system to run once each time the game leaves `state`.
+    pub fn add_exit_system<S: ParallelRunnable + 'static>(
+        &mut self,
+        state: GameState,
+        system: S,
+    ) -> &mut Self {
+        self.add_state_hook(state, StateHook::Exit, system)
+    }
+
+    fn add_state_hook<S: ParallelRunnable + 'static>(
+        &mut self,
+        state: GameState,
+        hook: StateHook,
+        system: S,
+    ) -> &mut Self {
+        if self.started {
+            self.logger.log(
+                LogRecord::error(format!(
+                    "GameManager.add_state_hook(): {} systems must be added before startup()",
+                    state
+                ))
+                .tag(self.m_type()),
+            );
+            return self;
+        }
+        self.state_hooks
+            .entry((state, hook))
+            .or_insert_with(Schedule::builder)
+            .add_system(system);
+        self
+    }
+
+    fn run_state_hook(&mut self, state: GameState, hook: StateHook) {
+        if let Some(schedule) = self.state_schedules.get_mut(&(state, hook)) {
+            schedule.execute(self.world, &mut self.resources);
+        }
+    }
+
+    fn build_schedule(&mut self, stages: &[Stage]) -> Result<Schedule, ScheduleError> {
+        self.schedule_builder.build(stages).map_err(|e| {
+            self.logger.log(
+                LogRecord::error(format!("GameManager.startup(): {}, game not started", e))
+                    .tag(self.m_type()),
+            );
+            e
+        })
+    }
+
+    /// Return what happened to the window, stopping the game loop if it was
+    /// closed, even during playback.
+    fn pump_display(&mut self) -> Vec<DisplayEvent> {
+        let events = match self.display.as_mut() {
+            Some(display) => display.pump_events(self.input.as_mut()),
+            None => return Vec::new(),
+        };
+        if events.contains(&DisplayEvent::CloseRequested) {
+            self.logger
+                .log(LogRecord::info("GameManager.run(): Window closed").tag(self.m_type()));
+            self.request_stop();
+        }
+        events
+    }
+
+    /// Send this frame's window events on as `DisplayEvent`s.
+    fn process_display(&mut self, events: Vec<DisplayEvent>) {
+        if let Some(mut sent) = self.resources.get_mut::<Events<DisplayEvent>>() {
+            for event in events {
+                sent.send(event);
+            }
+        }
+    }
+
+    /// Run the render stage. A display drawing into a `Framebuffer` lends it
+    /// to the render systems as a resource for the frame.
+    fn render_frame(&mut self) {
+        let lent = match self.display.as_mut().and_then(|d| d.framebuffer()) {
+            Some(framebuffer) => {
+                let lent = std::mem::replace(framebuffer, Framebuffer::new(0, 0));
+                self.resources.insert(lent);
+                true
+            }
+            None => false,
+        };
+        self.render.execute(self.world, &mut self.resources);
+        if !lent {
+            return;
+        }
+        let returned = self.resources.remove::<Framebuffer>();
+        if let (Some(framebuffer), Some(returned)) = (
+            self.display.as_mut().and_then(|d| d.framebuffer()),
+            returned,
+        ) {
+            *framebuffer = returned;
+        }
+    }
+
+    /// Apply this frame's input to `Input` and `ActionState`, and send it on
+    /// as `InputEvent`s.
+    fn process_input(&mut self, events: Vec<InputEvent>) {
+        if let Some(mut input) = self.resources.get_mut::<Input>() {
+            for event in &events {
+                input.apply(event);
+            }
+            if let (Some(manager), Some(mut actions)) =
+                (&self.input, self.resources.get_mut::<ActionState>())
+            {
+                actions.update(manager.actions(), &input);
+            }
+        }
+        if let Some(mut sent) = self.resources.get_mut::<Events<InputEvent>>() {
+            for event in events {
+                sent.send(event);
+            }
+        }
+    }
+
+    /// Record the state of the world, or compare it with the recording.
+    fn check_replay(&mut self) {
+        if let Replay::Off = self.replay {
+            return;
+        }
+        if let Some(frame) = self.replay.end_frame(replay::checksum(self.world)) {
+            self.logger.log(
+                LogRecord::warn("GameManager.run(): Replay desynced from the recording")
+                    .tag(self.m_type())
+                    .field("frame", frame),
+            );
+        }
+    }
+
+    /// Write out the recording, if the session was being recorded.
+    fn finish_replay(&mut self) {
+        match &self.replay {
+            Replay::Recording { path, recording } => self.logger.log(
+                LogRecord::info(format!(
+                    "GameManager.run(): Saving recording to {}",
+                    path.display()
+                ))
+                .tag(self.m_type())
+                .field("frames", recording.frames.len()),
+            ),
+            _ => return,
+        }
+        if let Err(e) = std::mem::replace(&mut self.replay, Replay::Off).finish() {
+            self.logger
+                .log(LogRecord::error(format!("GameManager.run(): {}", e)).tag(self.m_type()));
+        }
+    }
+
+    fn step_simulation(&mut self, frame_time: Duration) {
+        let (steps, dropped) = self
+            .resources
+            .get_mut::<FixedTime>()
+            .map(|mut f| f.accumulate(frame_time))
+            .unwrap_or((0, Duration::new(0, 0)));
+        if dropped > Duration::new(0, 0) {
+            self.logger.log(
+                LogRecord::warn("GameManager.run(): Simulation fell behind")
+                    .tag(self.m_type())
+                    .field("dropped_ms", dropped.as_millis()),
+            );
+        }
+        for _ in 0..steps {
+            self.events.execute(self.world, &mut self.resources);
+            self.simulation.execute(self.world, &mut self.resources);
+            if let Some(mut fixed) = self.resources.get_mut::<FixedTime>() {
+                fixed.advance();
+            }
+            // Every tick sees each change of input once, and none is lost
+            // when a frame runs no ticks
+            self.clear_input_changes();
+        }
+    }
+
+    fn clear_input_changes(&mut self) {
+        if let Some(mut input) = self.resources.get_mut::<Input>() {
+            input.clear_changes();
+        }
+        if let Some(mut actions) = self.resources.get_mut::<ActionState>() {
+            actions.clear_changes();
+        }
+    }
+}
+
+impl<'a> GameManager<'a> {
+    /// Take over `display_manager`, pumping its window events every frame and
+    /// stopping the game loop when the window is closed.
+    pub fn attach_display(&mut self, display_manager: DisplayManager<'a>) -> &mut Self {
+        self.display = Some(display_manager);
+        self
+    }
+
+    /// The attached display manager, e.g. to get at the window.
+    pub fn display_manager(&mut self) -> Option<&mut DisplayManager<'a>> {
+        self.display.as_mut()
+    }
+
+    /// Take over `input_manager`, feeding its input to systems every frame.
+    pub fn attach_input(&mut self, input_manager: InputManager<'a>) -> &mut Self {
+        self.input = Some(input_manager);
+        self
+    }
+
+    /// The attached input manager, e.g. to inject events.
+    pub fn input_manager(&mut self) -> Option<&mut InputManager<'a>> {
+        self.input.as_mut()
+    }
 }
@@ -0,0 +1,155 @@
+use std::error::Error;
+use std::fmt;
+
+/// Lifecycle states of the game loop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GameState {
+    Loading,
+    Running,
+    Paused,
+    Stopping,
+    Stopped,
+}
+
+impl GameState {
+    /// Return true if the state machine may move from this state to `next`.
+    pub fn can_transition_to(self, next: GameState) -> bool {
+        use GameState::*;
+        matches!(
+            (self, next),
+            (Loading, Running)
+                | (Loading, Stopping)
+                | (Running, Paused)
+                | (Running, Stopping)
+                | (Paused, Running)
+                | (Paused, Stopping)
+                | (Stopping, Stopped)
+                | (Stopped, Loading)
+        )
+    }
+}
+
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Returned when a state change isn't allowed from the current state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransitionError {
+    pub from: GameState,
+    pub to: GameState,
+}
+
+impl fmt::Display for TransitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid state transition from {} to {}",
+            self.from, self.to
+        )
+    }
+}
+
+impl Error for TransitionError {}
+
+/// Event sent by `GameManager` each time the game loop changes state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GameStateChanged {
+    pub from: GameState,
+    pub to: GameState,
+}
+
+/// Resource that lets systems ask the game loop to change state.
+///
+/// Requests are applied by `GameManager` at the end of the frame, so a system
+/// never sees the state change underneath it. The latest request wins, except
+/// that a stop can't be overridden, e.g. by a pause from a later system.
+#[derive(Debug, Default)]
+pub struct GameControl {
+    state: Option<GameState>,
+    requested: Option<GameState>,
+}
+
+impl GameControl {
+    /// The state the game loop was in when the current frame started.
+    pub fn state(&self) -> Option<GameState> {
+        self.state
+    }
+
+    /// Ask the game loop to stop at the end of the frame.
+    pub fn request_stop(&mut self) {
+        self.requested = Some(GameState::Stopping);
+    }
+
+    /// Ask the game loop to pause at the end of the frame.
+    pub fn request_pause(&mut self) {
+        self.request(GameState::Paused);
+    }
+
+    /// Ask the game loop to resume at the end of the frame.
+    pub fn request_resume(&mut self) {
+        self.request(GameState::Running);
+    }
+
+    fn request(&mut self, state: GameState) {
+        if self.requested != Some(GameState::Stopping) {
+            self.requested = Some(state);
+        }
+    }
+
+    pub(crate) fn set_state(&mut self, state: GameState) {
+        self.state = Some(state);
+    }
+
+    pub(crate) fn take_request(&mut self) -> Option<GameState> {
+        self.requested.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn running_can_pause_and_resume() {
+        assert!(GameState::Running.can_transition_to(GameState::Paused));
+        assert!(GameState::Paused.can_transition_to(GameState::Running));
+    }
+
+    #[test]
+    fn stopped_can_only_be_reached_through_stopping() {
+        assert!(!GameState::Running.can_transition_to(GameState::Stopped));
+        assert!(!GameState::Paused.can_transition_to(GameState::Stopped));
+        assert!(GameState::Stopping.can_transition_to(GameState::Stopped));
+    }
+
+    #[test]
+    fn stopping_cannot_be_resumed() {
+        assert!(!GameState::Stopping.can_transition_to(GameState::Running));
+        assert!(!GameState::Stopping.can_transition_to(GameState::Paused));
+    }
+
+    #[test]
+    fn game_control_hands_out_the_latest_request_once() {
+        let mut control = GameControl::default();
+        control.request_pause();
+        control.request_stop();
+
+        assert_eq!(control.take_request(), Some(GameState::Stopping));
+        assert_eq!(control.take_request(), None);
+    }
+
+    #[test]
+    fn stop_requests_are_not_overridden() {
+        let mut control = GameControl::default();
+        control.request_stop();
+        control.request_pause();
+        control.request_resume();
+
+        assert_eq!(control.take_request(), Some(GameState::Stopping));
+        control.request_pause();
+        assert_eq!(control.take_request(), Some(GameState::Paused));
+    }
+}
@@ -0,0 +1,402 @@
+// Transform hierarchy
+//
+// An entity with a `Parent` has its `Transform` expressed relative to that
+// parent. `propagate_transforms` walks down from the root entities once per
+// tick and writes each entity's local-to-world matrix into its
+// `GlobalTransform`, so a parent is always resolved before its children.
+
+use std::error::Error;
+use std::fmt;
+
+use legion::world::SubWorld;
+use legion::*;
+
+use crate::component::Transform;
+use crate::matrix::Matrix4x4;
+use crate::vector::Vector3;
+
+/// The entity this one is attached to.
+///
+/// Set it with `set_parent` rather than adding it directly, so the parent's
+/// `Children` stays in sync.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Parent(pub Entity);
+
+/// The entities attached to this one, maintained by `set_parent`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Children(Vec<Entity>);
+
+impl Children {
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.0.contains(&entity)
+    }
+}
+
+/// Local-to-world matrix of an entity, written by `propagate_transforms`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GlobalTransform(pub Matrix4x4);
+
+impl GlobalTransform {
+    /// World space position of the entity.
+    pub fn position(&self) -> Vector3 {
+        self.0.translation()
+    }
+}
+
+/// What to preserve when an entity changes parent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReparentMode {
+    /// Rewrite the local `Transform` so the entity stays where it is in the world.
+    KeepWorld,
+    /// Keep the local `Transform`, so the entity moves with its new parent.
+    KeepLocal,
+}
+
+/// Returned when an entity can't be attached to another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HierarchyError {
+    /// The entity isn't in the world.
+    NoSuchEntity(Entity),
+    /// The new parent is the child itself or one of its descendants.
+    Cycle { child: Entity, parent: Entity },
+    /// The new parent has a zero scale, so no local pose keeps the world pose.
+    SingularParent(Entity),
+}
+
+impl fmt::Display for HierarchyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HierarchyError::NoSuchEntity(entity) => write!(f, "Entity {:?} does not exist", entity),
+            HierarchyError::Cycle { child, parent } => write!(
+                f,
+                "Attaching {:?} to {:?} would make it its own ancestor",
+                child, parent
+            ),
+            HierarchyError::SingularParent(parent) => write!(
+                f,
+                "Parent {:?} has a zero scale, the world pose can't be kept",
+                parent
+            ),
+        }
+    }
+}
+
+impl Error for HierarchyError {}
+
+/// Write the local-to-world matrix of every entity with a `GlobalTransform`.
+///
+/// Runs in `Stage::PostUpdate`, after the simulation has moved things.
+#[system]
+#[read_component(Transform)]
+#[read_component(Children)]
+#[read_component(Parent)]
+#[write_component(GlobalTransform)]
+pub fn propagate_transforms(world: &mut SubWorld) {
+    let mut pending: Vec<(Entity, Matrix4x4)> = <(Entity, &Transform)>::query()
+        .filter(!component::<Parent>())
+        .iter(world)
+        .map(|(entity, transform)| (*entity, transform.to_matrix()))
+        .collect();
+
+    // Depth first, so every parent's matrix is final before its children use it
+    while let Some((entity, matrix)) = pending.pop() {
+        let children = match world.entry_mut(entity) {
+            Ok(mut entry) => {
+                if let Ok(global) = entry.get_component_mut::<GlobalTransform>() {
+                    global.0 = matrix;
+                }
+                entry
+                    .get_component::<Children>()
+                    .map(|children| children.0.clone())
+                    .unwrap_or_default()
+            }
+            Err(_) => continue,
+        };
+        for child in children {
+            if let Ok(entry) = world.entry_ref(child) {
+                let local = entry
+                    .get_component::<Transform>()
+                    .map(Transform::to_matrix)
+                    .unwrap_or(Matrix4x4::IDENTITY);
+                pending.push((child, matrix * local));
+            }
+        }
+    }
+}
+
+/// Attach `child` to `parent`, detaching it from any previous parent.
+///
+/// Both entities get a `GlobalTransform` if they don't have one yet. The new
+/// world matrix is written by the next run of `propagate_transforms`.
+pub fn set_parent(
+    world: &mut World,
+    child: Entity,
+    parent: Entity,
+    mode: ReparentMode,
+) -> Result<(), HierarchyError> {
+    for entity in [child, parent].iter() {
+        if !world.contains(*entity) {
+            return Err(HierarchyError::NoSuchEntity(*entity));
+        }
+    }
+    if parent == child || ancestors(world, parent).any(|ancestor| ancestor == child) {
+        return Err(HierarchyError::Cycle { child, parent });
+    }
+
+    let local = match mode {
+        ReparentMode::KeepLocal => None,
+        ReparentMode::KeepWorld => {
+            let world_to_parent = world_matrix(world, parent)
+                .inverse()
+                .ok_or(HierarchyError::SingularParent(parent))?;
+            Some(world_to_parent * world_matrix(world, child))
+        }
+    };
+
+    detach(world, child);
+    if let Some(mut entry) = world.entry(parent) {
+        if let Ok(children) = entry.get_component_mut::<Children>() {
+            children.0.push(child);
+        } else {
+            entry.add_component(Children(vec![child]));
+        }
+        if entry.get_component::<GlobalTransform>().is_err() {
+            entry.add_component(GlobalTransform::default());
+        }
+    }
+    if let Some(mut entry) = world.entry(child) {
+        entry.add_component(Parent(parent));
+        if entry.get_component::<GlobalTransform>().is_err() {
+            entry.add_component(GlobalTransform::default());
+        }
+        if let (Some(local), Ok(transform)) = (local, entry.get_component_mut::<Transform>()) {
+            *transform = Transform::from_matrix(&local);
+        }
+    }
+    Ok(())
+}
+
+/// Detach `child` from its parent, making it a root.
+pub fn remove_parent(
+    world: &mut World,
+    child: Entity,
+    mode: ReparentMode,
+) -> Result<(), HierarchyError> {
+    if !world.contains(child) {
+        return Err(HierarchyError::NoSuchEntity(child));
+    }
+    let local = match mode {
+        ReparentMode::KeepLocal => None,
+        ReparentMode::KeepWorld => Some(world_matrix(world, child)),
+    };
+
+    detach(world, child);
+    if let Some(mut entry) = world.entry(child) {
+        if let (Some(local), Ok(transform)) = (local, entry.get_component_mut::<Transform>()) {
+            *transform = Transform::from_matrix(&local);
+        }
+    }
+    Ok(())
+}
+
+/// Remove `entity` and everything attached below it from the world.
+///
+/// Returns false if the entity didn't exist.
+pub fn despawn_recursive(world: &mut World, entity: Entity) -> bool {
+    if !world.contains(entity) {
+        return false;
+    }
+    detach(world, entity);
+
+    let mut pending = vec![entity];
+    while let Some(entity) = pending.pop() {
+        if let Ok(entry) = world.entry_ref(entity) {
+            if let Ok(children) = entry.get_component::<Children>() {
+                pending.extend(children.iter());
+            }
+        }
+        world.remove(entity);
+    }
+    true
+}
+
+/// Local-to-world matrix of `entity`, computed from the `Transform`s up the
+/// hierarchy rather than the `GlobalTransform`, which may be a tick behind.
+pub fn world_matrix<W: EntityStore>(world: &W, entity: Entity) -> Matrix4x4 {
+    let local = |entity: Entity| {
+        world
+            .entry_ref(entity)
+            .ok()
+            .and_then(|entry| {
+                entry
+                    .get_component::<Transform>()
+                    .ok()
+                    .map(Transform::to_matrix)
+            })
+            .unwrap_or(Matrix4x4::IDENTITY)
+    };
+    ancestors(world, entity).fold(local(entity), |matrix, ancestor| local(ancestor) * matrix)
+}
+
+/// Iterate over the parent of `entity`, then its parent, up to the root.
+fn ancestors<W: EntityStore>(world: &W, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+    let parent_of = move |entity: &Entity| {
+        world
+            .entry_ref(*entity)
+            .ok()
+            .and_then(|entry| entry.get_component::<Parent>().ok().map(|parent| parent.0))
+    };
+    std::iter::successors(parent_of(&entity), parent_of)
+}
+
+/// Remove the `Parent` of `child` and take it out of the parent's `Children`.
+fn detach(world: &mut World, child: Entity) {
+    let parent = match world.entry(child) {
+        Some(mut entry) => {
+            let parent = entry.get_component::<Parent>().ok().map(|parent| parent.0);
+            entry.remove_component::<Parent>();
+            parent
+        }
+        None => None,
+    };
+    if let Some(mut entry) = parent.and_then(|parent| world.entry(parent)) {
+        if let Ok(children) = entry.get_component_mut::<Children>() {
+            children.0.retain(|entity| *entity != child);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+    use crate::quaternion::Quaternion;
+
+    fn propagate(world: &mut World) {
+        let mut schedule = Schedule::builder()
+            .add_system(propagate_transforms_system())
+            .build();
+        schedule.execute(world, &mut Resources::default());
+    }
+
+    fn global(world: &World, entity: Entity) -> Vector3 {
+        world
+            .entry_ref(entity)
+            .unwrap()
+            .get_component::<GlobalTransform>()
+            .unwrap()
+            .position()
+    }
+
+    #[test]
+    fn children_are_placed_relative_to_their_parents() {
+        let mut world = World::default();
+        let root = Transform::from_position(Vector3::new(10.0, 0.0, 0.0))
+            .with_rotation(Quaternion::angle_axis(90.0, Vector3::up()));
+        let root = world.push((root,));
+        let arm = world.push((Transform::from_position(Vector3::forward()),));
+        let hand = world.push((Transform::from_position(Vector3::forward()),));
+        set_parent(&mut world, arm, root, ReparentMode::KeepLocal).unwrap();
+        set_parent(&mut world, hand, arm, ReparentMode::KeepLocal).unwrap();
+
+        propagate(&mut world);
+
+        assert_approx_eq!(global(&world, root), Vector3::new(10.0, 0.0, 0.0));
+        assert_approx_eq!(global(&world, arm), Vector3::new(11.0, 0.0, 0.0));
+        assert_approx_eq!(global(&world, hand), Vector3::new(12.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn reparenting_can_keep_the_world_or_the_local_pose() {
+        let mut world = World::default();
+        let parent = world.push((Transform::from_position(Vector3::new(0.0, 5.0, 0.0)),));
+        let kept_world = world.push((Transform::from_position(Vector3::right()),));
+        let kept_local = world.push((Transform::from_position(Vector3::right()),));
+        set_parent(&mut world, kept_world, parent, ReparentMode::KeepWorld).unwrap();
+        set_parent(&mut world, kept_local, parent, ReparentMode::KeepLocal).unwrap();
+
+        propagate(&mut world);
+
+        assert_approx_eq!(global(&world, kept_world), Vector3::right());
+        assert_approx_eq!(global(&world, kept_local), Vector3::new(1.0, 5.0, 0.0));
+
+        remove_parent(&mut world, kept_local, ReparentMode::KeepWorld).unwrap();
+        propagate(&mut world);
+        assert_approx_eq!(global(&world, kept_local), Vector3::new(1.0, 5.0, 0.0));
+    }
+
+    #[test]
+    fn an_entity_cannot_become_its_own_ancestor() {
+        let mut world = World::default();
+        let a = world.push((Transform::identity(),));
+        let b = world.push((Transform::identity(),));
+        set_parent(&mut world, b, a, ReparentMode::KeepLocal).unwrap();
+
+        assert_eq!(
+            set_parent(&mut world, a, b, ReparentMode::KeepLocal),
+            Err(HierarchyError::Cycle {
+                child: a,
+                parent: b
+            })
+        );
+        assert_eq!(
+            set_parent(&mut world, a, a, ReparentMode::KeepLocal),
+            Err(HierarchyError::Cycle {
+                child: a,
+                parent: a
+            })
+        );
+    }
+
+    #[test]
+    fn moving_a_child_updates_both_parents_children() {
+        let mut world = World::default();
+        let first = world.push((Transform::identity(),));
+        let second = world.push((Transform::identity(),));
+        let child = world.push((Transform::identity(),));
+        set_parent(&mut world, child, first, ReparentMode::KeepLocal).unwrap();
+        set_parent(&mut world, child, second, ReparentMode::KeepLocal).unwrap();
+
+        let children = |world: &World, entity| {
+            world
+                .entry_ref(entity)
+                .unwrap()
+                .get_component::<Children>()
+                .unwrap()
+                .clone()
+        };
+        assert!(children(&world, first).is_empty());
+        assert!(children(&world, second).contains(child));
+    }
+
+    #[test]
+    fn despawning_a_parent_despawns_its_descendants() {
+        let mut world = World::default();
+        let root = world.push((Transform::identity(),));
+        let parent = world.push((Transform::identity(),));
+        let child = world.push((Transform::identity(),));
+        set_parent(&mut world, parent, root, ReparentMode::KeepLocal).unwrap();
+        set_parent(&mut world, child, parent, ReparentMode::KeepLocal).unwrap();
+
+        assert!(despawn_recursive(&mut world, parent));
+
+        assert!(world.contains(root));
+        assert!(!world.contains(parent));
+        assert!(!world.contains(child));
+        let root = world.entry_ref(root).unwrap();
+        assert!(root.get_component::<Children>().unwrap().is_empty());
+        assert!(!despawn_recursive(&mut world, parent));
+    }
+}
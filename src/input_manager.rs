@@ -0,0 +1,551 @@
+// Input
+//
+// `InputManager` collects input from wherever it comes from (window events
+// handed over by the display, gamepads polled through gilrs, or events
+// injected by tests and tools) into a queue of `InputEvent`s. Once a frame
+// `GameManager` drains that queue into the `Input` resource, which systems
+// read to find out what's held down and what changed since the last frame.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+use super::log_manager::{LogManager, LogRecord};
+use super::manager::Manager;
+use crate::action::{ActionMap, ActionMapError};
+use crate::vector::Vector2;
+
+/// Identifies a connected gamepad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Gamepad(pub usize);
+
+/// Buttons of a gamepad, named by their position on an Xbox style pad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftTrigger,
+    LeftTrigger2,
+    RightTrigger,
+    RightTrigger2,
+    Select,
+    Start,
+    Mode,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// Analog axes of a gamepad, each in the range [-1, 1].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    LeftZ,
+    RightStickX,
+    RightStickY,
+    RightZ,
+}
+
+/// A single change in input, from a device or injected.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    Key {
+        key: VirtualKeyCode,
+        state: ElementState,
+    },
+    MouseButton {
+        button: MouseButton,
+        state: ElementState,
+    },
+    /// The cursor moved to `position`, in pixels from the top left of the window.
+    CursorMoved {
+        position: Vector2,
+    },
+    CursorLeft,
+    /// The window lost focus, so held keys and mouse buttons won't be released.
+    FocusLost,
+    /// The wheel or touchpad scrolled by `delta`, in lines.
+    Scroll {
+        delta: Vector2,
+    },
+    GamepadConnected {
+        gamepad: Gamepad,
+    },
+    GamepadDisconnected {
+        gamepad: Gamepad,
+    },
+    GamepadButton {
+        gamepad: Gamepad,
+        button: GamepadButton,
+        state: ElementState,
+    },
+    GamepadAxis {
+        gamepad: Gamepad,
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+/// Which buttons of some kind are held, and which changed since the last
+/// simulation tick.
+#[derive(Clone, Debug)]
+pub struct ButtonInput<T: Copy + Eq + Hash> {
+    pressed: HashSet<T>,
+    just_pressed: HashSet<T>,
+    just_released: HashSet<T>,
+}
+
+impl<T: Copy + Eq + Hash> Default for ButtonInput<T> {
+    fn default() -> Self {
+        ButtonInput {
+            pressed: HashSet::new(),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Copy + Eq + Hash> ButtonInput<T> {
+    /// Returns true while `button` is held down.
+    pub fn pressed(&self, button: T) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    /// Returns true if `button` went down since the last tick.
+    pub fn just_pressed(&self, button: T) -> bool {
+        self.just_pressed.contains(&button)
+    }
+
+    /// Returns true if `button` came up since the last tick.
+    pub fn just_released(&self, button: T) -> bool {
+        self.just_released.contains(&button)
+    }
+
+    /// Every button held down, in no particular order.
+    pub fn get_pressed(&self) -> impl Iterator<Item = &T> {
+        self.pressed.iter()
+    }
+
+    pub fn press(&mut self, button: T) {
+        // Key repeat sends presses for a button already held
+        if self.pressed.insert(button) {
+            self.just_pressed.insert(button);
+        }
+    }
+
+    pub fn release(&mut self, button: T) {
+        if self.pressed.remove(&button) {
+            self.just_released.insert(button);
+        }
+    }
+
+    /// Release every button, e.g. when the window loses focus.
+    pub fn release_all(&mut self) {
+        let pressed = std::mem::take(&mut self.pressed);
+        self.just_released.extend(pressed);
+    }
+
+    /// Forget what changed, ready for the next tick.
+    pub fn clear(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+
+    fn set(&mut self, button: T, state: ElementState) {
+        match state {
+            ElementState::Pressed => self.press(button),
+            ElementState::Released => self.release(button),
+        }
+    }
+}
+
+/// Resource holding the state of every input device as of this frame.
+///
+/// Changes are kept until a simulation tick has run, so every tick sees each
+/// press exactly once, however many ticks a frame runs.
+#[derive(Clone, Debug, Default)]
+pub struct Input {
+    pub keys: ButtonInput<VirtualKeyCode>,
+    pub mouse_buttons: ButtonInput<MouseButton>,
+    pub gamepad_buttons: ButtonInput<(Gamepad, GamepadButton)>,
+    cursor: Option<Vector2>,
+    scroll: Vector2,
+    gamepads: Vec<Gamepad>,
+    axes: HashMap<(Gamepad, GamepadAxis), f32>,
+}
+
+impl Input {
+    /// Where the cursor is in the window, or `None` if it's outside it.
+    pub fn cursor_position(&self) -> Option<Vector2> {
+        self.cursor
+    }
+
+    /// How far the mouse wheel scrolled since the last tick, in lines.
+    pub fn scroll(&self) -> Vector2 {
+        self.scroll
+    }
+
+    /// Gamepads currently connected, in the order they connected.
+    pub fn gamepads(&self) -> &[Gamepad] {
+        &self.gamepads
+    }
+
+    /// Position of a gamepad axis, 0 if it hasn't moved.
+    pub fn axis(&self, gamepad: Gamepad, axis: GamepadAxis) -> f32 {
+        self.axes.get(&(gamepad, axis)).copied().unwrap_or(0.0)
+    }
+
+    /// Forget what changed, once a simulation tick has seen it.
+    pub fn clear_changes(&mut self) {
+        self.keys.clear();
+        self.mouse_buttons.clear();
+        self.gamepad_buttons.clear();
+        self.scroll = Vector2::ZERO;
+    }
+
+    pub fn apply(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::Key { key, state } => self.keys.set(key, state),
+            InputEvent::MouseButton { button, state } => self.mouse_buttons.set(button, state),
+            InputEvent::CursorMoved { position } => self.cursor = Some(position),
+            InputEvent::CursorLeft => self.cursor = None,
+            InputEvent::FocusLost => {
+                self.keys.release_all();
+                self.mouse_buttons.release_all();
+            }
+            InputEvent::Scroll { delta } => self.scroll += delta,
+            InputEvent::GamepadConnected { gamepad } => {
+                if !self.gamepads.contains(&gamepad) {
+                    self.gamepads.push(gamepad);
+                }
+            }
+            InputEvent::GamepadDisconnected { gamepad } => {
+                self.gamepads.retain(|&g| g != gamepad);
+                self.axes.retain(|&(g, _), _| g != gamepad);
+                let held: Vec<_> = self
+                    .gamepad_buttons
+                    .get_pressed()
+                    .filter(|(g, _)| *g == gamepad)
+                    .copied()
+                    .collect();
+                for button in held {
+                    self.gamepad_buttons.release(button);
+                }
+            }
+            InputEvent::GamepadButton {
+                gamepad,
+                button,
+                state,
+            } => self.gamepad_buttons.set((gamepad, button), state),
+            InputEvent::GamepadAxis {
+                gamepad,
+                axis,
+                value,
+            } => {
+                self.axes.insert((gamepad, axis), value);
+            }
+        }
+    }
+}
+
+/// Pixels per line for touchpads, which scroll in pixels rather than lines.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+pub struct InputManager<'a> {
+    started: bool,
+    logger: &'a LogManager,
+    queue: Vec<InputEvent>,
+    actions: ActionMap,
+    #[cfg(feature = "gamepad")]
+    gilrs: Option<gilrs::Gilrs>,
+}
+
+impl Manager for InputManager<'_> {
+    fn m_type(&self) -> &str {
+        "input_manager"
+    }
+}
+
+impl InputManager<'_> {
+    pub fn new(log_manager: &LogManager) -> InputManager<'_> {
+        InputManager {
+            started: false,
+            logger: log_manager,
+            queue: Vec::new(),
+            actions: ActionMap::new(),
+            #[cfg(feature = "gamepad")]
+            gilrs: None,
+        }
+    }
+    pub fn startup(&mut self) {
+        #[cfg(feature = "gamepad")]
+        match gilrs::Gilrs::new() {
+            Ok(gilrs) => self.gilrs = Some(gilrs),
+            Err(e) => self.logger.log(
+                LogRecord::warn(format!(
+                    "InputManager.startup(): Gamepads unavailable, {}",
+                    e
+                ))
+                .tag(self.m_type()),
+            ),
+        }
+        self.logger
+            .log(LogRecord::info("InputManager.startup(): Listening for input").tag(self.m_type()));
+        self.started = true
+    }
+    pub fn shutdown(mut self) {
+        self.started = false
+    }
+
+    /// Replace the action map with the one stored at `path`.
+    pub fn load_actions<P: AsRef<Path>>(&mut self, path: P) -> Result<(), ActionMapError> {
+        let path = path.as_ref();
+        self.actions = ActionMap::load(path)?;
+        self.logger.log(
+            LogRecord::info(format!(
+                "InputManager.load_actions(): Loaded actions from {}",
+                path.display()
+            ))
+            .tag(self.m_type()),
+        );
+        Ok(())
+    }
+
+    /// Store the action map at `path`, e.g. after rebinding.
+    pub fn save_actions<P: AsRef<Path>>(&self, path: P) -> Result<(), ActionMapError> {
+        self.actions.save(path)
+    }
+
+    /// Bindings used to work out `ActionState` each frame.
+    pub fn actions(&self) -> &ActionMap {
+        &self.actions
+    }
+
+    /// Bindings used to work out `ActionState` each frame, to rebind them.
+    pub fn actions_mut(&mut self) -> &mut ActionMap {
+        &mut self.actions
+    }
+
+    /// Queue an event as if a device had sent it.
+    pub fn inject(&mut self, event: InputEvent) {
+        self.queue.push(event);
+    }
+
+    /// Queue the input in a window event, ignoring the rest.
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        let input = match event {
+            WindowEvent::KeyboardInput { input, .. } => match input.virtual_keycode {
+                Some(key) => InputEvent::Key {
+                    key,
+                    state: input.state,
+                },
+                None => return,
+            },
+            WindowEvent::MouseInput { button, state, .. } => InputEvent::MouseButton {
+                button: *button,
+                state: *state,
+            },
+            WindowEvent::CursorMoved { position, .. } => InputEvent::CursorMoved {
+                position: Vector2::new(position.x as f32, position.y as f32),
+            },
+            WindowEvent::CursorLeft { .. } => InputEvent::CursorLeft,
+            WindowEvent::Focused(false) => InputEvent::FocusLost,
+            WindowEvent::MouseWheel { delta, .. } => InputEvent::Scroll {
+                delta: match delta {
+                    MouseScrollDelta::LineDelta(x, y) => Vector2::new(*x, *y),
+                    MouseScrollDelta::PixelDelta(p) => {
+                        Vector2::new(p.x as f32, p.y as f32) / PIXELS_PER_LINE
+                    }
+                },
+            },
+            _ => return,
+        };
+        self.inject(input);
+    }
+
+    /// Take every event queued since the last call, polling gamepads first.
+    pub fn poll(&mut self) -> Vec<InputEvent> {
+        #[cfg(feature = "gamepad")]
+        self.poll_gamepads();
+        std::mem::take(&mut self.queue)
+    }
+
+    #[cfg(feature = "gamepad")]
+    fn poll_gamepads(&mut self) {
+        use gilrs::EventType;
+
+        let gilrs = match self.gilrs.as_mut() {
+            Some(gilrs) => gilrs,
+            None => return,
+        };
+        while let Some(gilrs::Event { id, event, .. }) = gilrs.next_event() {
+            let gamepad = Gamepad(id.into());
+            let input = match event {
+                EventType::Connected => InputEvent::GamepadConnected { gamepad },
+                EventType::Disconnected => InputEvent::GamepadDisconnected { gamepad },
+                EventType::ButtonPressed(button, _) => match gamepad_button(button) {
+                    Some(button) => InputEvent::GamepadButton {
+                        gamepad,
+                        button,
+                        state: ElementState::Pressed,
+                    },
+                    None => continue,
+                },
+                EventType::ButtonReleased(button, _) => match gamepad_button(button) {
+                    Some(button) => InputEvent::GamepadButton {
+                        gamepad,
+                        button,
+                        state: ElementState::Released,
+                    },
+                    None => continue,
+                },
+                EventType::AxisChanged(axis, value, _) => match gamepad_axis(axis) {
+                    Some(axis) => InputEvent::GamepadAxis {
+                        gamepad,
+                        axis,
+                        value,
+                    },
+                    None => continue,
+                },
+                _ => continue,
+            };
+            self.queue.push(input);
+        }
+    }
+}
+
+#[cfg(feature = "gamepad")]
+fn gamepad_button(button: gilrs::Button) -> Option<GamepadButton> {
+    use gilrs::Button;
+
+    Some(match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::North => GamepadButton::North,
+        Button::West => GamepadButton::West,
+        Button::LeftTrigger => GamepadButton::LeftTrigger,
+        Button::LeftTrigger2 => GamepadButton::LeftTrigger2,
+        Button::RightTrigger => GamepadButton::RightTrigger,
+        Button::RightTrigger2 => GamepadButton::RightTrigger2,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::Mode => GamepadButton::Mode,
+        Button::LeftThumb => GamepadButton::LeftThumb,
+        Button::RightThumb => GamepadButton::RightThumb,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+#[cfg(feature = "gamepad")]
+fn gamepad_axis(axis: gilrs::Axis) -> Option<GamepadAxis> {
+    use gilrs::Axis;
+
+    Some(match axis {
+        Axis::LeftStickX => GamepadAxis::LeftStickX,
+        Axis::LeftStickY => GamepadAxis::LeftStickY,
+        Axis::LeftZ => GamepadAxis::LeftZ,
+        Axis::RightStickX => GamepadAxis::RightStickX,
+        Axis::RightStickY => GamepadAxis::RightStickY,
+        Axis::RightZ => GamepadAxis::RightZ,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: VirtualKeyCode, state: ElementState) -> InputEvent {
+        InputEvent::Key { key, state }
+    }
+
+    #[test]
+    fn buttons_are_just_pressed_for_one_frame() {
+        let mut buttons = ButtonInput::default();
+        buttons.press(VirtualKeyCode::Space);
+
+        assert!(buttons.pressed(VirtualKeyCode::Space));
+        assert!(buttons.just_pressed(VirtualKeyCode::Space));
+        buttons.clear();
+        buttons.press(VirtualKeyCode::Space);
+        assert!(buttons.pressed(VirtualKeyCode::Space));
+        assert!(!buttons.just_pressed(VirtualKeyCode::Space));
+
+        buttons.release(VirtualKeyCode::Space);
+        assert!(!buttons.pressed(VirtualKeyCode::Space));
+        assert!(buttons.just_released(VirtualKeyCode::Space));
+    }
+
+    #[test]
+    fn injected_events_reach_the_input_resource() {
+        let log_manager = LogManager::new();
+        let mut manager = InputManager::new(&log_manager);
+        let mut input = Input::default();
+        manager.inject(key(VirtualKeyCode::A, ElementState::Pressed));
+        manager.inject(InputEvent::CursorMoved {
+            position: Vector2::new(10.0, 20.0),
+        });
+        manager.inject(InputEvent::Scroll {
+            delta: Vector2::new(0.0, 1.0),
+        });
+        manager.inject(InputEvent::Scroll {
+            delta: Vector2::new(0.0, 2.0),
+        });
+
+        input.clear_changes();
+        for event in manager.poll() {
+            input.apply(&event);
+        }
+
+        assert!(input.keys.just_pressed(VirtualKeyCode::A));
+        assert_eq!(input.cursor_position(), Some(Vector2::new(10.0, 20.0)));
+        assert_eq!(input.scroll(), Vector2::new(0.0, 3.0));
+        assert!(manager.poll().is_empty());
+
+        input.clear_changes();
+        assert!(input.keys.pressed(VirtualKeyCode::A));
+        assert!(!input.keys.just_pressed(VirtualKeyCode::A));
+        assert_eq!(input.scroll(), Vector2::ZERO);
+    }
+
+    #[test]
+    fn disconnecting_a_gamepad_releases_its_buttons() {
+        let mut input = Input::default();
+        let pad = Gamepad(0);
+        input.apply(&InputEvent::GamepadConnected { gamepad: pad });
+        input.apply(&InputEvent::GamepadButton {
+            gamepad: pad,
+            button: GamepadButton::South,
+            state: ElementState::Pressed,
+        });
+        input.apply(&InputEvent::GamepadAxis {
+            gamepad: pad,
+            axis: GamepadAxis::LeftStickX,
+            value: -0.5,
+        });
+
+        assert_eq!(input.gamepads(), &[pad]);
+        assert_eq!(input.axis(pad, GamepadAxis::LeftStickX), -0.5);
+        input.clear_changes();
+        input.apply(&InputEvent::GamepadDisconnected { gamepad: pad });
+
+        assert!(input.gamepads().is_empty());
+        assert!(input
+            .gamepad_buttons
+            .just_released((pad, GamepadButton::South)));
+        assert_eq!(input.axis(pad, GamepadAxis::LeftStickX), 0.0);
+    }
+}
@@ -0,0 +1,27 @@
+pub mod action;
+pub mod angle;
+pub mod approx;
+pub mod batch;
+pub mod component;
+pub mod display_backend;
+pub mod display_manager;
+pub mod event;
+pub mod game_manager;
+pub mod game_state;
+pub mod hierarchy;
+pub mod input_manager;
+pub mod log_bridge;
+pub mod log_manager;
+pub mod log_rotation;
+pub mod log_sink;
+pub mod manager;
+pub mod matrix;
+pub mod physics;
+pub mod quaternion;
+pub mod random;
+pub mod replay;
+#[cfg(feature = "simd")]
+mod simd;
+pub mod stage;
+pub mod time;
+pub mod vector;
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use super::log_manager::{LogCore, LogLevel, LogRecord};
+
+impl From<log::Level> for LogLevel {
+    /// `log` has a Trace level below Debug, which is folded into Debug.
+    fn from(level: log::Level) -> LogLevel {
+        match level {
+            log::Level::Error => LogLevel::Error,
+            log::Level::Warn => LogLevel::Warn,
+            log::Level::Info => LogLevel::Info,
+            log::Level::Debug | log::Level::Trace => LogLevel::Debug,
+        }
+    }
+}
+
+impl From<LogLevel> for log::LevelFilter {
+    fn from(level: LogLevel) -> log::LevelFilter {
+        match level {
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Trace,
+        }
+    }
+}
+
+/// `log::Log` implementation forwarding records into a LogManager's sinks.
+///
+/// The record's target, usually the module path of the caller, becomes the tag.
+pub struct LogBridge {
+    core: Arc<LogCore>,
+}
+
+impl LogBridge {
+    pub(crate) fn new(core: Arc<LogCore>) -> LogBridge {
+        LogBridge { core }
+    }
+}
+
+impl log::Log for LogBridge {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.core.enabled(metadata.level().into())
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut entry = LogRecord::new(record.level().into(), record.args().to_string())
+            .tag(record.target());
+        if let (Some(file), Some(line)) = (record.file(), record.line()) {
+            entry = entry.field("location", format!("{}:{}", file, line));
+        }
+        self.core.write(&entry);
+    }
+
+    fn flush(&self) {
+        self.core.flush();
+    }
+}
+
+#[cfg(feature = "tracing-layer")]
+pub use self::tracing_layer::TracingLayer;
+
+#[cfg(feature = "tracing-layer")]
+mod tracing_layer {
+    use std::fmt;
+    use std::sync::Arc;
+
+    use tracing::field::{Field, Visit};
+    use tracing::{Event, Metadata, Subscriber};
+    use tracing_subscriber::layer::{Context, Layer};
+
+    use crate::log_manager::{LogCore, LogLevel, LogRecord};
+
+    impl From<tracing::Level> for LogLevel {
+        fn from(level: tracing::Level) -> LogLevel {
+            match level {
+                tracing::Level::ERROR => LogLevel::Error,
+                tracing::Level::WARN => LogLevel::Warn,
+                tracing::Level::INFO => LogLevel::Info,
+                _ => LogLevel::Debug,
+            }
+        }
+    }
+
+    /// `tracing` layer forwarding events into a LogManager's sinks.
+    ///
+    /// The event's `message` becomes the record message and every other field
+    /// is attached as a key/value field.
+    pub struct TracingLayer {
+        core: Arc<LogCore>,
+    }
+
+    impl TracingLayer {
+        pub(crate) fn new(core: Arc<LogCore>) -> TracingLayer {
+            TracingLayer { core }
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for TracingLayer {
+        fn enabled(&self, metadata: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+            self.core.enabled((*metadata.level()).into())
+        }
+
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            let metadata = event.metadata();
+            let mut visitor = RecordVisitor {
+                record: LogRecord::new((*metadata.level()).into(), String::new())
+                    .tag(metadata.target()),
+            };
+            event.record(&mut visitor);
+            self.core.write(&visitor.record);
+        }
+    }
+
+    struct RecordVisitor {
+        record: LogRecord,
+    }
+
+    impl Visit for RecordVisitor {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "message" {
+                self.record.message = String::from(value);
+            } else {
+                self.record
+                    .fields
+                    .push((String::from(field.name()), String::from(value)));
+            }
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                self.record.message = format!("{:?}", value);
+            } else {
+                self.record
+                    .fields
+                    .push((String::from(field.name()), format!("{:?}", value)));
+            }
+        }
+    }
+}
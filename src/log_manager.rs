@@ -1,10 +1,24 @@
-use std::fs::File;
-use std::fs::OpenOptions;
-use std::io::prelude::*;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicI8, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use super::log_bridge::LogBridge;
+use super::log_rotation::{RotatingFileSink, RotationConfig};
+use super::log_sink::{JsonLinesSink, LogSink, StderrSink};
 use super::manager::Manager;
 
-const LOG_LEVEL: LogLevel = LogLevel::Debug;
+const DEFAULT_LOG_LEVEL: LogLevel = LogLevel::Debug;
+const DEFAULT_TAG: &str = "gears";
+
+/// Environment variable that overrides the starting log level, e.g. `GEARS_LOG_LEVEL=warn`.
+pub const LOG_LEVEL_ENV: &str = "GEARS_LOG_LEVEL";
 
 impl Manager for LogManager {
     fn m_type(&self) -> &str {
@@ -12,27 +26,307 @@ impl Manager for LogManager {
     }
 }
 
-enum LogLevel {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogLevel {
     Error = 3,
     Warn = 2,
     Info = 1,
     Debug = 0,
 }
 
+impl LogLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warn => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+        }
+    }
+
+    fn from_i8(level: i8) -> LogLevel {
+        match level {
+            3 => LogLevel::Error,
+            2 => LogLevel::Warn,
+            1 => LogLevel::Info,
+            _ => LogLevel::Debug,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// Returned when a string doesn't name a log level.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseLevelError(String);
+
+impl fmt::Display for ParseLevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unknown log level \"{}\"", self.0)
+    }
+}
+
+impl Error for ParseLevelError {}
+
+impl FromStr for LogLevel {
+    type Err = ParseLevelError;
+
+    fn from_str(s: &str) -> Result<LogLevel, ParseLevelError> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Ok(LogLevel::Error),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            _ => Err(ParseLevelError(String::from(s))),
+        }
+    }
+}
+
+/// A single log entry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogRecord {
+    pub timestamp: DateTime<Utc>,
+    pub level: LogLevel,
+    /// Module or manager that produced the record, usually `Manager::m_type`.
+    pub tag: String,
+    pub message: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl LogRecord {
+    pub fn new<M: Into<String>>(level: LogLevel, message: M) -> LogRecord {
+        LogRecord {
+            timestamp: Utc::now(),
+            level,
+            tag: String::from(DEFAULT_TAG),
+            message: message.into(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn error<M: Into<String>>(message: M) -> LogRecord {
+        LogRecord::new(LogLevel::Error, message)
+    }
+
+    pub fn warn<M: Into<String>>(message: M) -> LogRecord {
+        LogRecord::new(LogLevel::Warn, message)
+    }
+
+    pub fn info<M: Into<String>>(message: M) -> LogRecord {
+        LogRecord::new(LogLevel::Info, message)
+    }
+
+    pub fn debug<M: Into<String>>(message: M) -> LogRecord {
+        LogRecord::new(LogLevel::Debug, message)
+    }
+
+    /// Set the module or manager tag.
+    pub fn tag(mut self, tag: &str) -> LogRecord {
+        self.tag = String::from(tag);
+        self
+    }
+
+    /// Attach a key/value field.
+    pub fn field<V: fmt::Display>(mut self, key: &str, value: V) -> LogRecord {
+        self.fields.push((String::from(key), value.to_string()));
+        self
+    }
+
+    /// Timestamp in RFC 3339 format with millisecond precision.
+    pub fn timestamp_string(&self) -> String {
+        self.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true)
+    }
+}
+
+impl fmt::Display for LogRecord {
+    /// Format as `<timestamp> <LEVEL> [<tag>] <message> key=value ...`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {:<5} [{}] {}",
+            self.timestamp_string(),
+            self.level,
+            self.tag,
+            self.message
+        )?;
+        for (key, value) in &self.fields {
+            write!(f, " {}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors raised while setting up or writing logs.
+#[derive(Debug)]
+pub enum LogError {
+    /// A log file couldn't be opened or created.
+    Open { path: PathBuf, source: io::Error },
+    /// A log file couldn't be rotated.
+    Rotate { path: PathBuf, source: io::Error },
+    /// A record couldn't be written or flushed.
+    Write(io::Error),
+}
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogError::Open { path, source } => {
+                write!(f, "Couldn't open log file {}: {}", path.display(), source)
+            }
+            LogError::Rotate { path, source } => {
+                write!(f, "Couldn't rotate log file {}: {}", path.display(), source)
+            }
+            LogError::Write(source) => write!(f, "Couldn't write to log sink: {}", source),
+        }
+    }
+}
+
+impl Error for LogError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LogError::Open { source, .. } | LogError::Rotate { source, .. } => Some(source),
+            LogError::Write(source) => Some(source),
+        }
+    }
+}
+
+impl From<io::Error> for LogError {
+    fn from(e: io::Error) -> LogError {
+        LogError::Write(e)
+    }
+}
+
+struct SinkSlot {
+    sink: Box<dyn LogSink>,
+    failing: AtomicBool,
+}
+
+impl SinkSlot {
+    fn new(sink: Box<dyn LogSink>) -> SinkSlot {
+        SinkSlot {
+            sink,
+            failing: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Level filter and sinks, shared between a LogManager and the global
+/// `log`/`tracing` bridges it installs.
+pub(crate) struct LogCore {
+    level: AtomicI8,
+    sinks: RwLock<Vec<Arc<SinkSlot>>>,
+    global: AtomicBool,
+    last_error: Mutex<Option<LogError>>,
+}
+
+impl LogCore {
+    pub(crate) fn enabled(&self, level: LogLevel) -> bool {
+        level as i8 >= self.level.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn write(&self, record: &LogRecord) {
+        if !self.enabled(record.level) {
+            return;
+        }
+        for slot in self.sinks() {
+            let result = slot.sink.write(record);
+            self.report(&slot, result);
+        }
+    }
+
+    pub(crate) fn flush(&self) {
+        for slot in self.sinks() {
+            let result = slot.sink.flush();
+            self.report(&slot, result);
+        }
+    }
+
+    fn add_sink(&self, sink: Box<dyn LogSink>) {
+        self.sinks
+            .write()
+            .unwrap()
+            .push(Arc::new(SinkSlot::new(sink)));
+    }
+
+    /// The sinks as they are now. Sinks are called without the lock held, so
+    /// one that logs itself, e.g. through `log` or `tracing`, can't deadlock.
+    fn sinks(&self) -> Vec<Arc<SinkSlot>> {
+        self.sinks.read().unwrap().clone()
+    }
+
+    /// Keep the latest error for `LogManager::take_error`. Only the first
+    /// failure of a sink goes to stderr, until the sink succeeds again.
+    fn report(&self, slot: &SinkSlot, result: Result<(), LogError>) {
+        match result {
+            Ok(()) => slot.failing.store(false, Ordering::Relaxed),
+            Err(e) => {
+                if !slot.failing.swap(true, Ordering::Relaxed) {
+                    eprintln!("{}, suppressing further errors from this sink", e);
+                }
+                *self.last_error.lock().unwrap() = Some(e);
+            }
+        }
+    }
+}
+
 pub struct LogManager {
     started: bool,
-    file_handle: File,
+    core: Arc<LogCore>,
+}
+
+impl Default for LogManager {
+    fn default() -> LogManager {
+        LogManager::new()
+    }
 }
 
 impl LogManager {
+    /// Create a LogManager appending to `gears.log`, or to `GEARS_LOG_FILE` if it's set.
+    ///
+    /// If the file can't be opened, e.g. on a read-only filesystem, it logs
+    /// to stderr instead.
     pub fn new() -> LogManager {
+        LogManager::builder()
+            .file(RotationConfig::default())
+            .build_or_stderr()
+    }
+
+    /// Like `new`, but returns the error if the log file can't be opened.
+    pub fn try_new() -> Result<LogManager, LogError> {
+        LogManager::builder()
+            .file(RotationConfig::default())
+            .build()
+    }
+
+    pub fn builder() -> LogManagerBuilder {
+        LogManagerBuilder::default()
+    }
+
+    /// Create a LogManager writing to `sinks`.
+    ///
+    /// The level starts at `GEARS_LOG_LEVEL` if it's set, or `Debug` otherwise.
+    pub fn with_sinks(sinks: Vec<Box<dyn LogSink>>) -> LogManager {
+        let level = env::var(LOG_LEVEL_ENV)
+            .ok()
+            .and_then(|l| l.parse().ok())
+            .unwrap_or(DEFAULT_LOG_LEVEL);
         LogManager {
             started: false,
-            file_handle: OpenOptions::new()
-                .append(true)
-                .create(true)
-                .open("gears.log")
-                .unwrap(),
+            core: Arc::new(LogCore {
+                level: AtomicI8::new(level as i8),
+                sinks: RwLock::new(
+                    sinks
+                        .into_iter()
+                        .map(|sink| Arc::new(SinkSlot::new(sink)))
+                        .collect(),
+                ),
+                global: AtomicBool::new(false),
+                last_error: Mutex::new(None),
+            }),
         }
     }
     pub fn startup(&mut self) {
@@ -40,25 +334,348 @@ impl LogManager {
         self.started = true;
     }
     pub fn shutdown(mut self) {
+        self.core.flush();
         self.started = false
     }
+
+    /// Add another destination for records.
+    pub fn add_sink(&mut self, sink: Box<dyn LogSink>) {
+        self.core.add_sink(sink);
+    }
+
+    /// Return the most recent error raised by a sink, clearing it.
+    pub fn take_error(&self) -> Option<LogError> {
+        self.core.last_error.lock().unwrap().take()
+    }
+
+    /// Set the minimum level a record needs to be written.
+    pub fn set_level(&self, level: LogLevel) {
+        self.core.level.store(level as i8, Ordering::Relaxed);
+        if self.core.global.load(Ordering::Relaxed) {
+            log::set_max_level(level.into());
+        }
+    }
+
+    pub fn level(&self) -> LogLevel {
+        LogLevel::from_i8(self.core.level.load(Ordering::Relaxed))
+    }
+
+    /// Return true if records at `level` would be written.
+    pub fn enabled(&self, level: LogLevel) -> bool {
+        self.core.enabled(level)
+    }
+
+    /// Install this LogManager as the global `log` logger, so records from
+    /// dependencies and the `error!`..`debug!` macros reach the same sinks.
+    ///
+    /// `log` only allows one logger per process, so this fails if any logger
+    /// has been installed before.
+    pub fn install_global(&self) -> Result<(), log::SetLoggerError> {
+        log::set_boxed_logger(Box::new(LogBridge::new(self.core.clone())))?;
+        log::set_max_level(self.level().into());
+        self.core.global.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Return a `tracing` layer writing events to this LogManager's sinks.
+    #[cfg(feature = "tracing-layer")]
+    pub fn tracing_layer(&self) -> super::log_bridge::TracingLayer {
+        super::log_bridge::TracingLayer::new(self.core.clone())
+    }
+
+    /// Install a `tracing` subscriber made of this LogManager's layer as the
+    /// global default.
+    #[cfg(feature = "tracing-layer")]
+    pub fn install_tracing(&self) -> Result<(), tracing::subscriber::SetGlobalDefaultError> {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let subscriber = tracing_subscriber::Registry::default().with(self.tracing_layer());
+        tracing::subscriber::set_global_default(subscriber)
+    }
+
     pub fn error(&self, msg: String) {
-        self.write_message(msg, LogLevel::Error)
+        self.log(LogRecord::new(LogLevel::Error, msg))
     }
     pub fn warn(&self, msg: String) {
-        self.write_message(msg, LogLevel::Warn)
+        self.log(LogRecord::new(LogLevel::Warn, msg))
     }
     pub fn info(&self, msg: String) {
-        self.write_message(msg, LogLevel::Info)
+        self.log(LogRecord::new(LogLevel::Info, msg))
     }
     pub fn debug(&self, msg: String) {
-        self.write_message(msg, LogLevel::Debug)
+        self.log(LogRecord::new(LogLevel::Debug, msg))
+    }
+
+    /// Write `record` to every sink if its level passes the filter.
+    pub fn log(&self, record: LogRecord) {
+        self.core.write(&record)
+    }
+}
+
+enum SinkConfig {
+    File(RotationConfig),
+    JsonLines(PathBuf),
+    Stderr,
+    Custom(Box<dyn LogSink>),
+}
+
+/// Builds a LogManager from a set of sinks, opening any files in `build`.
+///
+/// ```ignore
+/// let log_manager = LogManager::builder()
+///     .level(LogLevel::Info)
+///     .file(RotationConfig::new("logs/gears.log"))
+///     .stderr()
+///     .build()?;
+/// ```
+#[derive(Default)]
+pub struct LogManagerBuilder {
+    level: Option<LogLevel>,
+    sinks: Vec<SinkConfig>,
+}
+
+impl LogManagerBuilder {
+    /// Set the starting level, overriding `GEARS_LOG_LEVEL`.
+    pub fn level(mut self, level: LogLevel) -> LogManagerBuilder {
+        self.level = Some(level);
+        self
+    }
+
+    /// Write plain text lines to a rotating file.
+    pub fn file(mut self, config: RotationConfig) -> LogManagerBuilder {
+        self.sinks.push(SinkConfig::File(config));
+        self
+    }
+
+    /// Write JSON lines to `path`.
+    pub fn json_lines<P: Into<PathBuf>>(mut self, path: P) -> LogManagerBuilder {
+        self.sinks.push(SinkConfig::JsonLines(path.into()));
+        self
+    }
+
+    /// Write plain text lines to stderr.
+    pub fn stderr(mut self) -> LogManagerBuilder {
+        self.sinks.push(SinkConfig::Stderr);
+        self
+    }
+
+    /// Write to an already constructed sink, such as a `RingBufferSink`.
+    pub fn sink(mut self, sink: Box<dyn LogSink>) -> LogManagerBuilder {
+        self.sinks.push(SinkConfig::Custom(sink));
+        self
+    }
+
+    /// Open every sink, failing on the first one that can't be opened.
+    pub fn build(self) -> Result<LogManager, LogError> {
+        let level = self.level;
+        let sinks = self
+            .sinks
+            .into_iter()
+            .map(open_sink)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(LogManagerBuilder::finish(sinks, level))
+    }
+
+    /// Open every sink, replacing any that can't be opened with stderr.
+    ///
+    /// The errors are logged once the LogManager exists, and the last one
+    /// is available from `LogManager::take_error`.
+    pub fn build_or_stderr(self) -> LogManager {
+        let level = self.level;
+        let mut sinks: Vec<Box<dyn LogSink>> = Vec::new();
+        let mut errors = Vec::new();
+        let mut has_stderr = false;
+        for config in self.sinks {
+            has_stderr |= matches!(config, SinkConfig::Stderr);
+            match open_sink(config) {
+                Ok(sink) => sinks.push(sink),
+                Err(e) => errors.push(e),
+            }
+        }
+        if (!errors.is_empty() || sinks.is_empty()) && !has_stderr {
+            sinks.push(Box::new(StderrSink));
+        }
+
+        let log_manager = LogManagerBuilder::finish(sinks, level);
+        for e in errors {
+            log_manager.log(
+                LogRecord::error(format!("{}, falling back to stderr", e))
+                    .tag(log_manager.m_type()),
+            );
+            *log_manager.core.last_error.lock().unwrap() = Some(e);
+        }
+        log_manager
+    }
+
+    fn finish(sinks: Vec<Box<dyn LogSink>>, level: Option<LogLevel>) -> LogManager {
+        let log_manager = LogManager::with_sinks(sinks);
+        if let Some(level) = level {
+            log_manager.set_level(level);
+        }
+        log_manager
+    }
+}
+
+fn open_sink(config: SinkConfig) -> Result<Box<dyn LogSink>, LogError> {
+    Ok(match config {
+        SinkConfig::File(config) => Box::new(RotatingFileSink::new(config)?),
+        SinkConfig::JsonLines(path) => Box::new(JsonLinesSink::new(path)?),
+        SinkConfig::Stderr => Box::new(StderrSink),
+        SinkConfig::Custom(sink) => sink,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_sink::RingBufferSink;
+
+    #[test]
+    fn records_below_the_level_are_filtered() {
+        let sink = RingBufferSink::new(8);
+        let records = sink.handle();
+        let log_manager = LogManager::with_sinks(vec![Box::new(sink)]);
+        log_manager.set_level(LogLevel::Warn);
+
+        log_manager.info(String::from("hidden"));
+        log_manager.warn(String::from("shown"));
+        log_manager.error(String::from("also shown"));
+
+        let messages: Vec<String> = records.records().into_iter().map(|r| r.message).collect();
+        assert_eq!(messages, vec!["shown", "also shown"]);
+    }
+
+    #[test]
+    fn record_is_formatted_with_level_tag_and_fields() {
+        let record = LogRecord::new(LogLevel::Info, "Game started")
+            .tag("game_manager")
+            .field("entities", 1);
+        let line = format!("{}", record);
+
+        assert!(line.ends_with("INFO  [game_manager] Game started entities=1"));
+    }
+
+    #[test]
+    fn log_records_are_forwarded_through_the_bridge() {
+        let sink = RingBufferSink::new(8);
+        let records = sink.handle();
+        let log_manager = LogManager::with_sinks(vec![Box::new(sink)]);
+        let bridge = LogBridge::new(log_manager.core.clone());
+
+        log::Log::log(
+            &bridge,
+            &log::Record::builder()
+                .args(format_args!("Archetype created"))
+                .level(log::Level::Debug)
+                .target("legion")
+                .build(),
+        );
+
+        let record = &records.records()[0];
+        assert_eq!(record.level, LogLevel::Debug);
+        assert_eq!(record.tag, "legion");
+        assert_eq!(record.message, "Archetype created");
     }
-    fn write_message(&self, msg: String, level: LogLevel) {
-        if level as i8 >= LOG_LEVEL as i8 {
-            if let Err(e) = writeln!(&self.file_handle, "{}", msg) {
-                eprintln!("Couldn't write to file: {}", e);
+
+    #[cfg(feature = "tracing-layer")]
+    #[test]
+    fn tracing_events_are_forwarded_through_the_layer() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let sink = RingBufferSink::new(8);
+        let records = sink.handle();
+        let log_manager = LogManager::with_sinks(vec![Box::new(sink)]);
+        let subscriber = tracing_subscriber::Registry::default().with(log_manager.tracing_layer());
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(target: "physics", bodies = 3, name = "crate", "Fell behind");
+        });
+
+        let record = &records.records()[0];
+        assert_eq!(record.level, LogLevel::Warn);
+        assert_eq!(record.tag, "physics");
+        assert_eq!(record.message, "Fell behind");
+        assert_eq!(
+            record.fields,
+            vec![
+                (String::from("bodies"), String::from("3")),
+                (String::from("name"), String::from("crate")),
+            ]
+        );
+    }
+
+    #[test]
+    fn try_new_fails_when_the_file_cannot_be_opened() {
+        let result = LogManager::builder().file(RotationConfig::new("")).build();
+
+        assert!(matches!(result, Err(LogError::Open { .. })));
+    }
+
+    #[test]
+    fn unopenable_file_falls_back_to_stderr() {
+        let log_manager = LogManager::builder()
+            .file(RotationConfig::new(""))
+            .build_or_stderr();
+
+        assert!(matches!(
+            log_manager.take_error(),
+            Some(LogError::Open { .. })
+        ));
+        assert!(log_manager.take_error().is_none());
+    }
+
+    #[test]
+    fn write_failures_are_kept_for_take_error() {
+        struct BrokenSink;
+        impl LogSink for BrokenSink {
+            fn write(&self, _: &LogRecord) -> Result<(), LogError> {
+                Err(LogError::Write(io::Error::from(io::ErrorKind::WriteZero)))
             }
         }
+        let log_manager = LogManager::with_sinks(vec![Box::new(BrokenSink)]);
+
+        log_manager.info(String::from("lost"));
+        log_manager.info(String::from("also lost"));
+
+        assert!(matches!(log_manager.take_error(), Some(LogError::Write(_))));
+    }
+
+    #[test]
+    fn sinks_can_log_and_add_sinks_while_writing() {
+        /// The core to log through and the sink to add, once the manager exists.
+        type Pending = Arc<Mutex<Option<(Arc<LogCore>, RingBufferSink)>>>;
+        /// Logs a record and adds another sink during its first write.
+        struct Reentrant(Pending);
+        impl LogSink for Reentrant {
+            fn write(&self, _: &LogRecord) -> Result<(), LogError> {
+                let pending = self.0.lock().unwrap().take();
+                if let Some((core, added)) = pending {
+                    core.write(&LogRecord::info("nested"));
+                    core.add_sink(Box::new(added));
+                }
+                Ok(())
+            }
+        }
+        let buffer = RingBufferSink::new(8);
+        let records = buffer.handle();
+        let added = RingBufferSink::new(8);
+        let added_records = added.handle();
+        let pending = Arc::new(Mutex::new(None));
+        let log_manager =
+            LogManager::with_sinks(vec![Box::new(buffer), Box::new(Reentrant(pending.clone()))]);
+        *pending.lock().unwrap() = Some((log_manager.core.clone(), added));
+
+        log_manager.info(String::from("outer"));
+        log_manager.info(String::from("after"));
+
+        assert_eq!(records.records().len(), 3);
+        assert_eq!(added_records.records().len(), 1);
+    }
+
+    #[test]
+    fn level_can_be_parsed() {
+        assert_eq!("warn".parse(), Ok(LogLevel::Warn));
+        assert_eq!("DEBUG".parse(), Ok(LogLevel::Debug));
+        assert!("verbose".parse::<LogLevel>().is_err());
     }
 }
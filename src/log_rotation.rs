@@ -0,0 +1,479 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use chrono::Utc;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use super::log_manager::{LogError, LogRecord};
+use super::log_sink::LogSink;
+
+/// Environment variable that overrides the default log file path.
+pub const LOG_FILE_ENV: &str = "GEARS_LOG_FILE";
+const DEFAULT_LOG_FILE: &str = "gears.log";
+
+/// How many rotated log files to keep around.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Retention {
+    KeepAll,
+    /// Keep the newest N rotated files.
+    KeepFiles(usize),
+    /// Keep rotated files modified within the last N days.
+    KeepDays(u64),
+}
+
+/// Where the log file lives and when it gets rotated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RotationConfig {
+    pub path: PathBuf,
+    /// Rotate once the file would grow past this many bytes.
+    pub max_bytes: Option<u64>,
+    /// Rotate any existing file when the sink is created, giving each session its own file.
+    pub rotate_on_startup: bool,
+    pub retention: Retention,
+    /// Gzip rotated files.
+    pub compress: bool,
+}
+
+impl RotationConfig {
+    pub fn new<P: Into<PathBuf>>(path: P) -> RotationConfig {
+        RotationConfig {
+            path: path.into(),
+            max_bytes: None,
+            rotate_on_startup: false,
+            retention: Retention::KeepAll,
+            compress: false,
+        }
+    }
+}
+
+impl Default for RotationConfig {
+    /// `gears.log` in the working directory, or `GEARS_LOG_FILE` if it's set,
+    /// appended to forever.
+    fn default() -> RotationConfig {
+        let path = env::var_os(LOG_FILE_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_LOG_FILE));
+        RotationConfig::new(path)
+    }
+}
+
+struct ActiveFile {
+    file: Option<File>,
+    size: u64,
+}
+
+/// Writes records as plain text lines to a file, rotating it by size or by
+/// session.
+///
+/// A rotated file is renamed to `<stem>.<timestamp>.<ext>`, e.g.
+/// `gears.20201018T112700.123.log`, next to the live file.
+pub struct RotatingFileSink {
+    config: RotationConfig,
+    active: Mutex<ActiveFile>,
+}
+
+impl RotatingFileSink {
+    pub fn new(config: RotationConfig) -> Result<RotatingFileSink, LogError> {
+        if let Some(parent) = config.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent).map_err(|source| LogError::Open {
+                    path: config.path.clone(),
+                    source,
+                })?;
+            }
+        }
+
+        let sink = RotatingFileSink {
+            config,
+            active: Mutex::new(ActiveFile {
+                file: None,
+                size: 0,
+            }),
+        };
+        {
+            let mut active = sink.active.lock().unwrap();
+            let existing = fs::metadata(&sink.config.path)
+                .map(|m| m.len())
+                .unwrap_or(0);
+            if sink.config.rotate_on_startup && existing > 0 {
+                sink.rotate(&mut active)?;
+            } else {
+                sink.open(&mut active)?;
+            }
+        }
+        Ok(sink)
+    }
+
+    /// Path of the file currently being written.
+    pub fn path(&self) -> &Path {
+        &self.config.path
+    }
+
+    /// Rotate the current file now, whatever its size.
+    pub fn rotate_now(&self) -> Result<(), LogError> {
+        let mut active = self.active.lock().unwrap();
+        self.rotate(&mut active)
+    }
+
+    fn open(&self, active: &mut ActiveFile) -> Result<(), LogError> {
+        let open_error = |source: io::Error| LogError::Open {
+            path: self.config.path.clone(),
+            source,
+        };
+        let file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.config.path)
+            .map_err(open_error)?;
+        active.size = file.metadata().map_err(open_error)?.len();
+        active.file = Some(file);
+        Ok(())
+    }
+
+    fn rotate(&self, active: &mut ActiveFile) -> Result<(), LogError> {
+        let rotate_error = |source: io::Error| LogError::Rotate {
+            path: self.config.path.clone(),
+            source,
+        };
+        if let Some(mut file) = active.file.take() {
+            file.flush()?;
+        }
+        if self.config.path.exists() {
+            let rotated = self.rotated_path();
+            fs::rename(&self.config.path, &rotated).map_err(rotate_error)?;
+            if self.config.compress {
+                compress(&rotated).map_err(rotate_error)?;
+            }
+        }
+        self.open(active)?;
+        self.apply_retention().map_err(rotate_error)
+    }
+
+    fn rotated_path(&self) -> PathBuf {
+        let (stem, ext) = self.file_parts();
+        let timestamp = Utc::now().format("%Y%m%dT%H%M%S%.3f");
+        let mut attempt = 0;
+        loop {
+            let name = match attempt {
+                0 => format!("{}.{}{}", stem, timestamp, ext),
+                n => format!("{}.{}-{}{}", stem, timestamp, n, ext),
+            };
+            let candidate = self.config.path.with_file_name(name);
+            let compressed = gz_path(&candidate);
+            if !candidate.exists() && !compressed.exists() {
+                return candidate;
+            }
+            attempt += 1;
+        }
+    }
+
+    /// File stem and extension (including the dot) of the live file.
+    fn file_parts(&self) -> (String, String) {
+        let stem = self
+            .config
+            .path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let ext = self
+            .config
+            .path
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+        (stem, ext)
+    }
+
+    /// Rotated files belonging to this sink, oldest first.
+    fn rotated_files(&self) -> io::Result<Vec<(PathBuf, SystemTime)>> {
+        let (stem, ext) = self.file_parts();
+        let dir = match self.config.path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let live = self.config.path.file_name();
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if Some(name.as_os_str()) == live {
+                continue;
+            }
+            if is_rotated_name(&name.to_string_lossy(), &stem, &ext) {
+                let modified = entry.metadata()?.modified()?;
+                files.push((entry.path(), modified));
+            }
+        }
+        files.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(files)
+    }
+
+    fn apply_retention(&self) -> io::Result<()> {
+        let expired: Vec<PathBuf> = match self.config.retention {
+            Retention::KeepAll => return Ok(()),
+            Retention::KeepFiles(n) => {
+                let files = self.rotated_files()?;
+                let excess = files.len().saturating_sub(n);
+                files.into_iter().take(excess).map(|(p, _)| p).collect()
+            }
+            Retention::KeepDays(days) => {
+                let max_age = Duration::from_secs(days * 24 * 60 * 60);
+                let now = SystemTime::now();
+                self.rotated_files()?
+                    .into_iter()
+                    .filter(|(_, modified)| {
+                        now.duration_since(*modified).unwrap_or_default() > max_age
+                    })
+                    .map(|(p, _)| p)
+                    .collect()
+            }
+        };
+        for path in expired {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+impl LogSink for RotatingFileSink {
+    fn write(&self, record: &LogRecord) -> Result<(), LogError> {
+        let line = format!("{}\n", record);
+        let mut active = self.active.lock().unwrap();
+        let rotated = match self.config.max_bytes {
+            Some(max_bytes) if active.size > 0 && active.size + line.len() as u64 > max_bytes => {
+                self.rotate(&mut active)
+            }
+            _ => Ok(()),
+        };
+        // Write the record even if rotating failed, reopening the live file if
+        // need be, and only then report the failure
+        if active.file.is_none() {
+            self.open(&mut active)?;
+        }
+        if let Some(file) = active.file.as_mut() {
+            file.write_all(line.as_bytes())?;
+        }
+        active.size += line.len() as u64;
+        rotated
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        match self.active.lock().unwrap().file.as_mut() {
+            Some(file) => Ok(file.flush()?),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Whether `name` is one `rotated_path` makes for the live file `stem` + `ext`:
+/// `<stem>.<timestamp>[-<n>]<ext>[.gz]`, with the timestamp as
+/// `%Y%m%dT%H%M%S%.3f`.
+fn is_rotated_name(name: &str, stem: &str, ext: &str) -> bool {
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    let middle = match name
+        .strip_prefix(stem)
+        .and_then(|n| n.strip_prefix('.'))
+        .and_then(|n| n.strip_suffix(ext))
+    {
+        Some(middle) => middle,
+        None => return false,
+    };
+    let (timestamp, attempt) = match middle.find('-') {
+        Some(i) => (&middle[..i], Some(&middle[i + 1..])),
+        None => (middle, None),
+    };
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let timestamp_matches = timestamp.len() == 19
+        && timestamp.is_ascii()
+        && digits(&timestamp[0..8])
+        && &timestamp[8..9] == "T"
+        && digits(&timestamp[9..15])
+        && &timestamp[15..16] == "."
+        && digits(&timestamp[16..19]);
+    timestamp_matches
+        && match attempt {
+            Some(n) => digits(n),
+            None => true,
+        }
+}
+
+fn gz_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".gz");
+    PathBuf::from(name)
+}
+
+/// Gzip `path` into `<path>.gz` and remove the original.
+fn compress(path: &Path) -> io::Result<()> {
+    let mut input = File::open(path)?;
+    let output = File::create(gz_path(path))?;
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    fs::remove_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_manager::LogLevel;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("gears-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn file_count(dir: &Path) -> usize {
+        fs::read_dir(dir).unwrap().count()
+    }
+
+    #[test]
+    fn file_is_rotated_when_it_would_exceed_max_bytes() {
+        let dir = test_dir("rotate-size");
+        let mut config = RotationConfig::new(dir.join("gears.log"));
+        config.max_bytes = Some(64);
+        let sink = RotatingFileSink::new(config).unwrap();
+
+        for _ in 0..3 {
+            sink.write(&LogRecord::new(LogLevel::Info, "a message long enough"))
+                .unwrap();
+        }
+
+        assert_eq!(file_count(&dir), 3);
+        assert!(fs::metadata(dir.join("gears.log")).unwrap().len() <= 64);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn existing_file_is_rotated_on_startup() {
+        let dir = test_dir("rotate-session");
+        fs::write(dir.join("gears.log"), "previous session\n").unwrap();
+        let mut config = RotationConfig::new(dir.join("gears.log"));
+        config.rotate_on_startup = true;
+        RotatingFileSink::new(config).unwrap();
+
+        assert_eq!(file_count(&dir), 2);
+        assert_eq!(fs::metadata(dir.join("gears.log")).unwrap().len(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn only_the_newest_files_are_kept() {
+        let dir = test_dir("retention");
+        let mut config = RotationConfig::new(dir.join("gears.log"));
+        config.retention = Retention::KeepFiles(2);
+        let sink = RotatingFileSink::new(config).unwrap();
+
+        for _ in 0..4 {
+            sink.write(&LogRecord::new(LogLevel::Info, "message"))
+                .unwrap();
+            sink.rotate_now().unwrap();
+        }
+
+        assert_eq!(file_count(&dir), 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn retention_leaves_other_files_alone() {
+        let dir = test_dir("retention-stray");
+        let stray = [
+            "gears.backup.log",
+            "gears.old.log.gz",
+            "gears.20200101T000000.000.txt",
+        ];
+        for name in &stray {
+            fs::write(dir.join(name), "not a rotated file\n").unwrap();
+        }
+        let mut config = RotationConfig::new(dir.join("gears.log"));
+        config.retention = Retention::KeepFiles(0);
+        let sink = RotatingFileSink::new(config).unwrap();
+
+        sink.write(&LogRecord::new(LogLevel::Info, "message"))
+            .unwrap();
+        sink.rotate_now().unwrap();
+
+        for name in &stray {
+            assert!(dir.join(name).exists(), "{} was removed", name);
+        }
+        assert_eq!(file_count(&dir), stray.len() + 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn records_are_written_when_rotation_fails() {
+        let dir = test_dir("rotation-fails");
+        // Counts as a rotated file but can't be removed by retention
+        fs::create_dir(dir.join("gears.20200101T000000.000.log")).unwrap();
+        let mut config = RotationConfig::new(dir.join("gears.log"));
+        config.max_bytes = Some(1);
+        config.retention = Retention::KeepFiles(0);
+        let sink = RotatingFileSink::new(config).unwrap();
+
+        sink.write(&LogRecord::new(LogLevel::Info, "first"))
+            .unwrap();
+        let result = sink.write(&LogRecord::new(LogLevel::Info, "second"));
+        sink.flush().unwrap();
+
+        assert!(matches!(result, Err(LogError::Rotate { .. })));
+        let live = fs::read_to_string(dir.join("gears.log")).unwrap();
+        assert!(live.contains("second"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn only_generated_names_count_as_rotated() {
+        assert!(is_rotated_name(
+            "gears.20201018T120000.123.log",
+            "gears",
+            ".log"
+        ));
+        assert!(is_rotated_name(
+            "gears.20201018T120000.123-2.log.gz",
+            "gears",
+            ".log"
+        ));
+        assert!(is_rotated_name("gears.20201018T120000.123", "gears", ""));
+        assert!(!is_rotated_name("gears.backup.log", "gears", ".log"));
+        assert!(!is_rotated_name(
+            "gears.20201018T120000.123-.log",
+            "gears",
+            ".log"
+        ));
+        assert!(!is_rotated_name(
+            "other.20201018T120000.123.log",
+            "gears",
+            ".log"
+        ));
+    }
+
+    #[test]
+    fn rotated_files_can_be_compressed() {
+        let dir = test_dir("compress");
+        let mut config = RotationConfig::new(dir.join("gears.log"));
+        config.compress = true;
+        let sink = RotatingFileSink::new(config).unwrap();
+
+        sink.write(&LogRecord::new(LogLevel::Info, "message"))
+            .unwrap();
+        sink.rotate_now().unwrap();
+
+        let compressed = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".log.gz"))
+            .count();
+        assert_eq!(compressed, 1);
+        assert_eq!(file_count(&dir), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Map, Value};
+
+use super::log_manager::{LogError, LogRecord};
+
+/// Destination for log records. A `LogManager` writes every record that
+/// passes its level filter to each of its sinks.
+pub trait LogSink: Send + Sync {
+    fn write(&self, record: &LogRecord) -> Result<(), LogError>;
+
+    fn flush(&self) -> Result<(), LogError> {
+        Ok(())
+    }
+}
+
+fn open_append<P: AsRef<Path>>(path: P) -> Result<File, LogError> {
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path.as_ref())
+        .map_err(|source| LogError::Open {
+            path: path.as_ref().to_path_buf(),
+            source,
+        })
+}
+
+/// Writes records as plain text lines to a file.
+pub struct FileSink {
+    file: Mutex<File>,
+}
+
+impl FileSink {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<FileSink, LogError> {
+        Ok(FileSink {
+            file: Mutex::new(open_append(path)?),
+        })
+    }
+}
+
+impl LogSink for FileSink {
+    fn write(&self, record: &LogRecord) -> Result<(), LogError> {
+        let mut file = self.file.lock().unwrap();
+        Ok(writeln!(file, "{}", record)?)
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        Ok(self.file.lock().unwrap().flush()?)
+    }
+}
+
+/// Writes records as plain text lines to stderr.
+pub struct StderrSink;
+
+impl LogSink for StderrSink {
+    fn write(&self, record: &LogRecord) -> Result<(), LogError> {
+        Ok(writeln!(io::stderr(), "{}", record)?)
+    }
+}
+
+/// Keeps the most recent records in memory.
+///
+/// The records are shared with any `RingBuffer` handles taken from the sink,
+/// so they can still be read after the sink has been given to a `LogManager`.
+pub struct RingBufferSink {
+    buffer: RingBuffer,
+}
+
+impl RingBufferSink {
+    pub fn new(capacity: usize) -> RingBufferSink {
+        RingBufferSink {
+            buffer: RingBuffer {
+                records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+                capacity,
+            },
+        }
+    }
+
+    /// Return a handle for reading the buffered records.
+    pub fn handle(&self) -> RingBuffer {
+        self.buffer.clone()
+    }
+}
+
+impl LogSink for RingBufferSink {
+    fn write(&self, record: &LogRecord) -> Result<(), LogError> {
+        self.buffer.push(record.clone());
+        Ok(())
+    }
+}
+
+/// Read handle for the records held by a `RingBufferSink`.
+#[derive(Clone)]
+pub struct RingBuffer {
+    records: Arc<Mutex<VecDeque<LogRecord>>>,
+    capacity: usize,
+}
+
+impl RingBuffer {
+    /// Return a copy of the buffered records, oldest first.
+    pub fn records(&self) -> Vec<LogRecord> {
+        self.records.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Remove all buffered records.
+    pub fn clear(&self) {
+        self.records.lock().unwrap().clear();
+    }
+
+    fn push(&self, record: LogRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut records = self.records.lock().unwrap();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+}
+
+/// Writes records to a file as one JSON object per line.
+pub struct JsonLinesSink {
+    file: Mutex<File>,
+}
+
+impl JsonLinesSink {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<JsonLinesSink, LogError> {
+        Ok(JsonLinesSink {
+            file: Mutex::new(open_append(path)?),
+        })
+    }
+}
+
+impl LogSink for JsonLinesSink {
+    fn write(&self, record: &LogRecord) -> Result<(), LogError> {
+        let mut file = self.file.lock().unwrap();
+        Ok(writeln!(file, "{}", to_json(record))?)
+    }
+
+    fn flush(&self) -> Result<(), LogError> {
+        Ok(self.file.lock().unwrap().flush()?)
+    }
+}
+
+fn to_json(record: &LogRecord) -> Value {
+    let fields: Map<String, Value> = record
+        .fields
+        .iter()
+        .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+        .collect();
+    json!({
+        "timestamp": record.timestamp_string(),
+        "level": record.level.as_str(),
+        "tag": record.tag,
+        "message": record.message,
+        "fields": fields,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_manager::LogLevel;
+
+    #[test]
+    fn ring_buffer_drops_oldest_records() {
+        let sink = RingBufferSink::new(2);
+        let handle = sink.handle();
+        for msg in &["first", "second", "third"] {
+            sink.write(&LogRecord::new(LogLevel::Info, *msg)).unwrap();
+        }
+
+        let messages: Vec<String> = handle.records().into_iter().map(|r| r.message).collect();
+        assert_eq!(messages, vec!["second", "third"]);
+    }
+
+    #[test]
+    fn json_record_contains_fields() {
+        let record = LogRecord::new(LogLevel::Warn, "Frame over budget")
+            .tag("game_manager")
+            .field("loop_ms", 20);
+        let value = to_json(&record);
+
+        assert_eq!(value["level"], "WARN");
+        assert_eq!(value["tag"], "game_manager");
+        assert_eq!(value["message"], "Frame over budget");
+        assert_eq!(value["fields"]["loop_ms"], "20");
+    }
+}
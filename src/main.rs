@@ -1,40 +1,64 @@
 #![allow(unused_variables, dead_code)]
 
-use display_manager::DisplayManager;
-use game_manager::GameManager;
+use gears::component::{Transform, Velocity};
+use gears::display_manager::DisplayManager;
+use gears::game_manager::GameManager;
+use gears::game_state::GameControl;
+use gears::input_manager::InputManager;
+use gears::log_manager::LogManager;
+use gears::stage::Stage;
+use gears::time::FixedTime;
 use legion::*;
-use log_manager::LogManager;
+use log::{debug, error, info, warn};
 use std::time::Instant;
 
-mod component;
-mod display_manager;
-mod game_manager;
-mod log_manager;
-mod manager;
-mod quaternion;
-mod vector;
+/// Stop the demo after three seconds of simulation.
+#[system]
+fn stop_demo(#[resource] time: &FixedTime, #[resource] control: &mut GameControl) {
+    if time.tick() as f32 * time.step_secs() >= 3.0 {
+        control.request_stop();
+    }
+}
+
+#[system(for_each)]
+fn print_transforms(transform: &Transform) {
+    debug!("{:?}", transform);
+}
 
 fn main() {
     let mut log_manager: LogManager = LogManager::new();
     log_manager.startup();
-    log_manager.error(String::from("Gears.main(): This is an error"));
-    log_manager.warn(String::from("Gears.main(): This is a warning"));
-    log_manager.info(String::from("Gears.main(): This is an info"));
-    log_manager.debug(String::from("Gears.main(): This is a debug"));
+    if let Err(e) = log_manager.install_global() {
+        eprintln!("Couldn't install global logger: {}", e);
+    }
+    error!("This is an error");
+    warn!("This is a warning");
+    info!("This is an info");
+    debug!("This is a debug");
 
     let mut display_manager: DisplayManager = DisplayManager::new(&log_manager);
     display_manager.startup();
 
+    let mut input_manager: InputManager = InputManager::new(&log_manager);
+    input_manager.startup();
+
     let mut world = World::default();
+    world.push((Transform::identity(), Velocity::new(1.0, 1.0, 1.0)));
 
     let mut game_manager: GameManager = GameManager::new(&log_manager, &mut world);
-    game_manager.startup();
+    game_manager
+        .attach_display(display_manager)
+        .attach_input(input_manager)
+        .add_system(Stage::Update, stop_demo_system())
+        .add_system(Stage::Render, print_transforms_system());
+    game_manager
+        .startup()
+        .expect("Couldn't schedule the game's systems");
 
     let time = Instant::now();
     game_manager.run();
-    log_manager.debug(format!("{}ms", time.elapsed().as_millis()));
+    debug!("{}ms", time.elapsed().as_millis());
 
     game_manager.shutdown();
-    display_manager.shutdown();
     log_manager.shutdown();
 }
@@ -0,0 +1,830 @@
+// Matrices
+// Implements: https://docs.unity3d.com/ScriptReference/Matrix4x4.html
+
+use super::quaternion::Quaternion;
+#[cfg(feature = "simd")]
+use super::simd;
+use super::vector::{Vector3, Vector4};
+use std::fmt;
+use std::ops::{Index, IndexMut, Mul};
+
+/// A standard 4x4 transformation matrix.
+///
+/// Matrices act on column vectors (`m * v`) and are stored column-major, so
+/// `m[column][row]` can be handed to a shader as-is. Projections follow the
+/// engine's left-handed convention: +z is forward and clip-space depth runs
+/// from 0 at the near plane to 1 at the far plane.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matrix4x4 {
+    pub m: [[f32; 4]; 4],
+}
+
+/// A 3x3 matrix, typically the rotation and scale part of a `Matrix4x4`.
+///
+/// Stored column-major like `Matrix4x4`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matrix3x3 {
+    pub m: [[f32; 3]; 3],
+}
+
+// "Static Properties"
+impl Matrix4x4 {
+    pub const IDENTITY: Matrix4x4 = Matrix4x4 {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+    pub const ZERO: Matrix4x4 = Matrix4x4 { m: [[0.0; 4]; 4] };
+
+    /// Returns the identity matrix.
+    pub fn identity() -> Matrix4x4 {
+        Matrix4x4::IDENTITY
+    }
+
+    /// Returns a matrix with all elements set to zero.
+    pub fn zero() -> Matrix4x4 {
+        Matrix4x4::ZERO
+    }
+}
+
+// Constructors
+impl Matrix4x4 {
+    /// Constructs a matrix from four column vectors.
+    pub fn from_columns(c0: Vector4, c1: Vector4, c2: Vector4, c3: Vector4) -> Matrix4x4 {
+        Matrix4x4 {
+            m: [
+                [c0.x, c0.y, c0.z, c0.w],
+                [c1.x, c1.y, c1.z, c1.w],
+                [c2.x, c2.y, c2.z, c2.w],
+                [c3.x, c3.y, c3.z, c3.w],
+            ],
+        }
+    }
+
+    /// Creates a translation matrix.
+    pub fn from_translation(translation: Vector3) -> Matrix4x4 {
+        let mut result = Matrix4x4::IDENTITY;
+        result.m[3] = [translation.x, translation.y, translation.z, 1.0];
+        result
+    }
+
+    /// Creates a rotation matrix.
+    pub fn from_rotation(rotation: Quaternion) -> Matrix4x4 {
+        Matrix3x3::from_rotation(rotation).into()
+    }
+
+    /// Creates a scaling matrix.
+    pub fn from_scale(scale: Vector3) -> Matrix4x4 {
+        let mut result = Matrix4x4::IDENTITY;
+        result.m[0][0] = scale.x;
+        result.m[1][1] = scale.y;
+        result.m[2][2] = scale.z;
+        result
+    }
+
+    /// Creates a translation, rotation and scaling matrix, applying scale first
+    /// and translation last.
+    pub fn from_trs(translation: Vector3, rotation: Quaternion, scale: Vector3) -> Matrix4x4 {
+        let r = Matrix3x3::from_rotation(rotation).m;
+        let s = [scale.x, scale.y, scale.z];
+        let mut result = Matrix4x4::IDENTITY;
+        for (col, axis) in r.iter().enumerate() {
+            for (row, value) in axis.iter().enumerate() {
+                result.m[col][row] = value * s[col];
+            }
+        }
+        result.m[3] = [translation.x, translation.y, translation.z, 1.0];
+        result
+    }
+
+    /// Creates a perspective projection, `fov` being the vertical field of view in degrees.
+    pub fn perspective(fov: f32, aspect: f32, near: f32, far: f32) -> Matrix4x4 {
+        let f = 1.0 / (fov.to_radians() * 0.5).tan();
+        let depth = far / (far - near);
+        let mut result = Matrix4x4::ZERO;
+        result.m[0][0] = f / aspect;
+        result.m[1][1] = f;
+        result.m[2][2] = depth;
+        result.m[2][3] = 1.0;
+        result.m[3][2] = -near * depth;
+        result
+    }
+
+    /// Creates an orthographic projection of the given view volume.
+    pub fn orthographic(
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        near: f32,
+        far: f32,
+    ) -> Matrix4x4 {
+        let mut result = Matrix4x4::IDENTITY;
+        result.m[0][0] = 2.0 / (right - left);
+        result.m[1][1] = 2.0 / (top - bottom);
+        result.m[2][2] = 1.0 / (far - near);
+        result.m[3][0] = -(right + left) / (right - left);
+        result.m[3][1] = -(top + bottom) / (top - bottom);
+        result.m[3][2] = -near / (far - near);
+        result
+    }
+
+    /// Creates the view matrix of a camera at `eye` looking at `target`.
+    ///
+    /// The result maps world space into view space, where the camera looks
+    /// down +z with `up` roughly along +y.
+    pub fn look_at(eye: Vector3, target: Vector3, up: Vector3) -> Matrix4x4 {
+        let forward = (target - eye).normalized();
+        let right = Vector3::cross(up, forward).normalized();
+        let up = Vector3::cross(forward, right);
+        Matrix4x4 {
+            m: [
+                [right.x, up.x, forward.x, 0.0],
+                [right.y, up.y, forward.y, 0.0],
+                [right.z, up.z, forward.z, 0.0],
+                [
+                    -Vector3::dot(right, eye),
+                    -Vector3::dot(up, eye),
+                    -Vector3::dot(forward, eye),
+                    1.0,
+                ],
+            ],
+        }
+    }
+}
+
+// "Properties"
+impl Matrix4x4 {
+    /// Returns the determinant of the matrix.
+    pub fn determinant(&self) -> f32 {
+        self.cofactors().1
+    }
+
+    /// Returns the inverse of the matrix, or None if it's singular.
+    pub fn inverse(&self) -> Option<Matrix4x4> {
+        let (cofactors, det) = self.cofactors();
+        if det.abs() < f32::EPSILON * f32::EPSILON {
+            return None;
+        }
+        let mut result = Matrix4x4::ZERO;
+        for (i, value) in cofactors.iter().enumerate() {
+            result.m[i / 4][i % 4] = value / det;
+        }
+        Some(result)
+    }
+
+    /// Returns the transpose of the matrix.
+    pub fn transpose(&self) -> Matrix4x4 {
+        let mut result = Matrix4x4::ZERO;
+        for col in 0..4 {
+            for row in 0..4 {
+                result.m[row][col] = self.m[col][row];
+            }
+        }
+        result
+    }
+
+    /// Returns true if this is the identity matrix.
+    pub fn is_identity(&self) -> bool {
+        *self == Matrix4x4::IDENTITY
+    }
+
+    /// Returns the translation part of the matrix.
+    pub fn translation(&self) -> Vector3 {
+        Vector3::new(self.m[3][0], self.m[3][1], self.m[3][2])
+    }
+
+    /// Splits the matrix back into translation, rotation and scale.
+    ///
+    /// A mirroring matrix comes back with a negative x scale. Any shear is lost,
+    /// and a zero scale on any axis gives the identity rotation.
+    pub fn decompose(&self) -> (Vector3, Quaternion, Vector3) {
+        let mut basis = Matrix3x3::from(*self);
+        let mut scale = Vector3::new(
+            basis.column(0).magnitude(),
+            basis.column(1).magnitude(),
+            basis.column(2).magnitude(),
+        );
+        if basis.determinant() < 0.0 {
+            scale.x = -scale.x;
+        }
+
+        let rotation = if scale.x == 0.0 || scale.y == 0.0 || scale.z == 0.0 {
+            Quaternion::identity()
+        } else {
+            let s = [scale.x, scale.y, scale.z];
+            for (axis, s) in basis.m.iter_mut().zip(s.iter()) {
+                for value in axis.iter_mut() {
+                    *value /= s;
+                }
+            }
+            basis.to_rotation()
+        };
+        (self.translation(), rotation, scale)
+    }
+}
+
+// Public Methods
+impl Matrix4x4 {
+    /// Returns a column of the matrix.
+    pub fn column(&self, index: usize) -> Vector4 {
+        let c = self.m[index];
+        Vector4::new(c[0], c[1], c[2], c[3])
+    }
+
+    /// Returns a row of the matrix.
+    pub fn row(&self, index: usize) -> Vector4 {
+        Vector4::new(
+            self.m[0][index],
+            self.m[1][index],
+            self.m[2][index],
+            self.m[3][index],
+        )
+    }
+
+    /// Sets a column of the matrix.
+    pub fn set_column(&mut self, index: usize, column: Vector4) {
+        self.m[index] = [column.x, column.y, column.z, column.w];
+    }
+
+    /// Sets a row of the matrix.
+    pub fn set_row(&mut self, index: usize, row: Vector4) {
+        self.m[0][index] = row.x;
+        self.m[1][index] = row.y;
+        self.m[2][index] = row.z;
+        self.m[3][index] = row.w;
+    }
+
+    /// Transforms a position by this matrix, including the perspective divide.
+    pub fn multiply_point(&self, point: Vector3) -> Vector3 {
+        let v = *self * point.extend(1.0);
+        v.truncate() / v.w
+    }
+
+    /// Transforms a position by this matrix, assuming it's affine.
+    pub fn multiply_point3x4(&self, point: Vector3) -> Vector3 {
+        (*self * point.extend(1.0)).truncate()
+    }
+
+    /// Transforms a direction by this matrix, ignoring translation.
+    pub fn multiply_vector(&self, vector: Vector3) -> Vector3 {
+        (*self * vector.extend(0.0)).truncate()
+    }
+}
+
+// Private Methods
+impl Matrix4x4 {
+    /// Returns the transposed cofactor matrix, flattened column-major, and the determinant.
+    fn cofactors(&self) -> ([f32; 16], f32) {
+        let mut m = [0.0; 16];
+        for (i, value) in m.iter_mut().enumerate() {
+            *value = self.m[i / 4][i % 4];
+        }
+
+        let mut inv = [0.0; 16];
+        inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15]
+            + m[9] * m[7] * m[14]
+            + m[13] * m[6] * m[11]
+            - m[13] * m[7] * m[10];
+        inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15]
+            - m[8] * m[7] * m[14]
+            - m[12] * m[6] * m[11]
+            + m[12] * m[7] * m[10];
+        inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15]
+            + m[8] * m[7] * m[13]
+            + m[12] * m[5] * m[11]
+            - m[12] * m[7] * m[9];
+        inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14]
+            - m[8] * m[6] * m[13]
+            - m[12] * m[5] * m[10]
+            + m[12] * m[6] * m[9];
+        inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15]
+            - m[9] * m[3] * m[14]
+            - m[13] * m[2] * m[11]
+            + m[13] * m[3] * m[10];
+        inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15]
+            + m[8] * m[3] * m[14]
+            + m[12] * m[2] * m[11]
+            - m[12] * m[3] * m[10];
+        inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15]
+            - m[8] * m[3] * m[13]
+            - m[12] * m[1] * m[11]
+            + m[12] * m[3] * m[9];
+        inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14]
+            + m[8] * m[2] * m[13]
+            + m[12] * m[1] * m[10]
+            - m[12] * m[2] * m[9];
+        inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15]
+            + m[5] * m[3] * m[14]
+            + m[13] * m[2] * m[7]
+            - m[13] * m[3] * m[6];
+        inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15]
+            - m[4] * m[3] * m[14]
+            - m[12] * m[2] * m[7]
+            + m[12] * m[3] * m[6];
+        inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15]
+            + m[4] * m[3] * m[13]
+            + m[12] * m[1] * m[7]
+            - m[12] * m[3] * m[5];
+        inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14]
+            - m[4] * m[2] * m[13]
+            - m[12] * m[1] * m[6]
+            + m[12] * m[2] * m[5];
+        inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11]
+            - m[5] * m[3] * m[10]
+            - m[9] * m[2] * m[7]
+            + m[9] * m[3] * m[6];
+        inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11]
+            + m[4] * m[3] * m[10]
+            + m[8] * m[2] * m[7]
+            - m[8] * m[3] * m[6];
+        inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11]
+            - m[4] * m[3] * m[9]
+            - m[8] * m[1] * m[7]
+            + m[8] * m[3] * m[5];
+        inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10]
+            + m[4] * m[2] * m[9]
+            + m[8] * m[1] * m[6]
+            - m[8] * m[2] * m[5];
+
+        let det = m[0] * inv[0] + m[1] * inv[4] + m[2] * inv[8] + m[3] * inv[12];
+        (inv, det)
+    }
+}
+
+// "Static Properties"
+impl Matrix3x3 {
+    pub const IDENTITY: Matrix3x3 = Matrix3x3 {
+        m: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+    };
+    pub const ZERO: Matrix3x3 = Matrix3x3 { m: [[0.0; 3]; 3] };
+
+    /// Returns the identity matrix.
+    pub fn identity() -> Matrix3x3 {
+        Matrix3x3::IDENTITY
+    }
+
+    /// Returns a matrix with all elements set to zero.
+    pub fn zero() -> Matrix3x3 {
+        Matrix3x3::ZERO
+    }
+}
+
+// Constructors
+impl Matrix3x3 {
+    /// Constructs a matrix from three column vectors.
+    pub fn from_columns(c0: Vector3, c1: Vector3, c2: Vector3) -> Matrix3x3 {
+        Matrix3x3 {
+            m: [[c0.x, c0.y, c0.z], [c1.x, c1.y, c1.z], [c2.x, c2.y, c2.z]],
+        }
+    }
+
+    /// Creates a rotation matrix.
+    pub fn from_rotation(q: Quaternion) -> Matrix3x3 {
+        let (w, x, y, z) = (q.w, q.x, q.y, q.z);
+        Matrix3x3 {
+            m: [
+                [
+                    1.0 - 2.0 * (y * y + z * z),
+                    2.0 * (x * y + w * z),
+                    2.0 * (x * z - w * y),
+                ],
+                [
+                    2.0 * (x * y - w * z),
+                    1.0 - 2.0 * (x * x + z * z),
+                    2.0 * (y * z + w * x),
+                ],
+                [
+                    2.0 * (x * z + w * y),
+                    2.0 * (y * z - w * x),
+                    1.0 - 2.0 * (x * x + y * y),
+                ],
+            ],
+        }
+    }
+
+    /// Creates a scaling matrix.
+    pub fn from_scale(scale: Vector3) -> Matrix3x3 {
+        Matrix3x3 {
+            m: [
+                [scale.x, 0.0, 0.0],
+                [0.0, scale.y, 0.0],
+                [0.0, 0.0, scale.z],
+            ],
+        }
+    }
+}
+
+// "Properties"
+impl Matrix3x3 {
+    /// Returns the determinant of the matrix.
+    pub fn determinant(&self) -> f32 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[2][1] * m[1][2])
+            - m[1][0] * (m[0][1] * m[2][2] - m[2][1] * m[0][2])
+            + m[2][0] * (m[0][1] * m[1][2] - m[1][1] * m[0][2])
+    }
+
+    /// Returns the inverse of the matrix, or None if it's singular.
+    pub fn inverse(&self) -> Option<Matrix3x3> {
+        let det = self.determinant();
+        if det.abs() < f32::EPSILON * f32::EPSILON {
+            return None;
+        }
+        // The inverse's columns are the cross products of the rows of the
+        // transposed matrix, i.e. of our columns, scaled by 1 / det.
+        let c0 = self.column(0);
+        let c1 = self.column(1);
+        let c2 = self.column(2);
+        let rows = Matrix3x3::from_columns(
+            Vector3::cross(c1, c2),
+            Vector3::cross(c2, c0),
+            Vector3::cross(c0, c1),
+        );
+        let mut result = rows.transpose();
+        for axis in result.m.iter_mut() {
+            for value in axis.iter_mut() {
+                *value /= det;
+            }
+        }
+        Some(result)
+    }
+
+    /// Returns the transpose of the matrix.
+    pub fn transpose(&self) -> Matrix3x3 {
+        let mut result = Matrix3x3::ZERO;
+        for col in 0..3 {
+            for row in 0..3 {
+                result.m[row][col] = self.m[col][row];
+            }
+        }
+        result
+    }
+
+    /// Returns the rotation this matrix represents.
+    ///
+    /// The matrix must be orthonormal, i.e. a pure rotation.
+    pub fn to_rotation(&self) -> Quaternion {
+        let m = &self.m;
+        // m[col][row], so r(row, col) = m[col][row]
+        let (r00, r01, r02) = (m[0][0], m[1][0], m[2][0]);
+        let (r10, r11, r12) = (m[0][1], m[1][1], m[2][1]);
+        let (r20, r21, r22) = (m[0][2], m[1][2], m[2][2]);
+
+        let trace = r00 + r11 + r22;
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Quaternion::new(0.25 * s, (r21 - r12) / s, (r02 - r20) / s, (r10 - r01) / s)
+        } else if r00 > r11 && r00 > r22 {
+            let s = (1.0 + r00 - r11 - r22).sqrt() * 2.0;
+            Quaternion::new((r21 - r12) / s, 0.25 * s, (r01 + r10) / s, (r02 + r20) / s)
+        } else if r11 > r22 {
+            let s = (1.0 + r11 - r00 - r22).sqrt() * 2.0;
+            Quaternion::new((r02 - r20) / s, (r01 + r10) / s, 0.25 * s, (r12 + r21) / s)
+        } else {
+            let s = (1.0 + r22 - r00 - r11).sqrt() * 2.0;
+            Quaternion::new((r10 - r01) / s, (r02 + r20) / s, (r12 + r21) / s, 0.25 * s)
+        };
+        q.normalized()
+    }
+}
+
+// Public Methods
+impl Matrix3x3 {
+    /// Returns a column of the matrix.
+    pub fn column(&self, index: usize) -> Vector3 {
+        let c = self.m[index];
+        Vector3::new(c[0], c[1], c[2])
+    }
+
+    /// Returns a row of the matrix.
+    pub fn row(&self, index: usize) -> Vector3 {
+        Vector3::new(self.m[0][index], self.m[1][index], self.m[2][index])
+    }
+
+    /// Sets a column of the matrix.
+    pub fn set_column(&mut self, index: usize, column: Vector3) {
+        self.m[index] = [column.x, column.y, column.z];
+    }
+
+    /// Sets a row of the matrix.
+    pub fn set_row(&mut self, index: usize, row: Vector3) {
+        self.m[0][index] = row.x;
+        self.m[1][index] = row.y;
+        self.m[2][index] = row.z;
+    }
+}
+
+// Conversions
+impl From<Matrix4x4> for Matrix3x3 {
+    /// Takes the upper-left 3x3, dropping translation and projection.
+    fn from(matrix: Matrix4x4) -> Matrix3x3 {
+        let mut result = Matrix3x3::ZERO;
+        for col in 0..3 {
+            for row in 0..3 {
+                result.m[col][row] = matrix.m[col][row];
+            }
+        }
+        result
+    }
+}
+
+impl From<Matrix3x3> for Matrix4x4 {
+    /// Places the matrix in the upper-left of an identity matrix.
+    fn from(matrix: Matrix3x3) -> Matrix4x4 {
+        let mut result = Matrix4x4::IDENTITY;
+        for col in 0..3 {
+            for row in 0..3 {
+                result.m[col][row] = matrix.m[col][row];
+            }
+        }
+        result
+    }
+}
+
+impl From<Quaternion> for Matrix3x3 {
+    fn from(rotation: Quaternion) -> Matrix3x3 {
+        Matrix3x3::from_rotation(rotation)
+    }
+}
+
+impl From<Quaternion> for Matrix4x4 {
+    fn from(rotation: Quaternion) -> Matrix4x4 {
+        Matrix4x4::from_rotation(rotation)
+    }
+}
+
+impl From<Matrix3x3> for Quaternion {
+    fn from(matrix: Matrix3x3) -> Quaternion {
+        matrix.to_rotation()
+    }
+}
+
+// Std Trait Implementations
+impl Mul for Matrix4x4 {
+    type Output = Self;
+
+    /// Multiply two matrices, applying `other` first.
+    fn mul(self, other: Self) -> Self {
+        #[cfg(feature = "simd")]
+        {
+            simd::mat4_mul(&self, &other)
+        }
+        #[cfg(not(feature = "simd"))]
+        {
+            let mut result = Matrix4x4::ZERO;
+            for col in 0..4 {
+                for row in 0..4 {
+                    result.m[col][row] = (0..4).map(|k| self.m[k][row] * other.m[col][k]).sum();
+                }
+            }
+            result
+        }
+    }
+}
+
+impl Mul<Vector4> for Matrix4x4 {
+    type Output = Vector4;
+
+    /// Transform a vector by the matrix.
+    fn mul(self, v: Vector4) -> Vector4 {
+        self.column(0) * v.x + self.column(1) * v.y + self.column(2) * v.z + self.column(3) * v.w
+    }
+}
+
+impl Mul for Matrix3x3 {
+    type Output = Self;
+
+    /// Multiply two matrices, applying `other` first.
+    fn mul(self, other: Self) -> Self {
+        let mut result = Matrix3x3::ZERO;
+        for col in 0..3 {
+            for row in 0..3 {
+                result.m[col][row] = (0..3).map(|k| self.m[k][row] * other.m[col][k]).sum();
+            }
+        }
+        result
+    }
+}
+
+impl Mul<Vector3> for Matrix3x3 {
+    type Output = Vector3;
+
+    /// Transform a vector by the matrix.
+    fn mul(self, v: Vector3) -> Vector3 {
+        self.column(0) * v.x + self.column(1) * v.y + self.column(2) * v.z
+    }
+}
+
+impl Index<(usize, usize)> for Matrix4x4 {
+    type Output = f32;
+
+    /// Access an element by (row, column).
+    fn index(&self, (row, col): (usize, usize)) -> &Self::Output {
+        &self.m[col][row]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Matrix4x4 {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut Self::Output {
+        &mut self.m[col][row]
+    }
+}
+
+impl Index<(usize, usize)> for Matrix3x3 {
+    type Output = f32;
+
+    /// Access an element by (row, column).
+    fn index(&self, (row, col): (usize, usize)) -> &Self::Output {
+        &self.m[col][row]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Matrix3x3 {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut Self::Output {
+        &mut self.m[col][row]
+    }
+}
+
+impl Default for Matrix4x4 {
+    fn default() -> Self {
+        Matrix4x4::IDENTITY
+    }
+}
+
+impl Default for Matrix3x3 {
+    fn default() -> Self {
+        Matrix3x3::IDENTITY
+    }
+}
+
+impl fmt::Display for Matrix4x4 {
+    /// Returns a formatted string of the matrix, one row per line
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in 0..4 {
+            if row > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", self.row(row))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Matrix3x3 {
+    /// Returns a formatted string of the matrix, one row per line
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in 0..3 {
+            if row > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", self.row(row))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    /// 90 degrees about +y, turning forward (+z) to the right (+x).
+    fn quarter_turn_y() -> Quaternion {
+        Quaternion::new(FRAC_1_SQRT_2, 0.0, FRAC_1_SQRT_2, 0.0)
+    }
+
+    #[test]
+    fn identity_leaves_matrices_and_points_unchanged() {
+        let m = Matrix4x4::from_translation(Vector3::new(1.0, 2.0, 3.0));
+
+        assert_eq!(Matrix4x4::IDENTITY * m, m);
+        assert_eq!(m * Matrix4x4::IDENTITY, m);
+        assert_eq!(
+            Matrix4x4::identity().multiply_point(Vector3::ONE),
+            Vector3::ONE
+        );
+    }
+
+    #[test]
+    fn can_transpose_and_index_matrix4x4() {
+        let mut m = Matrix4x4::ZERO;
+        m[(0, 3)] = 5.0;
+
+        assert_eq!(m.m[3][0], 5.0);
+        assert_eq!(m.transpose()[(3, 0)], 5.0);
+        assert_eq!(m.row(0), Vector4::new(0.0, 0.0, 0.0, 5.0));
+    }
+
+    #[test]
+    fn can_calculate_determinant_and_inverse() {
+        let m = Matrix4x4::from_trs(
+            Vector3::new(1.0, -2.0, 3.0),
+            quarter_turn_y(),
+            Vector3::new(2.0, 3.0, 4.0),
+        );
+
+        assert_approx_eq!(m.determinant(), 24.0);
+        assert_approx_eq!(m * m.inverse().unwrap(), Matrix4x4::IDENTITY);
+        assert_eq!(Matrix4x4::from_scale(Vector3::ZERO).inverse(), None);
+    }
+
+    #[test]
+    fn matrix3x3_inverse_undoes_the_matrix() {
+        let m =
+            Matrix3x3::from_rotation(quarter_turn_y()) * Matrix3x3::from_scale(Vector3::ONE * 2.0);
+        let inverse = m.inverse().unwrap();
+        let v = Vector3::new(1.0, 2.0, 3.0);
+
+        assert_approx_eq!(m.determinant(), 8.0);
+        assert_approx_eq!(inverse * (m * v), v);
+        assert_eq!(Matrix3x3::ZERO.inverse(), None);
+    }
+
+    #[test]
+    fn trs_applies_scale_then_rotation_then_translation() {
+        let m = Matrix4x4::from_trs(
+            Vector3::new(10.0, 0.0, 0.0),
+            quarter_turn_y(),
+            Vector3::ONE * 2.0,
+        );
+
+        assert_approx_eq!(
+            m.multiply_point3x4(Vector3::FORWARD),
+            Vector3::new(12.0, 0.0, 0.0),
+        );
+        assert_approx_eq!(m.multiply_vector(Vector3::FORWARD), Vector3::RIGHT * 2.0);
+    }
+
+    #[test]
+    fn trs_can_be_decomposed() {
+        let position = Vector3::new(1.0, 2.0, 3.0);
+        let scale = Vector3::new(1.0, 2.0, 0.5);
+        let m = Matrix4x4::from_trs(position, quarter_turn_y(), scale);
+
+        let (t, r, s) = m.decompose();
+
+        assert_approx_eq!(t, position);
+        assert_approx_eq!(s, scale);
+        assert_approx_eq!(Quaternion::dot(r, quarter_turn_y()).abs(), 1.0);
+    }
+
+    #[test]
+    fn mirrored_matrix_decomposes_to_negative_x_scale() {
+        let m = Matrix4x4::from_scale(Vector3::new(-1.0, 1.0, 1.0));
+
+        let (_, r, s) = m.decompose();
+
+        assert_approx_eq!(s, Vector3::new(-1.0, 1.0, 1.0));
+        assert_approx_eq!(Quaternion::dot(r, Quaternion::identity()).abs(), 1.0);
+    }
+
+    #[test]
+    fn quaternion_round_trips_through_rotation_matrix() {
+        let q = Quaternion::new(0.5, 0.5, -0.5, 0.5);
+        let back = Quaternion::from(Matrix3x3::from(q));
+
+        assert_approx_eq!(Quaternion::dot(q, back).abs(), 1.0);
+        assert_eq!(Matrix3x3::from(Matrix4x4::from(q)), Matrix3x3::from(q));
+    }
+
+    #[test]
+    fn perspective_maps_near_and_far_planes_to_unit_depth() {
+        let m = Matrix4x4::perspective(90.0, 2.0, 0.1, 100.0);
+
+        assert_approx_eq!(m.multiply_point(Vector3::new(0.0, 0.0, 0.1)).z, 0.0);
+        assert_approx_eq!(m.multiply_point(Vector3::new(0.0, 0.0, 100.0)).z, 1.0);
+        assert_approx_eq!(m.multiply_point(Vector3::new(2.0, 1.0, 1.0)).x, 1.0);
+        assert_approx_eq!(m.multiply_point(Vector3::new(2.0, 1.0, 1.0)).y, 1.0);
+    }
+
+    #[test]
+    fn orthographic_maps_view_volume_to_clip_space() {
+        let m = Matrix4x4::orthographic(-4.0, 4.0, -2.0, 2.0, 1.0, 11.0);
+
+        assert_approx_eq!(
+            m.multiply_point(Vector3::new(4.0, 2.0, 11.0)),
+            Vector3::new(1.0, 1.0, 1.0),
+        );
+        assert_approx_eq!(
+            m.multiply_point(Vector3::new(-4.0, -2.0, 1.0)),
+            Vector3::new(-1.0, -1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn look_at_puts_target_straight_ahead() {
+        let eye = Vector3::new(0.0, 0.0, -5.0);
+        let view = Matrix4x4::look_at(eye, Vector3::ZERO, Vector3::UP);
+
+        assert_approx_eq!(
+            view.multiply_point(Vector3::ZERO),
+            Vector3::new(0.0, 0.0, 5.0),
+        );
+        assert_approx_eq!(view.multiply_point(Vector3::RIGHT + eye), Vector3::RIGHT);
+    }
+}
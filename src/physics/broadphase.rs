@@ -0,0 +1,105 @@
+// Broadphase
+//
+// Sweep and prune along the x axis: bounds are sorted by their left edge, and
+// each one is only compared with those starting before it ends. Cheap for the
+// scattered, mostly horizontal scenes of 2D-style games, and the pairs come
+// out in a fixed order for the same input. The sorted bounds are kept for
+// `SpatialQuery` to search the same way.
+
+use super::collider::Bounds;
+
+/// Bounds sorted along the x axis.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SweepAndPrune {
+    bounds: Vec<Bounds>,
+    order: Vec<usize>,
+}
+
+impl SweepAndPrune {
+    pub fn new(bounds: Vec<Bounds>) -> SweepAndPrune {
+        let mut order: Vec<usize> = (0..bounds.len()).collect();
+        // A stable sort keeps ties in storage order
+        order.sort_by(|&a, &b| {
+            bounds[a]
+                .min
+                .x
+                .partial_cmp(&bounds[b].min.x)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        SweepAndPrune { bounds, order }
+    }
+
+    /// Returns the index pairs whose bounds overlap, lower index first.
+    pub fn pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for (i, &a) in self.order.iter().enumerate() {
+            for &b in &self.order[i + 1..] {
+                if self.bounds[b].min.x > self.bounds[a].max.x {
+                    break;
+                }
+                if self.bounds[a].overlaps(&self.bounds[b]) {
+                    pairs.push((a.min(b), a.max(b)));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Returns the indices whose bounds overlap `query`, in sorted order.
+    pub fn overlapping<'a>(&'a self, query: &'a Bounds) -> impl Iterator<Item = usize> + 'a {
+        self.order
+            .iter()
+            .copied()
+            .take_while(move |&i| self.bounds[i].min.x <= query.max.x)
+            .filter(move |&i| self.bounds[i].overlaps(query))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Vector3;
+
+    fn cube(x: f32, y: f32) -> Bounds {
+        Bounds {
+            min: Vector3::new(x - 1.0, y - 1.0, -1.0),
+            max: Vector3::new(x + 1.0, y + 1.0, 1.0),
+        }
+    }
+
+    #[test]
+    fn finds_only_overlapping_pairs() {
+        let bounds = vec![
+            cube(4.0, 0.0),
+            cube(0.0, 0.0),
+            cube(1.5, 0.0),
+            cube(1.5, 5.0),
+        ];
+
+        let pairs = SweepAndPrune::new(bounds).pairs();
+
+        assert_eq!(pairs, vec![(1, 2)]);
+    }
+
+    #[test]
+    fn touching_bounds_overlap() {
+        let bounds = vec![cube(0.0, 0.0), cube(2.0, 0.0), cube(4.0, 0.0)];
+
+        let pairs = SweepAndPrune::new(bounds).pairs();
+
+        assert_eq!(pairs, vec![(0, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn finds_bounds_overlapping_a_query() {
+        let sweep = SweepAndPrune::new(vec![cube(4.0, 0.0), cube(0.0, 0.0), cube(9.0, 0.0)]);
+        let query = Bounds {
+            min: Vector3::new(0.5, -0.5, -0.5),
+            max: Vector3::new(3.5, 0.5, 0.5),
+        };
+
+        let found: Vec<usize> = sweep.overlapping(&query).collect();
+
+        assert_eq!(found, vec![1, 0]);
+    }
+}
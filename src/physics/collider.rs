@@ -0,0 +1,408 @@
+// Colliders
+//
+// Shapes are described in the entity's local space and placed in the world by
+// its `Transform` each tick. Narrowphase only needs a support function, the
+// point of the shape furthest along a direction, so adding a shape means
+// adding its support and bounds here.
+
+use crate::component::Transform;
+use crate::vector::Vector3;
+
+/// Geometry of a collider, in local space.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Sphere {
+        radius: f32,
+    },
+    /// A box that stays aligned with the world axes whatever the rotation.
+    Aabb {
+        half_extents: Vector3,
+    },
+    /// A box that turns with the entity.
+    Obb {
+        half_extents: Vector3,
+    },
+    /// A cylinder with hemispherical ends, along the local y axis.
+    Capsule {
+        half_height: f32,
+        radius: f32,
+    },
+    /// The smallest convex shape enclosing the points.
+    ConvexHull {
+        points: Vec<Vector3>,
+    },
+}
+
+/// How a collider reacts to others, after Dragonfly's solidness.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Solidity {
+    /// Blocks other hard colliders, and reports collisions with soft ones.
+    Hard,
+    /// Overlaps anything, but still reports collisions.
+    Soft,
+    /// Never collides with anything.
+    Spectral,
+}
+
+/// What happens when two colliders touch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interaction {
+    /// Report the collision and push the colliders apart.
+    Block,
+    /// Report the collision but let them overlap.
+    Overlap,
+    /// Don't test the pair at all.
+    Ignore,
+}
+
+impl Solidity {
+    pub fn interaction(self, other: Solidity) -> Interaction {
+        match (self, other) {
+            (Solidity::Spectral, _) | (_, Solidity::Spectral) => Interaction::Ignore,
+            (Solidity::Hard, Solidity::Hard) => Interaction::Block,
+            _ => Interaction::Overlap,
+        }
+    }
+}
+
+/// Makes an entity collide with others, placed by its `Transform`.
+///
+/// Two colliders are only tested if each one's `layer` is in the other's
+/// `mask`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collider {
+    pub shape: Shape,
+    pub solidity: Solidity,
+    pub layer: u32,
+    pub mask: u32,
+}
+
+impl Collider {
+    /// A hard collider on layer 1 that collides with every layer.
+    pub fn new(shape: Shape) -> Collider {
+        Collider {
+            shape,
+            solidity: Solidity::Hard,
+            layer: 1,
+            mask: u32::MAX,
+        }
+    }
+
+    pub fn sphere(radius: f32) -> Collider {
+        Collider::new(Shape::Sphere { radius })
+    }
+
+    pub fn aabb(half_extents: Vector3) -> Collider {
+        Collider::new(Shape::Aabb { half_extents })
+    }
+
+    pub fn obb(half_extents: Vector3) -> Collider {
+        Collider::new(Shape::Obb { half_extents })
+    }
+
+    pub fn capsule(half_height: f32, radius: f32) -> Collider {
+        Collider::new(Shape::Capsule {
+            half_height,
+            radius,
+        })
+    }
+
+    /// A hull around `points`. Without any points it's a single point at the
+    /// entity's position.
+    pub fn convex_hull(points: Vec<Vector3>) -> Collider {
+        Collider::new(Shape::ConvexHull { points })
+    }
+
+    pub fn with_solidity(mut self, solidity: Solidity) -> Collider {
+        self.solidity = solidity;
+        self
+    }
+
+    pub fn with_layers(mut self, layer: u32, mask: u32) -> Collider {
+        self.layer = layer;
+        self.mask = mask;
+        self
+    }
+
+    /// Returns true if the layers and solidity of both allow them to collide.
+    pub fn can_collide(&self, other: &Collider) -> bool {
+        self.layer & other.mask != 0
+            && other.layer & self.mask != 0
+            && self.solidity.interaction(other.solidity) != Interaction::Ignore
+    }
+
+    /// Places the shape in the world.
+    pub(crate) fn to_world(&self, transform: &Transform) -> WorldShape {
+        let center = transform.position;
+        let scale = abs(transform.scale);
+        match &self.shape {
+            Shape::Sphere { radius } => WorldShape::Sphere {
+                center,
+                radius: radius * scale.x.max(scale.y).max(scale.z),
+            },
+            Shape::Aabb { half_extents } => WorldShape::Box {
+                center,
+                axes: [Vector3::RIGHT, Vector3::UP, Vector3::FORWARD],
+                half_extents: *half_extents * scale,
+            },
+            Shape::Obb { half_extents } => WorldShape::Box {
+                center,
+                axes: [transform.right(), transform.up(), transform.forward()],
+                half_extents: *half_extents * scale,
+            },
+            Shape::Capsule {
+                half_height,
+                radius,
+            } => {
+                let axis = transform.up() * (half_height * scale.y);
+                WorldShape::Capsule {
+                    a: center - axis,
+                    b: center + axis,
+                    radius: radius * scale.x.max(scale.z),
+                }
+            }
+            Shape::ConvexHull { points } if points.is_empty() => WorldShape::Hull {
+                points: vec![transform.position],
+            },
+            Shape::ConvexHull { points } => WorldShape::Hull {
+                points: points
+                    .iter()
+                    .map(|p| transform.transform_point(*p))
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// An axis-aligned box in world space, used by the broadphase and queries.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub min: Vector3,
+    pub max: Vector3,
+}
+
+impl Bounds {
+    pub fn overlaps(&self, other: &Bounds) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+            && self.min.z <= other.max.z
+            && other.min.z <= self.max.z
+    }
+}
+
+/// A collider shape placed in the world.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum WorldShape {
+    Sphere {
+        center: Vector3,
+        radius: f32,
+    },
+    Box {
+        center: Vector3,
+        axes: [Vector3; 3],
+        half_extents: Vector3,
+    },
+    Capsule {
+        a: Vector3,
+        b: Vector3,
+        radius: f32,
+    },
+    /// Never empty, see `Collider::convex_hull`.
+    Hull {
+        points: Vec<Vector3>,
+    },
+}
+
+impl WorldShape {
+    /// Returns the point of the shape furthest along `direction`.
+    pub fn support(&self, direction: Vector3) -> Vector3 {
+        match self {
+            WorldShape::Sphere { center, radius } => *center + along(direction) * *radius,
+            WorldShape::Box {
+                center,
+                axes,
+                half_extents,
+            } => {
+                let half = [half_extents.x, half_extents.y, half_extents.z];
+                axes.iter()
+                    .zip(half.iter())
+                    .fold(*center, |point, (axis, half)| {
+                        let sign = if Vector3::dot(*axis, direction) < 0.0 {
+                            -1.0
+                        } else {
+                            1.0
+                        };
+                        point + *axis * (half * sign)
+                    })
+            }
+            WorldShape::Capsule { a, b, radius } => {
+                let end = if Vector3::dot(*b - *a, direction) < 0.0 {
+                    *a
+                } else {
+                    *b
+                };
+                end + along(direction) * *radius
+            }
+            WorldShape::Hull { points } => points
+                .iter()
+                .copied()
+                .fold(None, |best: Option<Vector3>, point| match best {
+                    Some(best)
+                        if Vector3::dot(best, direction) >= Vector3::dot(point, direction) =>
+                    {
+                        Some(best)
+                    }
+                    _ => Some(point),
+                })
+                .expect("hulls have at least one point"),
+        }
+    }
+
+    /// A point inside the shape.
+    pub fn center(&self) -> Vector3 {
+        match self {
+            WorldShape::Sphere { center, .. } | WorldShape::Box { center, .. } => *center,
+            WorldShape::Capsule { a, b, .. } => (*a + *b) * 0.5,
+            WorldShape::Hull { points } => {
+                points.iter().fold(Vector3::ZERO, |sum, p| sum + *p) / points.len() as f32
+            }
+        }
+    }
+
+    /// The smallest axis-aligned box containing the shape.
+    pub fn bounds(&self) -> Bounds {
+        let (min, max) = match self {
+            WorldShape::Sphere { center, radius } => {
+                let r = Vector3::ONE * *radius;
+                (*center - r, *center + r)
+            }
+            WorldShape::Box {
+                center,
+                axes,
+                half_extents,
+            } => {
+                let extent = abs(axes[0]) * half_extents.x
+                    + abs(axes[1]) * half_extents.y
+                    + abs(axes[2]) * half_extents.z;
+                (*center - extent, *center + extent)
+            }
+            WorldShape::Capsule { a, b, radius } => {
+                let r = Vector3::ONE * *radius;
+                (Vector3::min(*a, *b) - r, Vector3::max(*a, *b) + r)
+            }
+            WorldShape::Hull { points } => points
+                .iter()
+                .skip(1)
+                .fold((points[0], points[0]), |(min, max), p| {
+                    (Vector3::min(min, *p), Vector3::max(max, *p))
+                }),
+        };
+        Bounds { min, max }
+    }
+}
+
+/// `direction` normalized, or an arbitrary unit vector if it's zero.
+fn along(direction: Vector3) -> Vector3 {
+    if direction == Vector3::ZERO {
+        Vector3::RIGHT
+    } else {
+        direction.normalized()
+    }
+}
+
+fn abs(v: Vector3) -> Vector3 {
+    Vector3::new(v.x.abs(), v.y.abs(), v.z.abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+    use crate::quaternion::Quaternion;
+
+    #[test]
+    fn solidity_decides_whether_colliders_block_or_overlap() {
+        assert_eq!(
+            Solidity::Hard.interaction(Solidity::Hard),
+            Interaction::Block
+        );
+        assert_eq!(
+            Solidity::Hard.interaction(Solidity::Soft),
+            Interaction::Overlap
+        );
+        assert_eq!(
+            Solidity::Soft.interaction(Solidity::Soft),
+            Interaction::Overlap
+        );
+        assert_eq!(
+            Solidity::Spectral.interaction(Solidity::Hard),
+            Interaction::Ignore
+        );
+        assert_eq!(
+            Solidity::Soft.interaction(Solidity::Spectral),
+            Interaction::Ignore
+        );
+    }
+
+    #[test]
+    fn layers_must_match_both_ways() {
+        let player = Collider::sphere(1.0).with_layers(0b01, 0b10);
+        let enemy = Collider::sphere(1.0).with_layers(0b10, 0b01);
+        let pickup = Collider::sphere(1.0).with_layers(0b100, u32::MAX);
+
+        assert!(player.can_collide(&enemy));
+        assert!(!player.can_collide(&pickup));
+        assert!(!pickup.can_collide(&player));
+    }
+
+    #[test]
+    fn obb_turns_with_the_transform_but_aabb_does_not() {
+        let transform = Transform::from_rotation(Quaternion::angle_axis(90.0, Vector3::UP));
+        let half_extents = Vector3::new(2.0, 1.0, 1.0);
+
+        let aabb = Collider::aabb(half_extents).to_world(&transform).bounds();
+        let obb = Collider::obb(half_extents).to_world(&transform).bounds();
+
+        assert_approx_eq!(aabb.max, Vector3::new(2.0, 1.0, 1.0));
+        assert_approx_eq!(obb.max, Vector3::new(1.0, 1.0, 2.0));
+    }
+
+    #[test]
+    fn support_points_lie_on_the_surface() {
+        let transform = Transform::from_position(Vector3::new(0.0, 1.0, 0.0));
+        let capsule = Collider::capsule(1.0, 0.5).to_world(&transform);
+        let hull = Collider::convex_hull(vec![
+            Vector3::ZERO,
+            Vector3::RIGHT,
+            Vector3::UP,
+            Vector3::FORWARD,
+        ])
+        .to_world(&transform);
+
+        assert_approx_eq!(capsule.support(Vector3::UP), Vector3::new(0.0, 2.5, 0.0));
+        assert_approx_eq!(capsule.support(Vector3::LEFT), Vector3::new(-0.5, 2.0, 0.0));
+        assert_approx_eq!(
+            hull.support(Vector3::new(1.0, 0.1, 0.0)),
+            Vector3::new(1.0, 1.0, 0.0)
+        );
+        assert_approx_eq!(hull.bounds().max, Vector3::new(1.0, 2.0, 1.0));
+    }
+
+    #[test]
+    fn empty_hulls_are_a_point_at_the_entity() {
+        let position = Vector3::new(3.0, -1.0, 2.0);
+        let hull = Collider::convex_hull(Vec::new()).to_world(&Transform::from_position(position));
+
+        assert_eq!(hull.support(Vector3::UP), position);
+        assert_eq!(hull.center(), position);
+        assert_eq!(
+            hull.bounds(),
+            Bounds {
+                min: position,
+                max: position
+            }
+        );
+    }
+}
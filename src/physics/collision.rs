@@ -0,0 +1,440 @@
+// Collision
+//
+// `detect_collisions` runs once per tick, after bodies have moved. It places
+// every collider in the world, pairs up the ones whose bounds overlap, and
+// tests each pair for contact. Contacts are compared with the previous tick's
+// to send events when a collision starts, goes on and ends, and pairs of hard
+// colliders are then pushed apart. The placed colliders are handed on to
+// `SpatialQuery` for gameplay code to search.
+//
+// Colliders on entities with a `Parent` are placed with their world matrix,
+// worked out from the `Transform`s up the hierarchy, and pushes are turned
+// back into the parent's space. Any shear from non-uniformly scaled parents
+// is ignored.
+
+use std::collections::HashSet;
+
+use legion::world::SubWorld;
+use legion::*;
+
+use super::broadphase::SweepAndPrune;
+use super::collider::{Collider, Interaction, WorldShape};
+use super::narrowphase::{self, Contact};
+use super::query::{self, SpatialQuery};
+use super::{Mass, RigidBody};
+use crate::component::{Transform, Velocity};
+use crate::event::Events;
+use crate::hierarchy::{self, Parent};
+use crate::matrix::Matrix4x4;
+use crate::vector::Vector3;
+
+/// Two colliders that started touching this tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CollisionStarted {
+    pub a: Entity,
+    pub b: Entity,
+    pub contact: Contact,
+}
+
+/// Two colliders that were already touching and still are.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CollisionOngoing {
+    pub a: Entity,
+    pub b: Entity,
+    pub contact: Contact,
+}
+
+/// Two colliders that stopped touching this tick, or of which one was
+/// despawned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollisionEnded {
+    pub a: Entity,
+    pub b: Entity,
+}
+
+/// Pairs of colliders touching as of the latest run of `detect_collisions`.
+#[derive(Clone, Debug, Default)]
+pub struct Collisions {
+    touching: Vec<(Entity, Entity)>,
+}
+
+impl Collisions {
+    /// Returns true if `a` and `b` are touching, in either order.
+    pub fn are_touching(&self, a: Entity, b: Entity) -> bool {
+        self.touching
+            .iter()
+            .any(|&pair| pair == (a, b) || pair == (b, a))
+    }
+
+    /// Record this tick's `contacts`, sending events for how each pair changed.
+    fn update(
+        &mut self,
+        contacts: &[(Entity, Entity, Contact)],
+        started: &mut Events<CollisionStarted>,
+        ongoing: &mut Events<CollisionOngoing>,
+        ended: &mut Events<CollisionEnded>,
+    ) {
+        let previous = std::mem::take(&mut self.touching);
+        let before: HashSet<(Entity, Entity)> = previous.iter().copied().collect();
+
+        for &(a, b, contact) in contacts {
+            if before.contains(&(a, b)) || before.contains(&(b, a)) {
+                ongoing.send(CollisionOngoing { a, b, contact });
+            } else {
+                started.send(CollisionStarted { a, b, contact });
+            }
+            self.touching.push((a, b));
+        }
+
+        // Walk last tick's pairs in order, so the events come out the same on every run
+        let now: HashSet<(Entity, Entity)> = self.touching.iter().copied().collect();
+        for (a, b) in previous {
+            if !now.contains(&(a, b)) && !now.contains(&(b, a)) {
+                ended.send(CollisionEnded { a, b });
+            }
+        }
+    }
+}
+
+/// A collider placed for this tick.
+struct Placed<'a> {
+    collider: &'a Collider,
+    shape: WorldShape,
+    body: Body,
+}
+
+/// What resolving a collision needs to know about one of the entities.
+#[derive(Clone, Copy)]
+struct Body {
+    entity: Entity,
+    inverse_mass: f32,
+    /// World matrix of the entity's parent, which its `Transform` and
+    /// `Velocity` are relative to.
+    parent: Matrix4x4,
+}
+
+/// Find the colliders touching each other, send collision events and push
+/// apart pairs of hard colliders. Also rebuilds `SpatialQuery`.
+///
+/// Only dynamic rigid bodies are pushed; other colliders act as immovable.
+#[system]
+#[read_component(Collider)]
+#[read_component(RigidBody)]
+#[read_component(Mass)]
+#[read_component(Parent)]
+#[write_component(Transform)]
+#[write_component(Velocity)]
+pub fn detect_collisions(
+    world: &mut SubWorld,
+    #[resource] collisions: &mut Collisions,
+    #[resource] started: &mut Events<CollisionStarted>,
+    #[resource] ongoing: &mut Events<CollisionOngoing>,
+    #[resource] ended: &mut Events<CollisionEnded>,
+    #[resource] spatial: &mut SpatialQuery,
+) {
+    let mut contacts = Vec::new();
+    let mut blocking = Vec::new();
+    {
+        let mut query = <(
+            Entity,
+            &Collider,
+            &Transform,
+            Option<&RigidBody>,
+            Option<&Mass>,
+            Option<&Parent>,
+        )>::query();
+        let placed: Vec<Placed> = query
+            .iter(world)
+            .map(|(entity, collider, transform, body, mass, parent)| {
+                let (shape, parent) = match parent {
+                    Some(parent) => {
+                        let parent = hierarchy::world_matrix(world, parent.0);
+                        let (position, rotation, scale) =
+                            (parent * transform.to_matrix()).decompose();
+                        let global = Transform {
+                            position,
+                            rotation,
+                            scale,
+                        };
+                        (collider.to_world(&global), parent)
+                    }
+                    None => (collider.to_world(transform), Matrix4x4::IDENTITY),
+                };
+                Placed {
+                    collider,
+                    shape,
+                    body: Body {
+                        entity: *entity,
+                        inverse_mass: match body {
+                            Some(RigidBody::Dynamic) => {
+                                mass.copied().unwrap_or_default().inverse_mass()
+                            }
+                            _ => 0.0,
+                        },
+                        parent,
+                    },
+                }
+            })
+            .collect();
+        let broadphase = SweepAndPrune::new(placed.iter().map(|p| p.shape.bounds()).collect());
+
+        for (i, j) in broadphase.pairs() {
+            let (a, b) = (&placed[i], &placed[j]);
+            if !a.collider.can_collide(b.collider) {
+                continue;
+            }
+            if let Some(contact) = narrowphase::contact(&a.shape, &b.shape) {
+                contacts.push((a.body.entity, b.body.entity, contact));
+                if a.collider.solidity.interaction(b.collider.solidity) == Interaction::Block {
+                    blocking.push((a.body, b.body, contact));
+                }
+            }
+        }
+
+        let colliders = placed
+            .into_iter()
+            .map(|p| query::Placed {
+                entity: p.body.entity,
+                layer: p.collider.layer,
+                shape: p.shape,
+            })
+            .collect();
+        *spatial = SpatialQuery::new(colliders, broadphase);
+    }
+
+    collisions.update(&contacts, started, ongoing, ended);
+    for (a, b, contact) in blocking {
+        resolve(world, a, b, &contact);
+    }
+}
+
+/// Move two overlapping bodies apart in proportion to their inverse masses,
+/// and stop them moving towards each other.
+fn resolve(world: &mut SubWorld, a: Body, b: Body, contact: &Contact) {
+    let total = a.inverse_mass + b.inverse_mass;
+    if total <= 0.0 {
+        return;
+    }
+
+    // In world space, as the contact is
+    let velocity = |world: &SubWorld, body: Body| {
+        world
+            .entry_ref(body.entity)
+            .ok()
+            .and_then(|entry| entry.get_component::<Velocity>().ok().copied())
+            .map_or(Vector3::ZERO, |v| {
+                body.parent.multiply_vector(Vector3::new(v.dx, v.dy, v.dz))
+            })
+    };
+    let closing = Vector3::dot(velocity(world, b) - velocity(world, a), contact.normal);
+    // Only cancel velocity towards each other, so separating bodies keep going
+    let impulse = if closing < 0.0 { -closing / total } else { 0.0 };
+
+    let push = contact.normal * (contact.depth / total);
+    let (inv_a, inv_b) = (a.inverse_mass, b.inverse_mass);
+    nudge(world, a, -push * inv_a, contact.normal * (-impulse * inv_a));
+    nudge(world, b, push * inv_b, contact.normal * (impulse * inv_b));
+}
+
+/// Move `body` by a world space `offset` and change its velocity by a world
+/// space `velocity_change`, both turned into its parent's space first.
+fn nudge(world: &mut SubWorld, body: Body, offset: Vector3, velocity_change: Vector3) {
+    // A parent scaled to nothing leaves no way back into its space
+    let to_parent = match body.parent.inverse() {
+        Some(inverse) => inverse,
+        None => return,
+    };
+    let offset = to_parent.multiply_vector(offset);
+    let velocity_change = to_parent.multiply_vector(velocity_change);
+    if let Ok(mut entry) = world.entry_mut(body.entity) {
+        if let Ok(transform) = entry.get_component_mut::<Transform>() {
+            transform.position += offset;
+        }
+        if let Ok(velocity) = entry.get_component_mut::<Velocity>() {
+            velocity.dx += velocity_change.x;
+            velocity.dy += velocity_change.y;
+            velocity.dz += velocity_change.z;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+    use crate::event::EventReader;
+    use crate::physics::collider::Solidity;
+    use crate::quaternion::Quaternion;
+    use legion::systems::Resource;
+
+    fn resources() -> Resources {
+        let mut resources = Resources::default();
+        resources.insert(Collisions::default());
+        resources.insert(Events::<CollisionStarted>::new());
+        resources.insert(Events::<CollisionOngoing>::new());
+        resources.insert(Events::<CollisionEnded>::new());
+        resources.insert(SpatialQuery::default());
+        resources
+    }
+
+    fn detect(world: &mut World, resources: &mut Resources) {
+        let mut schedule = Schedule::builder()
+            .add_system(Events::<CollisionStarted>::update_system())
+            .add_system(Events::<CollisionOngoing>::update_system())
+            .add_system(Events::<CollisionEnded>::update_system())
+            .add_system(detect_collisions_system())
+            .build();
+        schedule.execute(world, resources);
+    }
+
+    fn read<T: Copy + Resource>(resources: &Resources, reader: &mut EventReader<T>) -> Vec<T> {
+        let events = resources.get::<Events<T>>().unwrap();
+        reader.iter(&events).copied().collect()
+    }
+
+    fn position(world: &World, entity: Entity) -> Vector3 {
+        world
+            .entry_ref(entity)
+            .unwrap()
+            .get_component::<Transform>()
+            .unwrap()
+            .position
+    }
+
+    #[test]
+    fn collisions_start_go_on_and_end() {
+        let mut world = World::default();
+        let mut resources = resources();
+        let soft = Collider::sphere(1.0).with_solidity(Solidity::Soft);
+        let a = world.push((Transform::identity(), soft.clone()));
+        let b = world.push((Transform::from_position(Vector3::new(1.5, 0.0, 0.0)), soft));
+
+        let mut started = EventReader::<CollisionStarted>::new();
+        let mut ongoing = EventReader::<CollisionOngoing>::new();
+        let mut ended = EventReader::<CollisionEnded>::new();
+
+        detect(&mut world, &mut resources);
+        assert_eq!(read(&resources, &mut started).len(), 1);
+        assert!(resources.get::<Collisions>().unwrap().are_touching(b, a));
+
+        detect(&mut world, &mut resources);
+        assert!(read(&resources, &mut started).is_empty());
+        assert_eq!(read(&resources, &mut ongoing).len(), 1);
+
+        world
+            .entry(b)
+            .unwrap()
+            .get_component_mut::<Transform>()
+            .unwrap()
+            .position = Vector3::new(5.0, 0.0, 0.0);
+        detect(&mut world, &mut resources);
+        assert!(read(&resources, &mut started).is_empty());
+        assert!(read(&resources, &mut ongoing).is_empty());
+        assert_eq!(read(&resources, &mut ended), vec![CollisionEnded { a, b }]);
+        assert!(!resources.get::<Collisions>().unwrap().are_touching(a, b));
+    }
+
+    #[test]
+    fn hard_colliders_push_dynamic_bodies_out_of_static_ones() {
+        let mut world = World::default();
+        let mut resources = resources();
+        let ground = world.push((
+            Transform::identity(),
+            Collider::aabb(Vector3::new(10.0, 1.0, 10.0)),
+            RigidBody::Static,
+        ));
+        let ball = world.push((
+            Transform::from_position(Vector3::new(0.0, 1.5, 0.0)),
+            Velocity::new(1.0, -3.0, 0.0),
+            Collider::sphere(1.0),
+            RigidBody::Dynamic,
+        ));
+
+        detect(&mut world, &mut resources);
+
+        assert_approx_eq!(position(&world, ground), Vector3::ZERO);
+        // Sphere against box goes through EPA, which is accurate to about 1e-4
+        assert_approx_eq!(position(&world, ball), Vector3::new(0.0, 2.0, 0.0), 1e-3);
+        let velocity = *world
+            .entry_ref(ball)
+            .unwrap()
+            .get_component::<Velocity>()
+            .unwrap();
+        assert_approx_eq!(velocity.dx, 1.0, 1e-3);
+        assert_approx_eq!(velocity.dy, 0.0, 1e-3);
+    }
+
+    #[test]
+    fn child_colliders_are_placed_and_pushed_in_world_space() {
+        let mut world = World::default();
+        let mut resources = resources();
+        world.push((
+            Transform::identity(),
+            Collider::aabb(Vector3::new(10.0, 1.0, 10.0)),
+            RigidBody::Static,
+        ));
+        // Upside down and twice the size, so the child's local up is world down
+        let parent = world.push((Transform {
+            position: Vector3::new(0.0, 10.0, 0.0),
+            rotation: Quaternion::angle_axis(180.0, Vector3::FORWARD),
+            scale: Vector3::new(2.0, 2.0, 2.0),
+        },));
+        let ball = world.push((
+            Transform::from_position(Vector3::new(0.0, 4.25, 0.0)),
+            Velocity::new(0.0, 3.0, 0.0),
+            Collider::sphere(0.5),
+            RigidBody::Dynamic,
+        ));
+        hierarchy::set_parent(&mut world, ball, parent, hierarchy::ReparentMode::KeepLocal)
+            .unwrap();
+
+        detect(&mut world, &mut resources);
+
+        assert_eq!(
+            read(&resources, &mut EventReader::<CollisionStarted>::new()).len(),
+            1
+        );
+        // Pushed up out of the ground by 0.5, which is 0.25 down in the parent
+        assert_approx_eq!(position(&world, ball), Vector3::new(0.0, 4.0, 0.0), 1e-3);
+        let velocity = *world
+            .entry_ref(ball)
+            .unwrap()
+            .get_component::<Velocity>()
+            .unwrap();
+        assert_approx_eq!(velocity.dy, 0.0, 1e-3);
+    }
+
+    #[test]
+    fn soft_colliders_overlap_and_spectral_ones_are_ignored() {
+        let mut world = World::default();
+        let mut resources = resources();
+        let hard = world.push((
+            Transform::identity(),
+            Collider::sphere(1.0),
+            RigidBody::Dynamic,
+        ));
+        let soft = world.push((
+            Transform::from_position(Vector3::new(1.0, 0.0, 0.0)),
+            Collider::sphere(1.0).with_solidity(Solidity::Soft),
+            RigidBody::Dynamic,
+        ));
+        world.push((
+            Transform::from_position(Vector3::new(-1.0, 0.0, 0.0)),
+            Collider::sphere(1.0).with_solidity(Solidity::Spectral),
+            RigidBody::Dynamic,
+        ));
+
+        detect(&mut world, &mut resources);
+
+        assert_eq!(
+            read(&resources, &mut EventReader::<CollisionStarted>::new()).len(),
+            1
+        );
+        assert!(resources
+            .get::<Collisions>()
+            .unwrap()
+            .are_touching(hard, soft));
+        assert_approx_eq!(position(&world, hard), Vector3::ZERO);
+        assert_approx_eq!(position(&world, soft), Vector3::new(1.0, 0.0, 0.0));
+    }
+}
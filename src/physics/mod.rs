@@ -0,0 +1,399 @@
+// Physics
+//
+// Rigid bodies are integrated once per fixed simulation tick, so a run is
+// reproducible given the same starting world and inputs: every step uses the
+// same `dt`, bodies are visited in storage order and nothing depends on wall
+// clock time.
+
+mod broadphase;
+pub mod collider;
+pub mod collision;
+mod narrowphase;
+pub mod query;
+
+pub use self::narrowphase::Contact;
+
+use legion::*;
+
+use crate::component::{Transform, Velocity};
+use crate::quaternion::Quaternion;
+use crate::time::FixedTime;
+use crate::vector::Vector3;
+
+/// How a body responds to the simulation. Entities with a `RigidBody` also
+/// need a `Transform` and a `Velocity`, and are integrated by
+/// `integrate_bodies` rather than `update_positions`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RigidBody {
+    /// Moved by its velocity, which forces and gravity change.
+    Dynamic,
+    /// Moved by its velocity only, e.g. a platform driven by game code.
+    Kinematic,
+    /// Never moved by the simulation.
+    Static,
+}
+
+/// Rotation speed about each world axis, in radians per second.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AngularVelocity(pub Vector3);
+
+/// Resistance of a body to forces and torques.
+///
+/// Bodies without a `Mass` are treated as having a mass of 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mass {
+    inverse_mass: f32,
+    inverse_inertia: f32,
+}
+
+impl Mass {
+    /// A body of `mass` kilograms, whose inertia is the same as its mass.
+    pub fn new(mass: f32) -> Mass {
+        Mass::with_inertia(mass, mass)
+    }
+
+    /// A body of `mass` kilograms with a rotational inertia of `inertia`.
+    ///
+    /// A value of zero or less is treated as infinite.
+    pub fn with_inertia(mass: f32, inertia: f32) -> Mass {
+        let inverse = |value: f32| if value > 0.0 { 1.0 / value } else { 0.0 };
+        Mass {
+            inverse_mass: inverse(mass),
+            inverse_inertia: inverse(inertia),
+        }
+    }
+
+    /// A body no force or torque can move.
+    pub fn infinite() -> Mass {
+        Mass {
+            inverse_mass: 0.0,
+            inverse_inertia: 0.0,
+        }
+    }
+
+    pub fn inverse_mass(&self) -> f32 {
+        self.inverse_mass
+    }
+
+    pub fn inverse_inertia(&self) -> f32 {
+        self.inverse_inertia
+    }
+}
+
+impl Default for Mass {
+    fn default() -> Self {
+        Mass::new(1.0)
+    }
+}
+
+/// Forces, torques and impulses to apply on the next tick, cleared after
+/// each step.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Forces {
+    force: Vector3,
+    torque: Vector3,
+    impulse: Vector3,
+    angular_impulse: Vector3,
+}
+
+impl Forces {
+    /// Push continuously, in newtons, for the next tick.
+    pub fn add_force(&mut self, force: Vector3) {
+        self.force += force;
+    }
+
+    /// Twist continuously, in newton metres about each world axis, for the next tick.
+    pub fn add_torque(&mut self, torque: Vector3) {
+        self.torque += torque;
+    }
+
+    /// Change momentum instantly, in newton seconds, e.g. for a jump or a hit.
+    pub fn add_impulse(&mut self, impulse: Vector3) {
+        self.impulse += impulse;
+    }
+
+    /// Change angular momentum instantly.
+    pub fn add_angular_impulse(&mut self, impulse: Vector3) {
+        self.angular_impulse += impulse;
+    }
+
+    pub fn clear(&mut self) {
+        *self = Forces::default();
+    }
+}
+
+/// Fraction of linear and angular velocity lost per second, like air drag.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Damping {
+    pub linear: f32,
+    pub angular: f32,
+}
+
+/// Acceleration applied to every dynamic body, in metres per second squared.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gravity(pub Vector3);
+
+impl Default for Gravity {
+    fn default() -> Self {
+        Gravity(Vector3::new(0.0, -9.81, 0.0))
+    }
+}
+
+/// Scheme used to advance bodies by one tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Integrator {
+    /// Update velocity first, then move by the new velocity. Cheap and stable,
+    /// the usual choice for games.
+    SemiImplicitEuler,
+    /// Velocity Verlet, moving by the average of the old and new velocity.
+    /// Follows projectile arcs exactly for a constant force.
+    Verlet,
+}
+
+/// Simulation settings, read by `integrate_bodies` on every tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhysicsConfig {
+    pub integrator: Integrator,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        PhysicsConfig {
+            integrator: Integrator::SemiImplicitEuler,
+        }
+    }
+}
+
+/// Advance every rigid body by one fixed step.
+// One parameter per component and resource is how legion's for_each systems
+// take them, so they're grouped up in `integrate` instead.
+#[allow(clippy::too_many_arguments)]
+#[system(for_each)]
+pub fn integrate_bodies(
+    body: &RigidBody,
+    transform: &mut Transform,
+    velocity: &mut Velocity,
+    angular_velocity: Option<&mut AngularVelocity>,
+    mass: Option<&Mass>,
+    forces: Option<&mut Forces>,
+    damping: Option<&Damping>,
+    #[resource] gravity: &Gravity,
+    #[resource] config: &PhysicsConfig,
+    #[resource] time: &FixedTime,
+) {
+    let mut spin = angular_velocity
+        .as_ref()
+        .map(|w| w.0)
+        .unwrap_or(Vector3::ZERO);
+    let mut linear = Vector3::new(velocity.dx, velocity.dy, velocity.dz);
+    let state = BodyState {
+        body: *body,
+        mass: mass.copied().unwrap_or_default(),
+        forces: forces.as_deref().copied().unwrap_or_default(),
+        damping: damping.copied().unwrap_or_default(),
+    };
+    let step = Step {
+        gravity: gravity.0,
+        integrator: config.integrator,
+        dt: time.step_secs(),
+    };
+    integrate(&state, transform, &mut linear, &mut spin, &step);
+    *velocity = Velocity::new(linear.x, linear.y, linear.z);
+    if let Some(angular_velocity) = angular_velocity {
+        angular_velocity.0 = spin;
+    }
+    if let Some(forces) = forces {
+        forces.clear();
+    }
+}
+
+/// What a body brings to a step, with missing components defaulted.
+#[derive(Clone, Copy, Debug)]
+struct BodyState {
+    body: RigidBody,
+    mass: Mass,
+    forces: Forces,
+    damping: Damping,
+}
+
+/// Settings of a step, the same for every body.
+#[derive(Clone, Copy, Debug)]
+struct Step {
+    gravity: Vector3,
+    integrator: Integrator,
+    dt: f32,
+}
+
+/// One step of `integrate_bodies` for a single body.
+fn integrate(
+    state: &BodyState,
+    transform: &mut Transform,
+    velocity: &mut Vector3,
+    angular_velocity: &mut Vector3,
+    step: &Step,
+) {
+    let BodyState {
+        body,
+        mass,
+        forces,
+        damping,
+    } = *state;
+    let Step {
+        gravity,
+        integrator,
+        dt,
+    } = *step;
+    if body == RigidBody::Static {
+        *velocity = Vector3::ZERO;
+        *angular_velocity = Vector3::ZERO;
+        return;
+    }
+
+    // Impulses take effect at the start of the step, forces over the whole of it
+    if body == RigidBody::Dynamic {
+        *velocity += forces.impulse * mass.inverse_mass;
+        *angular_velocity += forces.angular_impulse * mass.inverse_inertia;
+    }
+    let start = *velocity;
+    if body == RigidBody::Dynamic {
+        let acceleration = gravity + forces.force * mass.inverse_mass;
+        *velocity = (*velocity + acceleration * dt) * drag(damping.linear, dt);
+        let angular_acceleration = forces.torque * mass.inverse_inertia;
+        *angular_velocity =
+            (*angular_velocity + angular_acceleration * dt) * drag(damping.angular, dt);
+    }
+
+    transform.position += match integrator {
+        Integrator::SemiImplicitEuler => *velocity * dt,
+        Integrator::Verlet => (start + *velocity) * (0.5 * dt),
+    };
+
+    let speed = angular_velocity.magnitude();
+    if speed > 0.0 {
+        let turn = Quaternion::angle_axis((speed * dt).to_degrees(), *angular_velocity);
+        transform.rotation = (turn * transform.rotation).normalized();
+    }
+}
+
+/// Factor applied to a velocity for `damping` over `dt`, stable for any step.
+fn drag(damping: f32, dt: f32) -> f32 {
+    1.0 / (1.0 + damping.max(0.0) * dt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn dynamic() -> BodyState {
+        BodyState {
+            body: RigidBody::Dynamic,
+            mass: Mass::default(),
+            forces: Forces::default(),
+            damping: Damping::default(),
+        }
+    }
+
+    /// One second-long semi-implicit Euler step of `state` from the origin,
+    /// returning where it ended up and its new velocity and spin.
+    fn step(
+        state: BodyState,
+        velocity: Vector3,
+        spin: Vector3,
+        gravity: Vector3,
+    ) -> (Transform, Vector3, Vector3) {
+        let mut transform = Transform::identity();
+        let (mut velocity, mut spin) = (velocity, spin);
+        let step = Step {
+            gravity,
+            integrator: Integrator::SemiImplicitEuler,
+            dt: 1.0,
+        };
+        integrate(&state, &mut transform, &mut velocity, &mut spin, &step);
+        (transform, velocity, spin)
+    }
+
+    fn fall(integrator: Integrator, seconds: f32) -> Transform {
+        let mut transform = Transform::identity();
+        let (mut velocity, mut spin) = (Vector3::ZERO, Vector3::ZERO);
+        let step = Step {
+            gravity: Gravity::default().0,
+            integrator,
+            dt: DT,
+        };
+        for _ in 0..(seconds / DT).round() as u32 {
+            integrate(&dynamic(), &mut transform, &mut velocity, &mut spin, &step);
+        }
+        transform
+    }
+
+    #[test]
+    fn verlet_follows_a_constant_acceleration_exactly() {
+        let fallen = fall(Integrator::Verlet, 1.0);
+        assert_approx_eq!(fallen.position.y, -9.81 / 2.0, 1e-3);
+
+        // Semi-implicit Euler overshoots by half a step's worth of velocity
+        let fallen = fall(Integrator::SemiImplicitEuler, 1.0);
+        assert_approx_eq!(fallen.position.y, -9.81 / 2.0 - 9.81 * DT / 2.0, 1e-3);
+    }
+
+    #[test]
+    fn mass_scales_forces_and_impulses() {
+        let mut forces = Forces::default();
+        forces.add_force(Vector3::new(10.0, 0.0, 0.0));
+        forces.add_impulse(Vector3::new(0.0, 0.0, 2.0));
+        let state = BodyState {
+            mass: Mass::new(2.0),
+            forces,
+            ..dynamic()
+        };
+
+        let (transform, velocity, _) = step(state, Vector3::ZERO, Vector3::ZERO, Vector3::ZERO);
+
+        assert_approx_eq!(velocity, Vector3::new(5.0, 0.0, 1.0));
+        assert_approx_eq!(transform.position, Vector3::new(5.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn kinematic_and_static_bodies_ignore_gravity() {
+        for body in [RigidBody::Kinematic, RigidBody::Static].iter() {
+            let state = BodyState {
+                body: *body,
+                ..dynamic()
+            };
+            let (transform, _, _) =
+                step(state, Vector3::RIGHT, Vector3::ZERO, Gravity::default().0);
+
+            let expected = match body {
+                RigidBody::Kinematic => Vector3::RIGHT,
+                _ => Vector3::ZERO,
+            };
+            assert_eq!(transform.position, expected);
+        }
+    }
+
+    #[test]
+    fn angular_velocity_turns_the_body() {
+        let spin = Vector3::new(0.0, std::f32::consts::FRAC_PI_2, 0.0);
+        let (transform, _, _) = step(dynamic(), Vector3::ZERO, spin, Vector3::ZERO);
+
+        assert_approx_eq!(transform.forward(), Vector3::RIGHT);
+    }
+
+    #[test]
+    fn damping_slows_bodies_down() {
+        let state = BodyState {
+            damping: Damping {
+                linear: 1.0,
+                angular: 3.0,
+            },
+            ..dynamic()
+        };
+        let (_, velocity, spin) = step(state, Vector3::RIGHT, Vector3::UP, Vector3::ZERO);
+
+        assert_approx_eq!(velocity, Vector3::new(0.5, 0.0, 0.0));
+        assert_approx_eq!(spin, Vector3::new(0.0, 0.25, 0.0));
+    }
+}
@@ -0,0 +1,579 @@
+// Narrowphase
+//
+// GJK decides whether two convex shapes intersect by searching their
+// Minkowski difference for the origin, and EPA then expands the final simplex
+// to find the shallowest way out. Both only ask the shapes for support points,
+// so every shape pair shares one path. Spheres and capsules are common, and
+// curved enough that EPA converges slowly on them, so pairs of those are
+// solved exactly from the closest points of their core segments instead.
+//
+// Casts use the GJK ray cast from van den Bergen's Collision Detection in
+// Interactive 3D Environments: a ray is stepped forward to each separating
+// plane GJK finds until it reaches the shape. A shape swept along a line is
+// the same ray cast against the Minkowski difference.
+
+use super::collider::WorldShape;
+use crate::vector::Vector3;
+
+const GJK_MAX_ITERATIONS: usize = 64;
+/// The origin is treated as lying on a simplex feature closer than this.
+const GJK_TOLERANCE: f32 = 1e-5;
+const EPA_MAX_ITERATIONS: usize = 64;
+const EPA_TOLERANCE: f32 = 1e-4;
+const CAST_MAX_ITERATIONS: usize = 64;
+const CAST_TOLERANCE: f32 = 1e-4;
+
+/// How two intersecting colliders touch.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    /// Unit direction from the first collider towards the second.
+    pub normal: Vector3,
+    /// How far the second collider must move along `normal` to separate them.
+    pub depth: f32,
+    /// A point halfway between the deepest points of each collider.
+    pub point: Vector3,
+}
+
+/// Returns the contact between `a` and `b`, or `None` if they don't overlap.
+/// Shapes that only touch don't overlap.
+pub(crate) fn contact(a: &WorldShape, b: &WorldShape) -> Option<Contact> {
+    if let (Some(a), Some(b)) = (rounded_segment(a), rounded_segment(b)) {
+        return rounded_contact(a, b);
+    }
+
+    let simplex = gjk(a, b)?;
+    let (normal, depth) = epa(a, b, simplex)?;
+    Some(Contact {
+        normal,
+        depth,
+        point: (a.support(normal) + b.support(-normal)) * 0.5,
+    })
+}
+
+/// A sphere or capsule as the segment it rounds, and its radius.
+fn rounded_segment(shape: &WorldShape) -> Option<(Vector3, Vector3, f32)> {
+    match shape {
+        WorldShape::Sphere { center, radius } => Some((*center, *center, *radius)),
+        WorldShape::Capsule { a, b, radius } => Some((*a, *b, *radius)),
+        _ => None,
+    }
+}
+
+fn rounded_contact(
+    (p1, q1, ra): (Vector3, Vector3, f32),
+    (p2, q2, rb): (Vector3, Vector3, f32),
+) -> Option<Contact> {
+    let (ca, cb) = closest_points(p1, q1, p2, q2);
+    let offset = cb - ca;
+    let distance = offset.magnitude();
+    if distance >= ra + rb {
+        return None;
+    }
+    let normal = if distance > 0.0 {
+        offset / distance
+    } else {
+        Vector3::UP
+    };
+    Some(Contact {
+        normal,
+        depth: ra + rb - distance,
+        point: ((ca + normal * ra) + (cb - normal * rb)) * 0.5,
+    })
+}
+
+/// Closest points between the segments `p1 q1` and `p2 q2`, after Ericson's
+/// Real-Time Collision Detection.
+fn closest_points(p1: Vector3, q1: Vector3, p2: Vector3, q2: Vector3) -> (Vector3, Vector3) {
+    let (d1, d2, r) = (q1 - p1, q2 - p2, p1 - p2);
+    let (a, e, f) = (d1.sqr_magnitude(), d2.sqr_magnitude(), Vector3::dot(d2, r));
+    let clamp = |x: f32| x.clamp(0.0, 1.0);
+
+    let (s, t) = if a <= f32::EPSILON && e <= f32::EPSILON {
+        (0.0, 0.0)
+    } else if a <= f32::EPSILON {
+        (0.0, clamp(f / e))
+    } else {
+        let c = Vector3::dot(d1, r);
+        if e <= f32::EPSILON {
+            (clamp(-c / a), 0.0)
+        } else {
+            let b = Vector3::dot(d1, d2);
+            let denominator = a * e - b * b;
+            // Parallel segments have no single closest pair, so start from p1
+            let s = if denominator > 0.0 {
+                clamp((b * f - c * e) / denominator)
+            } else {
+                0.0
+            };
+            let t = (b * s + f) / e;
+            if t < 0.0 {
+                (clamp(-c / a), 0.0)
+            } else if t > 1.0 {
+                (clamp((b - c) / a), 1.0)
+            } else {
+                (s, t)
+            }
+        }
+    };
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+/// Returns true if `a` and `b` overlap, without working out how deeply.
+pub(crate) fn intersects(a: &WorldShape, b: &WorldShape) -> bool {
+    match (rounded_segment(a), rounded_segment(b)) {
+        (Some(a), Some(b)) => rounded_contact(a, b).is_some(),
+        _ => gjk(a, b).is_some(),
+    }
+}
+
+/// Moves `caster` along the unit vector `direction` until it first touches
+/// `target`, returning the distance moved and the target's surface normal
+/// there. Shapes that overlap to begin with aren't hit.
+pub(crate) fn cast(
+    caster: &WorldShape,
+    target: &WorldShape,
+    direction: Vector3,
+    max_distance: f32,
+) -> Option<(f32, Vector3)> {
+    let mut distance = 0.0;
+    let mut x = Vector3::ZERO;
+    let mut normal = Vector3::ZERO;
+    let mut points: Vec<Vector3> = Vec::with_capacity(4);
+    // Closest point to `x` of the simplex, as a vector from it to `x`
+    let mut v = x - (target.center() - caster.center());
+
+    for _ in 0..CAST_MAX_ITERATIONS {
+        if v.sqr_magnitude() <= CAST_TOLERANCE * CAST_TOLERANCE {
+            break;
+        }
+        let point = support(target, caster, v);
+        let w = x - point;
+        let separated = Vector3::dot(v, w) > 0.0;
+        if separated {
+            // `point` lies on a plane separating `x` from the shape, so step
+            // the ray up to it, or give up if the ray is heading away
+            let approach = Vector3::dot(v, direction);
+            if approach >= 0.0 {
+                return None;
+            }
+            distance -= Vector3::dot(v, w) / approach;
+            if distance > max_distance {
+                return None;
+            }
+            x = direction * distance;
+            normal = v;
+        }
+        if points.contains(&point) {
+            // Rounding near an edge can leave GJK circling the same points
+            if !separated {
+                break;
+            }
+        } else {
+            points.push(point);
+        }
+        let offsets: Vec<Vector3> = points.iter().map(|p| x - *p).collect();
+        let (closest, kept) = closest_on_simplex(&offsets);
+        points = kept.into_iter().map(|i| points[i]).collect();
+        v = closest;
+    }
+
+    // A ray that never had to step started inside the shape
+    if normal == Vector3::ZERO {
+        None
+    } else {
+        Some((distance, normal.normalized()))
+    }
+}
+
+/// Returns the point of the simplex `points` closest to the origin, and the
+/// indices of the smallest set of points it is a blend of.
+fn closest_on_simplex(points: &[Vector3]) -> (Vector3, Vec<usize>) {
+    let mut best: Option<(f32, Vector3, Vec<usize>)> = None;
+    // Smaller subsets first, so ties keep the simplest feature
+    for size in 1..=points.len() {
+        for subset in 1u32..(1 << points.len()) {
+            if subset.count_ones() as usize != size {
+                continue;
+            }
+            let indices: Vec<usize> = (0..points.len())
+                .filter(|i| subset & (1 << i) != 0)
+                .collect();
+            let corners: Vec<Vector3> = indices.iter().map(|&i| points[i]).collect();
+            if let Some(closest) = closest_on_affine_hull(&corners) {
+                let distance = closest.sqr_magnitude();
+                match &best {
+                    Some((nearest, _, _)) if *nearest <= distance => {}
+                    _ => best = Some((distance, closest, indices)),
+                }
+            }
+        }
+    }
+    best.map_or((Vector3::ZERO, Vec::new()), |(_, closest, indices)| {
+        (closest, indices)
+    })
+}
+
+/// Projects the origin onto the plane, line or point through `corners`.
+/// Returns `None` if the projection falls outside them or they're degenerate.
+fn closest_on_affine_hull(corners: &[Vector3]) -> Option<Vector3> {
+    let base = corners[0];
+    let edges: Vec<Vector3> = corners[1..].iter().map(|c| *c - base).collect();
+    // Normal equations for the blend weights of each edge
+    let mut rows = [[0.0f32; 4]; 3];
+    for (i, row) in rows.iter_mut().enumerate().take(edges.len()) {
+        for (j, edge) in edges.iter().enumerate() {
+            row[j] = Vector3::dot(edges[i], *edge);
+        }
+        row[3] = -Vector3::dot(edges[i], base);
+    }
+    let weights = solve(&mut rows[..edges.len()])?;
+    let sum: f32 = weights.iter().sum();
+    if sum > 1.0 + 1e-6 || weights.iter().any(|&t| t < -1e-6) {
+        return None;
+    }
+    Some(
+        edges
+            .iter()
+            .zip(weights.iter())
+            .fold(base, |point, (edge, t)| point + *edge * *t),
+    )
+}
+
+/// Solves a small linear system given as augmented rows, by Gaussian
+/// elimination with partial pivoting.
+fn solve(rows: &mut [[f32; 4]]) -> Option<Vec<f32>> {
+    let n = rows.len();
+    for column in 0..n {
+        let pivot = (column..n).max_by(|&a, &b| {
+            rows[a][column]
+                .abs()
+                .partial_cmp(&rows[b][column].abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })?;
+        if rows[pivot][column].abs() <= f32::EPSILON {
+            return None;
+        }
+        rows.swap(column, pivot);
+        let top = rows[column];
+        for row in rows[column + 1..].iter_mut() {
+            let factor = row[column] / top[column];
+            for (value, above) in row.iter_mut().zip(top.iter()).skip(column) {
+                *value -= factor * above;
+            }
+        }
+    }
+    let mut solution = vec![0.0; n];
+    for row in (0..n).rev() {
+        let known: f32 = (row + 1..n).map(|k| rows[row][k] * solution[k]).sum();
+        solution[row] = (rows[row][3] - known) / rows[row][row];
+    }
+    Some(solution)
+}
+
+/// Support point of the Minkowski difference `a - b`.
+fn support(a: &WorldShape, b: &WorldShape, direction: Vector3) -> Vector3 {
+    a.support(direction) - b.support(-direction)
+}
+
+/// Returns a tetrahedron of `a - b` enclosing the origin if the shapes
+/// overlap. The simplex is kept newest point first.
+fn gjk(a: &WorldShape, b: &WorldShape) -> Option<Vec<Vector3>> {
+    let mut direction = b.center() - a.center();
+    if direction == Vector3::ZERO {
+        direction = Vector3::RIGHT;
+    }
+    let first = support(a, b, direction);
+    if first == Vector3::ZERO {
+        return None;
+    }
+    let mut simplex = vec![first];
+    direction = -first;
+
+    for _ in 0..GJK_MAX_ITERATIONS {
+        let point = support(a, b, direction);
+        if Vector3::dot(point, direction) <= 0.0 {
+            return None;
+        }
+        simplex.insert(0, point);
+        if next_simplex(&mut simplex, &mut direction) {
+            return Some(simplex);
+        }
+    }
+    None
+}
+
+/// Reduces the simplex to the feature nearest the origin and points
+/// `direction` at it. Returns true once a tetrahedron encloses the origin.
+fn next_simplex(simplex: &mut Vec<Vector3>, direction: &mut Vector3) -> bool {
+    match simplex.len() {
+        2 => line(simplex, direction),
+        3 => triangle(simplex, direction),
+        _ => tetrahedron(simplex, direction),
+    }
+}
+
+fn line(simplex: &mut Vec<Vector3>, direction: &mut Vector3) -> bool {
+    let (a, b) = (simplex[0], simplex[1]);
+    let (ab, ao) = (b - a, -a);
+    if Vector3::dot(ab, ao) > 0.0 {
+        *direction = triple_cross(ab, ao, ab);
+        // The length is |ab|^2 times the origin's distance from the line. When
+        // the origin lies on the segment, step off it sideways instead.
+        if direction.magnitude() <= GJK_TOLERANCE * ab.sqr_magnitude() {
+            *direction = perpendicular(ab);
+        }
+    } else {
+        simplex.truncate(1);
+        *direction = ao;
+    }
+    false
+}
+
+fn triangle(simplex: &mut Vec<Vector3>, direction: &mut Vector3) -> bool {
+    let (a, b, c) = (simplex[0], simplex[1], simplex[2]);
+    let (ab, ac, ao) = (b - a, c - a, -a);
+    let abc = Vector3::cross(ab, ac);
+
+    if Vector3::dot(Vector3::cross(abc, ac), ao) > 0.0 {
+        if Vector3::dot(ac, ao) > 0.0 {
+            *simplex = vec![a, c];
+            *direction = triple_cross(ac, ao, ac);
+            false
+        } else {
+            *simplex = vec![a, b];
+            line(simplex, direction)
+        }
+    } else if Vector3::dot(Vector3::cross(ab, abc), ao) > 0.0 {
+        *simplex = vec![a, b];
+        line(simplex, direction)
+    } else if Vector3::dot(abc, ao) > 0.0 {
+        *direction = abc;
+        false
+    } else {
+        *simplex = vec![a, c, b];
+        *direction = -abc;
+        false
+    }
+}
+
+fn tetrahedron(simplex: &mut Vec<Vector3>, direction: &mut Vector3) -> bool {
+    let (a, b, c, d) = (simplex[0], simplex[1], simplex[2], simplex[3]);
+    let (ab, ac, ad, ao) = (b - a, c - a, d - a, -a);
+
+    let faces = [
+        (Vector3::cross(ab, ac), [a, b, c]),
+        (Vector3::cross(ac, ad), [a, c, d]),
+        (Vector3::cross(ad, ab), [a, d, b]),
+    ];
+    // An origin on a face counts as enclosed, and EPA finds a shallow contact
+    for (normal, face) in faces.iter() {
+        if Vector3::dot(*normal, ao) > GJK_TOLERANCE * normal.magnitude() {
+            *simplex = face.to_vec();
+            return triangle(simplex, direction);
+        }
+    }
+    true
+}
+
+/// Returns the face normal of `a - b` nearest the origin and its distance,
+/// starting from the tetrahedron GJK ended on.
+fn epa(a: &WorldShape, b: &WorldShape, simplex: Vec<Vector3>) -> Option<(Vector3, f32)> {
+    let mut polytope = simplex;
+    let mut faces: Vec<[usize; 3]> = vec![[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]];
+    // Wind every face outwards once, so neighbouring faces run their shared
+    // edge in opposite directions. New faces inherit the winding of the hole.
+    let inside = polytope.iter().fold(Vector3::ZERO, |sum, p| sum + *p) * 0.25;
+    let (p0, p1, p2) = (polytope[0], polytope[1], polytope[2]);
+    if Vector3::dot(Vector3::cross(p1 - p0, p2 - p0), inside - p0) > 0.0 {
+        for face in faces.iter_mut() {
+            face.swap(1, 2);
+        }
+    }
+    let mut normals = face_normals(&polytope, &faces);
+
+    for iteration in 0..EPA_MAX_ITERATIONS {
+        let &(normal, distance) = normals
+            .iter()
+            .min_by(|x, y| x.1.partial_cmp(&y.1).unwrap_or(std::cmp::Ordering::Equal))?;
+        if !distance.is_finite() {
+            return None;
+        }
+
+        // Curved shapes can need many faces, so settle for the best so far
+        let point = support(a, b, normal);
+        let converged = Vector3::dot(normal, point) - distance <= EPA_TOLERANCE;
+        if converged || iteration + 1 == EPA_MAX_ITERATIONS {
+            return if distance > 0.0 {
+                Some((normal, distance))
+            } else {
+                None
+            };
+        }
+
+        // Remove every face the new point can see, keeping the edges of the
+        // hole they leave behind
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        let mut i = 0;
+        while i < faces.len() {
+            let face = faces[i];
+            if Vector3::dot(normals[i].0, point - polytope[face[0]]) > 0.0 {
+                for &(from, to) in &[(face[0], face[1]), (face[1], face[2]), (face[2], face[0])] {
+                    match edges.iter().position(|&edge| edge == (to, from)) {
+                        Some(shared) => {
+                            edges.swap_remove(shared);
+                        }
+                        None => edges.push((from, to)),
+                    }
+                }
+                faces.swap_remove(i);
+                normals.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
+        let index = polytope.len();
+        polytope.push(point);
+        let new_faces: Vec<[usize; 3]> =
+            edges.iter().map(|&(from, to)| [from, to, index]).collect();
+        normals.extend(face_normals(&polytope, &new_faces));
+        faces.extend(new_faces);
+    }
+    None
+}
+
+/// Unit normal and distance from the origin of each face. Faces with no area
+/// are infinitely far, so they are never chosen.
+fn face_normals(polytope: &[Vector3], faces: &[[usize; 3]]) -> Vec<(Vector3, f32)> {
+    faces
+        .iter()
+        .map(|face| {
+            let (a, b, c) = (polytope[face[0]], polytope[face[1]], polytope[face[2]]);
+            let normal = Vector3::cross(b - a, c - a).normalized();
+            if normal == Vector3::ZERO {
+                (normal, f32::INFINITY)
+            } else {
+                (normal, Vector3::dot(normal, a))
+            }
+        })
+        .collect()
+}
+
+/// `(a x b) x c`, which for `triple_cross(ab, ao, ab)` points from the line
+/// through `ab` towards the origin.
+fn triple_cross(a: Vector3, b: Vector3, c: Vector3) -> Vector3 {
+    Vector3::cross(Vector3::cross(a, b), c)
+}
+
+/// Any direction at right angles to `v`.
+fn perpendicular(v: Vector3) -> Vector3 {
+    let other = if v.x.abs() < 0.9 {
+        Vector3::RIGHT
+    } else {
+        Vector3::UP
+    };
+    Vector3::cross(v, other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+    use crate::component::Transform;
+    use crate::physics::collider::Collider;
+    use crate::quaternion::Quaternion;
+
+    fn place(collider: Collider, position: Vector3) -> WorldShape {
+        collider.to_world(&Transform::from_position(position))
+    }
+
+    #[test]
+    fn spheres_contact_along_the_line_between_centers() {
+        let a = place(Collider::sphere(1.0), Vector3::ZERO);
+        let b = place(Collider::sphere(1.0), Vector3::new(1.5, 0.0, 0.0));
+
+        let contact = contact(&a, &b).unwrap();
+
+        assert_approx_eq!(contact.normal, Vector3::RIGHT);
+        assert_approx_eq!(contact.depth, 0.5);
+        assert_approx_eq!(contact.point, Vector3::new(0.75, 0.0, 0.0));
+        assert_eq!(
+            super::contact(
+                &a,
+                &place(Collider::sphere(1.0), Vector3::new(2.0, 0.0, 0.0))
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn crossed_capsules_touch_where_their_axes_pass() {
+        let upright = place(Collider::capsule(1.0, 0.5), Vector3::ZERO);
+        let lying = Collider::capsule(1.0, 0.5).to_world(
+            &Transform::from_position(Vector3::new(0.0, 0.5, 0.8))
+                .with_rotation(Quaternion::angle_axis(90.0, Vector3::FORWARD)),
+        );
+
+        let contact = contact(&upright, &lying).unwrap();
+
+        assert_approx_eq!(contact.normal, Vector3::FORWARD);
+        assert_approx_eq!(contact.depth, 0.2);
+    }
+
+    #[test]
+    fn boxes_separate_along_the_shallowest_axis() {
+        let a = place(Collider::aabb(Vector3::ONE), Vector3::ZERO);
+        let b = place(Collider::aabb(Vector3::ONE), Vector3::new(0.5, 1.8, 0.0));
+
+        let contact = contact(&a, &b).unwrap();
+
+        assert_approx_eq!(contact.normal, Vector3::UP, 1e-3);
+        assert_approx_eq!(contact.depth, 0.2, 1e-3);
+    }
+
+    #[test]
+    fn separated_shapes_have_no_contact() {
+        let rotated = Collider::obb(Vector3::ONE).to_world(
+            &Transform::from_position(Vector3::new(2.2, 0.0, 0.0))
+                .with_rotation(Quaternion::angle_axis(45.0, Vector3::UP)),
+        );
+        let capsule = place(Collider::capsule(1.0, 0.5), Vector3::new(-1.0, 0.0, 0.0));
+
+        // The rotated box's nearest corner is at x = 2.2 - sqrt(2), inside the
+        // unit box but clear of the capsule
+        assert_eq!(contact(&capsule, &rotated), None);
+        assert!(contact(
+            &place(Collider::aabb(Vector3::ONE), Vector3::ZERO),
+            &rotated
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn capsule_and_hull_penetration_matches_the_overlap() {
+        let capsule = place(Collider::capsule(1.0, 0.5), Vector3::ZERO);
+        let hull = place(
+            Collider::convex_hull(vec![
+                Vector3::new(-1.0, -1.0, -1.0),
+                Vector3::new(1.0, -1.0, -1.0),
+                Vector3::new(0.0, -1.0, 1.0),
+                Vector3::new(0.0, 1.0, 0.0),
+            ]),
+            Vector3::new(0.0, -2.25, 0.0),
+        );
+
+        let contact = contact(&capsule, &hull).unwrap();
+
+        assert_approx_eq!(contact.normal, Vector3::DOWN, 1e-2);
+        assert_approx_eq!(contact.depth, 0.25, 1e-2);
+    }
+
+    #[test]
+    fn concentric_shapes_still_produce_a_contact() {
+        let a = place(Collider::aabb(Vector3::ONE), Vector3::ZERO);
+        let b = place(Collider::sphere(0.5), Vector3::ZERO);
+
+        let contact = contact(&a, &b).unwrap();
+
+        assert_approx_eq!(contact.depth, 1.5, 1e-2);
+    }
+}
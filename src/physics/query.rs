@@ -0,0 +1,321 @@
+// Spatial queries
+//
+// `SpatialQuery` answers "what is over there" for gameplay code: rays, shape
+// overlaps and sweeps. It keeps the colliders and sorted bounds that
+// `detect_collisions` built this tick, so queries search the same sweep and
+// prune structure rather than every collider.
+
+use legion::Entity;
+
+use super::broadphase::SweepAndPrune;
+use super::collider::{Bounds, Collider, WorldShape};
+use super::narrowphase;
+use crate::component::Transform;
+use crate::quaternion::Quaternion;
+use crate::vector::Vector3;
+
+/// Where a ray or sweep first touched a collider.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    pub entity: Entity,
+    /// Point of contact, in world space.
+    pub point: Vector3,
+    /// Surface normal of the collider hit, facing back along the ray.
+    pub normal: Vector3,
+    /// How far along the ray the hit is.
+    pub distance: f32,
+}
+
+/// A collider as placed by the latest `detect_collisions`.
+#[derive(Clone, Debug)]
+pub(crate) struct Placed {
+    pub entity: Entity,
+    pub layer: u32,
+    pub shape: WorldShape,
+}
+
+/// Ray casts and shape queries against the colliders in the world.
+///
+/// Colliders are where `detect_collisions` last found them, before it pushed
+/// hard pairs apart. Each query takes a `mask`, and only sees colliders whose
+/// `layer` shares a bit with it; pass `u32::MAX` to see everything.
+#[derive(Clone, Debug, Default)]
+pub struct SpatialQuery {
+    colliders: Vec<Placed>,
+    broadphase: SweepAndPrune,
+}
+
+impl SpatialQuery {
+    pub(crate) fn new(colliders: Vec<Placed>, broadphase: SweepAndPrune) -> SpatialQuery {
+        SpatialQuery {
+            colliders,
+            broadphase,
+        }
+    }
+
+    /// The nearest collider hit by a ray, within `max_distance` of `origin`.
+    ///
+    /// Colliders the ray starts inside aren't hit.
+    pub fn raycast(
+        &self,
+        origin: Vector3,
+        direction: Vector3,
+        max_distance: f32,
+        mask: u32,
+    ) -> Option<RayHit> {
+        self.raycast_all(origin, direction, max_distance, mask)
+            .into_iter()
+            .next()
+    }
+
+    /// Every collider hit by a ray, nearest first.
+    pub fn raycast_all(
+        &self,
+        origin: Vector3,
+        direction: Vector3,
+        max_distance: f32,
+        mask: u32,
+    ) -> Vec<RayHit> {
+        let point = WorldShape::Sphere {
+            center: origin,
+            radius: 0.0,
+        };
+        self.cast(&point, direction, max_distance, mask)
+    }
+
+    /// Every collider overlapping a sphere.
+    pub fn sphere_overlap(&self, center: Vector3, radius: f32, mask: u32) -> Vec<Entity> {
+        self.overlap(&WorldShape::Sphere { center, radius }, mask)
+    }
+
+    /// Every collider overlapping a box turned by `rotation`.
+    pub fn box_overlap(
+        &self,
+        center: Vector3,
+        half_extents: Vector3,
+        rotation: Quaternion,
+        mask: u32,
+    ) -> Vec<Entity> {
+        let shape = WorldShape::Box {
+            center,
+            axes: [
+                rotation * Vector3::RIGHT,
+                rotation * Vector3::UP,
+                rotation * Vector3::FORWARD,
+            ],
+            half_extents,
+        };
+        self.overlap(&shape, mask)
+    }
+
+    /// The first collider `collider` would touch moving from `start` along
+    /// `direction` for up to `max_distance`.
+    ///
+    /// Colliders it overlaps at `start` aren't hit, so an entity can sweep its
+    /// own collider without hitting itself.
+    pub fn sweep(
+        &self,
+        collider: &Collider,
+        start: &Transform,
+        direction: Vector3,
+        max_distance: f32,
+        mask: u32,
+    ) -> Option<RayHit> {
+        self.cast(&collider.to_world(start), direction, max_distance, mask)
+            .into_iter()
+            .next()
+    }
+
+    /// Hits of `caster` moving along `direction`, nearest first.
+    fn cast(
+        &self,
+        caster: &WorldShape,
+        direction: Vector3,
+        max_distance: f32,
+        mask: u32,
+    ) -> Vec<RayHit> {
+        let direction = direction.normalized();
+        if direction == Vector3::ZERO || max_distance < 0.0 {
+            return Vec::new();
+        }
+        let start = caster.bounds();
+        // Avoid 0 * infinity for an unlimited distance
+        let reach = |along: f32| {
+            if along == 0.0 {
+                0.0
+            } else {
+                along * max_distance
+            }
+        };
+        let moved = Vector3::new(reach(direction.x), reach(direction.y), reach(direction.z));
+        let swept = Bounds {
+            min: start.min + Vector3::min(moved, Vector3::ZERO),
+            max: start.max + Vector3::max(moved, Vector3::ZERO),
+        };
+
+        let mut hits: Vec<RayHit> = self
+            .candidates(&swept, mask)
+            .filter_map(|placed| {
+                let (distance, normal) =
+                    narrowphase::cast(caster, &placed.shape, direction, max_distance)?;
+                Some(RayHit {
+                    entity: placed.entity,
+                    point: caster.support(-normal) + direction * distance,
+                    normal,
+                    distance,
+                })
+            })
+            .collect();
+        // Stable, so equally distant hits keep the broadphase order
+        hits.sort_by(|a, b| {
+            a.distance
+                .partial_cmp(&b.distance)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        hits
+    }
+
+    fn overlap(&self, shape: &WorldShape, mask: u32) -> Vec<Entity> {
+        self.candidates(&shape.bounds(), mask)
+            .filter(|placed| narrowphase::intersects(shape, &placed.shape))
+            .map(|placed| placed.entity)
+            .collect()
+    }
+
+    fn candidates<'a>(&'a self, bounds: &'a Bounds, mask: u32) -> impl Iterator<Item = &'a Placed> {
+        self.broadphase
+            .overlapping(bounds)
+            .map(move |i| &self.colliders[i])
+            .filter(move |placed| placed.layer & mask != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+    use legion::World;
+
+    fn query(world: &mut World, colliders: Vec<(Collider, Transform)>) -> SpatialQuery {
+        let placed: Vec<Placed> = colliders
+            .into_iter()
+            .map(|(collider, transform)| Placed {
+                entity: world.push(()),
+                layer: collider.layer,
+                shape: collider.to_world(&transform),
+            })
+            .collect();
+        let bounds = placed.iter().map(|p| p.shape.bounds()).collect();
+        SpatialQuery::new(placed, SweepAndPrune::new(bounds))
+    }
+
+    fn at(x: f32, y: f32, z: f32) -> Transform {
+        Transform::from_position(Vector3::new(x, y, z))
+    }
+
+    #[test]
+    fn raycast_hits_the_nearest_collider() {
+        let mut world = World::default();
+        let query = query(
+            &mut world,
+            vec![
+                (Collider::sphere(1.0), at(10.0, 0.0, 0.0)),
+                (Collider::aabb(Vector3::ONE), at(5.0, 0.0, 0.0)),
+                (Collider::sphere(1.0), at(0.0, 5.0, 0.0)),
+            ],
+        );
+
+        let hit = query
+            .raycast(Vector3::ZERO, Vector3::RIGHT, f32::INFINITY, u32::MAX)
+            .unwrap();
+
+        assert_eq!(hit.entity, query.colliders[1].entity);
+        assert_approx_eq!(hit.distance, 4.0, 1e-3);
+        assert_approx_eq!(hit.point, Vector3::new(4.0, 0.0, 0.0), 1e-3);
+        assert_approx_eq!(hit.normal, Vector3::LEFT, 1e-3);
+
+        let all = query.raycast_all(Vector3::ZERO, Vector3::RIGHT, 100.0, u32::MAX);
+        assert_eq!(all.len(), 2);
+        assert_approx_eq!(all[1].distance, 9.0, 1e-3);
+        assert!(query
+            .raycast(Vector3::ZERO, Vector3::RIGHT, 3.0, u32::MAX)
+            .is_none());
+    }
+
+    #[test]
+    fn layer_masks_filter_queries() {
+        let mut world = World::default();
+        let query = query(
+            &mut world,
+            vec![
+                (
+                    Collider::sphere(1.0).with_layers(0b01, u32::MAX),
+                    at(3.0, 0.0, 0.0),
+                ),
+                (
+                    Collider::sphere(1.0).with_layers(0b10, u32::MAX),
+                    at(6.0, 0.0, 0.0),
+                ),
+            ],
+        );
+
+        let hit = query
+            .raycast(Vector3::ZERO, Vector3::RIGHT, 100.0, 0b10)
+            .unwrap();
+        assert_eq!(hit.entity, query.colliders[1].entity);
+        assert_approx_eq!(hit.distance, 5.0, 1e-3);
+
+        let near = query.sphere_overlap(Vector3::new(4.5, 0.0, 0.0), 1.0, u32::MAX);
+        assert_eq!(near.len(), 2);
+        assert!(query
+            .sphere_overlap(Vector3::new(4.5, 0.0, 0.0), 1.0, 0b100)
+            .is_empty());
+    }
+
+    #[test]
+    fn box_overlap_respects_rotation() {
+        let mut world = World::default();
+        let query = query(&mut world, vec![(Collider::sphere(0.5), at(0.0, 0.0, 2.5))]);
+        let long = Vector3::new(3.0, 0.5, 0.5);
+
+        assert!(query
+            .box_overlap(Vector3::ZERO, long, Quaternion::identity(), u32::MAX)
+            .is_empty());
+        let turned = Quaternion::angle_axis(90.0, Vector3::UP);
+        assert_eq!(
+            query.box_overlap(Vector3::ZERO, long, turned, u32::MAX),
+            vec![query.colliders[0].entity]
+        );
+    }
+
+    #[test]
+    fn sweep_stops_where_the_shape_first_touches() {
+        let mut world = World::default();
+        let query = query(
+            &mut world,
+            vec![
+                (Collider::sphere(0.5), at(0.0, 0.0, 0.0)),
+                (
+                    Collider::aabb(Vector3::new(5.0, 0.5, 5.0)),
+                    at(0.0, -3.0, 0.0),
+                ),
+            ],
+        );
+
+        // Starts inside its own collider, which is skipped
+        let hit = query
+            .sweep(
+                &Collider::sphere(0.5),
+                &at(0.0, 0.0, 0.0),
+                Vector3::DOWN,
+                10.0,
+                u32::MAX,
+            )
+            .unwrap();
+
+        assert_eq!(hit.entity, query.colliders[1].entity);
+        assert_approx_eq!(hit.distance, 2.0, 1e-3);
+        assert_approx_eq!(hit.normal, Vector3::UP, 1e-3);
+        assert_approx_eq!(hit.point, Vector3::new(0.0, -2.5, 0.0), 1e-3);
+    }
+}
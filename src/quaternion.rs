@@ -1,10 +1,58 @@
 // Quaternions
 // Implements: https://docs.unity3d.com/ScriptReference/Quaternion.html
 
+use super::angle::{Degrees, Radians};
+use super::matrix::Matrix3x3;
+#[cfg(feature = "simd")]
+use super::simd;
 use super::vector::Vector3;
+use serde::{Deserialize, Serialize};
 use std::f32::consts::PI;
 use std::fmt;
-use std::ops::Index;
+use std::ops::{Index, Mul};
+
+/// Dot products closer to 1 than this are treated as the same rotation.
+const DOT_EPSILON: f32 = 1e-6;
+
+/// How close the middle Euler angle's sine must get to 1 to count as gimbal lock.
+const GIMBAL_EPSILON: f32 = 1e-5;
+
+/// Order in which the rotations about each axis are applied by Euler angles.
+///
+/// `ZXY`, the default, rotates about z first, then x, then y, which is the
+/// order Unity uses. Each rotation is about the fixed world axes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RotationOrder {
+    XYZ,
+    XZY,
+    YXZ,
+    YZX,
+    #[default]
+    ZXY,
+    ZYX,
+}
+
+impl RotationOrder {
+    /// Axes (0 = x, 1 = y, 2 = z) in the order they're applied.
+    fn axes(self) -> [usize; 3] {
+        match self {
+            RotationOrder::XYZ => [0, 1, 2],
+            RotationOrder::XZY => [0, 2, 1],
+            RotationOrder::YXZ => [1, 0, 2],
+            RotationOrder::YZX => [1, 2, 0],
+            RotationOrder::ZXY => [2, 0, 1],
+            RotationOrder::ZYX => [2, 1, 0],
+        }
+    }
+
+    /// Whether the axes are a cyclic (even) permutation of x, y, z.
+    fn is_cyclic(self) -> bool {
+        match self {
+            RotationOrder::XYZ | RotationOrder::YZX | RotationOrder::ZXY => true,
+            RotationOrder::XZY | RotationOrder::YXZ | RotationOrder::ZYX => false,
+        }
+    }
+}
 
 /// Quaternions are used to represent rotations.
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -30,10 +78,15 @@ impl Quaternion {
 
 // "Properties"
 impl Quaternion {
-    /// Returns the euler angle representation of the rotation
+    /// Returns the euler angle representation of the rotation in degrees,
+    /// each in the range [0, 360), using the default rotation order
     pub fn euler_angles(&self) -> Vector3 {
-        let (x, y, z) = Quaternion::quaternion_to_euler(self.w, self.x, self.y, self.z);
-        Vector3 { x, y, z }
+        let (x, y, z) = self.to_euler(RotationOrder::default());
+        Vector3 {
+            x: wrap_degrees(Degrees::from(x).0),
+            y: wrap_degrees(Degrees::from(y).0),
+            z: wrap_degrees(Degrees::from(z).0),
+        }
     }
 
     /// Returns this quaternion with a magnitude of 1
@@ -46,19 +99,30 @@ impl Quaternion {
         let z = self.z / magnitude;
         Quaternion { w, x, y, z }
     }
+
+    /// Returns the inverse of this rotation
+    pub fn inverse(&self) -> Quaternion {
+        let norm = Quaternion::dot(*self, *self);
+        let c = self.conjugate();
+        Quaternion::new(c.w / norm, c.x / norm, c.y / norm, c.z / norm)
+    }
+
+    /// Returns the conjugate, which for a unit quaternion is also its inverse
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
 }
 
 // Constructors
 impl Quaternion {
-    /// Constructs new Quaternion with given x,y,z,w components
+    /// Constructs new Quaternion with given w, x, y, z components
     pub fn new(w: f32, x: f32, y: f32, z: f32) -> Quaternion {
         Quaternion { w, x, y, z }
     }
-    /// Constructs new Quaternion with given Vector3
+    /// Constructs new Quaternion from euler angles in degrees, using the
+    /// default rotation order
     pub fn new_from_euler(vector: Vector3) -> Quaternion {
-        // REFACTOR: This should be an unpack/destructure.
-        let (x, y, z, w) = Quaternion::euler_to_quaternion(vector.x, vector.y, vector.z);
-        Quaternion { w, x, y, z }
+        Quaternion::euler(vector.x, vector.y, vector.z)
     }
 }
 
@@ -72,56 +136,236 @@ impl Quaternion {
         self.z = z;
     }
 
-    /// Sets the euler angle representation of the rotation.
+    /// Sets the euler angle representation of the rotation in degrees.
     pub fn set_from_euler(&mut self, vector: Vector3) {
-        let c = Quaternion::euler_to_quaternion(vector.x, vector.y, vector.z);
-        self.x = c.0;
-        self.y = c.1;
-        self.z = c.2;
-        self.w = c.3;
+        *self = Quaternion::new_from_euler(vector);
     }
 
-    /// Creates a rotation which rotates from 'from' to 'to'
-    pub fn set_from_to_rotation(from: Vector3, to: Vector3) {
-        // TODO: Work out how to do this...
+    /// Sets this to a rotation which rotates from 'from' to 'to'
+    pub fn set_from_to_rotation(&mut self, from: Vector3, to: Vector3) {
+        *self = Quaternion::from_to_rotation(from, to);
     }
 
-    /// Creates a rotation with the specified forward directions using default "up"
-    pub fn set_look_rotation(view: Vector3) {
-        // TODO: Work out how to do this...
-        let up = Vector3::up();
+    /// Sets this to a rotation with the specified forward direction using default "up"
+    pub fn set_look_rotation(&mut self, view: Vector3) {
+        *self = Quaternion::look_rotation(view);
     }
 
-    /// Creates a rotation with the specified forward and upwards directions
-    pub fn set_look_rotation_with_up(view: Vector3, up: Vector3) {
-        // TODO: Work out how to do this...
+    /// Sets this to a rotation with the specified forward and upwards directions
+    pub fn set_look_rotation_with_up(&mut self, view: Vector3, up: Vector3) {
+        *self = Quaternion::look_rotation_with_up(view, up);
     }
 
     /// Converts a rotation to angle-axis representation (angles in degrees)
-    pub fn to_angle_axis(&self, angle: &f32, axis: &Vector3) {}
+    pub fn to_angle_axis(&self) -> (f32, Vector3) {
+        let q = if self.w < 0.0 {
+            Quaternion::new(-self.w, -self.x, -self.y, -self.z).normalized()
+        } else {
+            self.normalized()
+        };
+        let angle = 2.0 * q.w.min(1.0).acos();
+        let s = (1.0 - q.w * q.w).max(0.0).sqrt();
+        if s < DOT_EPSILON {
+            return (0.0, Vector3::right());
+        }
+        (angle.to_degrees(), Vector3::new(q.x / s, q.y / s, q.z / s))
+    }
+
+    /// Returns the euler angles of the rotation for the given rotation order.
+    ///
+    /// The middle axis' angle is in [-90, 90] degrees and the others in
+    /// (-180, 180]. At gimbal lock the first and last axes line up and the
+    /// whole of their rotation is put on the first axis.
+    pub fn to_euler(&self, order: RotationOrder) -> (Radians, Radians, Radians) {
+        let [i, j, k] = order.axes();
+        let sign = if order.is_cyclic() { 1.0 } else { -1.0 };
+        let m = Matrix3x3::from_rotation(self.normalized());
+
+        let mut angles = [0.0; 3];
+        let sin_middle = (-sign * m[(k, i)]).clamp(-1.0, 1.0);
+        angles[j] = sin_middle.asin();
+        if sin_middle.abs() < 1.0 - GIMBAL_EPSILON {
+            angles[i] = (sign * m[(k, j)]).atan2(m[(k, k)]);
+            angles[k] = (sign * m[(j, i)]).atan2(m[(i, i)]);
+        } else {
+            // Undo the middle rotation, leaving a rotation about the first axis only
+            let first = Quaternion::axis_rotation(j, -angles[j]) * self.normalized();
+            let axis_component = [first.x, first.y, first.z][i];
+            angles[i] = wrap_radians(2.0 * axis_component.atan2(first.w));
+        }
+        (Radians(angles[0]), Radians(angles[1]), Radians(angles[2]))
+    }
 }
 
 // Static Methods
 impl Quaternion {
     /// Returns the angle in degrees between two rotations a and b
     pub fn angle(a: Quaternion, b: Quaternion) -> f32 {
-        let f = Quaternion::dot(a, b);
-        (((f).abs().min(1.0)) * 2.0 * 57.29578).acos()
+        let dot = Quaternion::dot(a, b).abs().min(1.0);
+        if dot > 1.0 - DOT_EPSILON {
+            0.0
+        } else {
+            (dot.acos() * 2.0).to_degrees()
+        }
+    }
+
+    /// Returns a rotation of z degrees around the z axis, x degrees around the
+    /// x axis and y degrees around the y axis, applied in that order
+    pub fn euler(x: f32, y: f32, z: f32) -> Quaternion {
+        Quaternion::from_euler(Degrees(x), Degrees(y), Degrees(z), RotationOrder::default())
+    }
+
+    /// Returns a rotation from euler angles applied in the given order
+    ///
+    /// ```ignore
+    /// let q = Quaternion::from_euler(Degrees(90.0), Degrees(0.0), Degrees(45.0), RotationOrder::XYZ);
+    /// ```
+    pub fn from_euler<A: Into<Radians>>(x: A, y: A, z: A, order: RotationOrder) -> Quaternion {
+        let angles = [x.into().0, y.into().0, z.into().0];
+        let [a, b, c] = order.axes();
+        Quaternion::axis_rotation(c, angles[c])
+            * Quaternion::axis_rotation(b, angles[b])
+            * Quaternion::axis_rotation(a, angles[a])
     }
 
     /// Returns a rotation which rotates `angle` degrees around `axis`.
     pub fn angle_axis(angle: f32, axis: Vector3) -> Quaternion {
+        let axis = axis.normalized();
+        let half = angle.to_radians() * 0.5;
+        let s = half.sin();
         Quaternion {
-            w: angle.cos(),
-            x: axis.x * angle,
-            y: axis.y * angle,
-            z: axis.z * angle,
+            w: half.cos(),
+            x: axis.x * s,
+            y: axis.y * s,
+            z: axis.z * s,
+        }
+    }
+
+    /// Creates a rotation which rotates from 'from' to 'to'
+    pub fn from_to_rotation(from: Vector3, to: Vector3) -> Quaternion {
+        let from = from.normalized();
+        let to = to.normalized();
+        let dot = Vector3::dot(from, to);
+        if dot >= 1.0 - DOT_EPSILON {
+            return Quaternion::identity();
+        }
+        if dot <= -1.0 + DOT_EPSILON {
+            // Opposite directions, turn half way around any perpendicular axis
+            let mut axis = Vector3::cross(Vector3::right(), from);
+            if axis.sqr_magnitude() < DOT_EPSILON {
+                axis = Vector3::cross(Vector3::up(), from);
+            }
+            return Quaternion::angle_axis(180.0, axis);
+        }
+        let axis = Vector3::cross(from, to);
+        Quaternion::new(1.0 + dot, axis.x, axis.y, axis.z).normalized()
+    }
+
+    /// Creates a rotation with the specified forward direction using default "up"
+    pub fn look_rotation(forward: Vector3) -> Quaternion {
+        Quaternion::look_rotation_with_up(forward, Vector3::up())
+    }
+
+    /// Creates a rotation with the specified forward and upwards directions
+    ///
+    /// The result's z axis points along `forward` and its y axis is as close
+    /// to `up` as possible. If the two are parallel the rotation simply turns
+    /// +z onto `forward`.
+    pub fn look_rotation_with_up(forward: Vector3, up: Vector3) -> Quaternion {
+        if forward.sqr_magnitude() < DOT_EPSILON {
+            return Quaternion::identity();
+        }
+        let forward = forward.normalized();
+        let right = Vector3::cross(up, forward);
+        if right.sqr_magnitude() < DOT_EPSILON {
+            return Quaternion::from_to_rotation(Vector3::forward(), forward);
         }
+        let right = right.normalized();
+        let up = Vector3::cross(forward, right);
+        Matrix3x3::from_columns(right, up, forward).to_rotation()
+    }
+
+    /// Spherically interpolates between a and b by t, with t clamped to [0, 1]
+    pub fn slerp(a: Quaternion, b: Quaternion, t: f32) -> Quaternion {
+        Quaternion::slerp_unclamped(a, b, t.clamp(0.0, 1.0))
+    }
+
+    /// Spherically interpolates between a and b by t, taking the shortest path
+    pub fn slerp_unclamped(a: Quaternion, b: Quaternion, t: f32) -> Quaternion {
+        let mut dot = Quaternion::dot(a, b);
+        let b = if dot < 0.0 {
+            dot = -dot;
+            Quaternion::new(-b.w, -b.x, -b.y, -b.z)
+        } else {
+            b
+        };
+        if dot > 1.0 - DOT_EPSILON {
+            return Quaternion::nlerp_unclamped(a, b, t);
+        }
+        let theta = dot.acos();
+        let sin_theta = theta.sin();
+        let wa = ((1.0 - t) * theta).sin() / sin_theta;
+        let wb = (t * theta).sin() / sin_theta;
+        Quaternion::new(
+            a.w * wa + b.w * wb,
+            a.x * wa + b.x * wb,
+            a.y * wa + b.y * wb,
+            a.z * wa + b.z * wb,
+        )
+        .normalized()
+    }
+
+    /// Linearly interpolates between a and b by t and normalizes the result,
+    /// with t clamped to [0, 1]
+    ///
+    /// Faster than slerp but the angular speed isn't constant.
+    pub fn nlerp(a: Quaternion, b: Quaternion, t: f32) -> Quaternion {
+        Quaternion::nlerp_unclamped(a, b, t.clamp(0.0, 1.0))
+    }
+
+    /// Linearly interpolates between a and b by t and normalizes the result
+    pub fn nlerp_unclamped(a: Quaternion, b: Quaternion, t: f32) -> Quaternion {
+        let sign = if Quaternion::dot(a, b) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        Quaternion::new(
+            a.w + (b.w * sign - a.w) * t,
+            a.x + (b.x * sign - a.x) * t,
+            a.y + (b.y * sign - a.y) * t,
+            a.z + (b.z * sign - a.z) * t,
+        )
+        .normalized()
+    }
+
+    /// Rotates from towards to by at most max_degrees_delta degrees
+    ///
+    /// A negative delta rotates away from to, but never past the opposite rotation.
+    pub fn rotate_towards(from: Quaternion, to: Quaternion, max_degrees_delta: f32) -> Quaternion {
+        let angle = Quaternion::angle(from, to);
+        if angle == 0.0 {
+            return to;
+        }
+        let t = max_degrees_delta / angle;
+        if t >= 1.0 {
+            return to;
+        }
+        // Backing off further than 180 degrees from to would come back round
+        let t = t.max(-(180.0 - angle) / angle);
+        Quaternion::slerp_unclamped(from, to, t)
     }
 
     /// Returns the dot product between two rotations
     pub fn dot(a: Quaternion, b: Quaternion) -> f32 {
-        a.x * b.x + a.y * b.y + a.z * b.z + a.w * b.w
+        #[cfg(feature = "simd")]
+        {
+            simd::dot(a, b)
+        }
+        #[cfg(not(feature = "simd"))]
+        {
+            a.x * b.x + a.y * b.y + a.z * b.z + a.w * b.w
+        }
     }
 }
 
@@ -129,76 +373,102 @@ impl Quaternion {
 impl Index<i8> for Quaternion {
     type Output = f32;
 
-    /// Access the x, y, z, w components using [0], [1], [2], [3] respectively
+    /// Access the w, x, y, z components using [0], [1], [2], [3] respectively,
+    /// matching the argument order of `new`
     fn index(&self, i: i8) -> &Self::Output {
         match i {
-            0 => &self.x,
-            1 => &self.y,
-            2 => &self.z,
-            3 => &self.w,
+            0 => &self.w,
+            1 => &self.x,
+            2 => &self.y,
+            3 => &self.z,
             _ => panic!("Index out of range, got {}.", i),
         }
     }
 }
 
+impl Mul for Quaternion {
+    type Output = Self;
+
+    /// Combine two rotations, applying `other` first and then `self`.
+    fn mul(self, other: Self) -> Self {
+        #[cfg(feature = "simd")]
+        {
+            simd::Columns::from_rotation(self).rotate(other)
+        }
+        #[cfg(not(feature = "simd"))]
+        {
+            Quaternion {
+                w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+                x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+                y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+                z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+            }
+        }
+    }
+}
+
+impl Mul<Vector3> for Quaternion {
+    type Output = Vector3;
+
+    /// Rotate a vector by this rotation.
+    fn mul(self, v: Vector3) -> Vector3 {
+        let u = Vector3::new(self.x, self.y, self.z);
+        let t = Vector3::cross(u, v) * 2.0;
+        v + t * self.w + Vector3::cross(u, t)
+    }
+}
+
 impl fmt::Display for Quaternion {
     /// Returns a formatted string of the Quaternion
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            format!("{{{}, {}, {}, {}}}", self.x, self.y, self.z, self.w)
-        )
+        write!(f, "{{{}, {}, {}, {}}}", self.x, self.y, self.z, self.w)
     }
 }
 
 // Private Methods
 impl Quaternion {
-    fn euler_to_quaternion(x: f32, y: f32, z: f32) -> (f32, f32, f32, f32) {
-        // Abbreviations for the various angular functions
-        let cy = (z * 0.5).cos();
-        let sy = (z * 0.5).sin();
-        let cp = (y * 0.5).cos();
-        let sp = (y * 0.5).sin();
-        let cr = (x * 0.5).cos();
-        let sr = (x * 0.5).sin();
-
-        let _w = cr * cp * cy + sr * sp * sy;
-        let _x = sr * cp * cy - cr * sp * sy;
-        let _y = cr * sp * cy + sr * cp * sy;
-        let _z = cr * cp * sy - sr * sp * cy;
-
-        (_x, _y, _z, _w)
-    }
-
-    fn quaternion_to_euler(w: f32, x: f32, y: f32, z: f32) -> (f32, f32, f32) {
-        // roll (x-axis rotation)
-        let sinr_cosp = 2.0 * (w * x + y * z);
-        let cosr_cosp = 1.0 - 2.0 * (x * x + y * y);
-        let ex = sinr_cosp.atan2(cosr_cosp);
-
-        // pitch (y-axis rotation)
-        let ey: f32;
-        let sinp = 2.0 * (w * y - z * x);
-        if sinp.abs() >= 1.0 {
-            ey = (PI / 2.0).copysign(sinp); // use 90 degrees if out of range
-        } else {
-            ey = sinp.asin();
+    /// Rotation of `radians` around the x (0), y (1) or z (2) axis.
+    fn axis_rotation(axis: usize, radians: f32) -> Quaternion {
+        let half = radians * 0.5;
+        let mut q = Quaternion::new(half.cos(), 0.0, 0.0, 0.0);
+        match axis {
+            0 => q.x = half.sin(),
+            1 => q.y = half.sin(),
+            _ => q.z = half.sin(),
         }
+        q
+    }
+}
 
-        // yaw (z-axis rotation)
-        let siny_cosp = 2.0 * (w * z + x * y);
-        let cosy_cosp = 1.0 - 2.0 * (y * y + z * z);
-        let ez = siny_cosp.atan2(cosy_cosp);
+/// Wrap an angle in degrees into [0, 360).
+fn wrap_degrees(angle: f32) -> f32 {
+    let wrapped = angle.rem_euclid(360.0);
+    if wrapped >= 360.0 {
+        0.0
+    } else {
+        wrapped
+    }
+}
 
-        (ex, ey, ez)
+/// Wrap an angle in radians into (-PI, PI].
+fn wrap_radians(angle: f32) -> f32 {
+    if angle > PI {
+        angle - 2.0 * PI
+    } else if angle <= -PI {
+        angle + 2.0 * PI
+    } else {
+        angle
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::f32::consts::FRAC_PI_2;
+    use crate::assert_approx_eq;
+
+    /// Tolerance for results in degrees, which are ~57x larger than the radians they come from.
+    const DEGREES_EPSILON: f32 = 1e-3;
+    use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2};
 
     #[test]
     fn quaternion_identity_is_zeroed_by_default() {
@@ -210,19 +480,113 @@ mod tests {
         assert_eq!(q.w, 1.0);
     }
 
+    fn assert_same_rotation(a: Quaternion, b: Quaternion) {
+        assert_approx_eq!(Quaternion::dot(a, b).abs(), 1.0);
+    }
+
+    const ORDERS: [RotationOrder; 6] = [
+        RotationOrder::XYZ,
+        RotationOrder::XZY,
+        RotationOrder::YXZ,
+        RotationOrder::YZX,
+        RotationOrder::ZXY,
+        RotationOrder::ZYX,
+    ];
+
     #[test]
     fn quaternion_can_derive_euler_angles() {
-        let q = Quaternion {
-            x: 90.0,
-            y: 90.0,
-            z: 90.0,
-            w: 1.0,
-        };
+        let q = Quaternion::euler(30.0, 45.0, 60.0);
         let eq = q.euler_angles();
 
-        assert_eq!(eq.x, 2.6734982);
-        assert_eq!(eq.y, -FRAC_PI_2);
-        assert_eq!(eq.z, 2.6734982);
+        assert_approx_eq!(eq, Vector3::new(30.0, 45.0, 60.0), DEGREES_EPSILON);
+    }
+
+    #[test]
+    fn euler_angles_are_wrapped_to_positive_degrees() {
+        let eq = Quaternion::euler(-30.0, -90.0, 0.0).euler_angles();
+
+        assert_approx_eq!(eq, Vector3::new(330.0, 270.0, 0.0), DEGREES_EPSILON);
+    }
+
+    #[test]
+    fn euler_applies_z_then_x_then_y() {
+        let q = Quaternion::euler(10.0, 20.0, 30.0);
+        let expected = Quaternion::angle_axis(20.0, Vector3::up())
+            * Quaternion::angle_axis(10.0, Vector3::right())
+            * Quaternion::angle_axis(30.0, Vector3::forward());
+
+        assert_same_rotation(q, expected);
+    }
+
+    #[test]
+    fn from_euler_applies_axes_in_the_given_order() {
+        let x = Quaternion::angle_axis(10.0, Vector3::right());
+        let y = Quaternion::angle_axis(20.0, Vector3::up());
+        let z = Quaternion::angle_axis(30.0, Vector3::forward());
+        let expected = [
+            (RotationOrder::XYZ, z * y * x),
+            (RotationOrder::XZY, y * z * x),
+            (RotationOrder::YXZ, z * x * y),
+            (RotationOrder::YZX, x * z * y),
+            (RotationOrder::ZXY, y * x * z),
+            (RotationOrder::ZYX, x * y * z),
+        ];
+
+        for (order, rotation) in expected.iter() {
+            let q = Quaternion::from_euler(Degrees(10.0), Degrees(20.0), Degrees(30.0), *order);
+            assert_same_rotation(q, *rotation);
+        }
+    }
+
+    #[test]
+    fn euler_angles_round_trip_for_every_order() {
+        let angles = [
+            (10.0, 20.0, 30.0),
+            (-170.0, 80.0, 45.0),
+            (135.0, -60.0, -120.0),
+            (0.0, 0.0, 179.0),
+        ];
+
+        for order in ORDERS.iter() {
+            for &(x, y, z) in angles.iter() {
+                let q = Quaternion::from_euler(Degrees(x), Degrees(y), Degrees(z), *order);
+                let (rx, ry, rz) = q.to_euler(*order);
+                let back = Vector3::new(
+                    Degrees::from(rx).0,
+                    Degrees::from(ry).0,
+                    Degrees::from(rz).0,
+                );
+
+                // The middle axis is limited to [-90, 90], so compare the
+                // angles only where that holds for the input
+                let middle = order.axes()[1];
+                let input = [x, y, z];
+                if input[middle].abs() <= 90.0 {
+                    assert_approx_eq!(back, Vector3::new(x, y, z), DEGREES_EPSILON);
+                }
+                assert_same_rotation(Quaternion::from_euler(rx, ry, rz, *order), q);
+            }
+        }
+    }
+
+    #[test]
+    fn euler_angles_round_trip_at_gimbal_lock() {
+        for order in ORDERS.iter() {
+            for &middle in [90.0, -90.0].iter() {
+                let mut input = [25.0, 40.0, 70.0];
+                input[order.axes()[1]] = middle;
+                let q = Quaternion::from_euler(
+                    Degrees(input[0]),
+                    Degrees(input[1]),
+                    Degrees(input[2]),
+                    *order,
+                );
+
+                let (rx, ry, rz) = q.to_euler(*order);
+
+                assert_same_rotation(Quaternion::from_euler(rx, ry, rz, *order), q);
+            }
+        }
     }
 
     #[test]
@@ -235,10 +599,11 @@ mod tests {
         };
         let eq = q.normalized();
 
-        assert_eq!(eq.x, 0.57733834);
-        assert_eq!(eq.y, 0.57733834);
-        assert_eq!(eq.z, 0.57733834);
-        assert_eq!(eq.w, 0.0064148707);
+        assert_approx_eq!(
+            eq,
+            Quaternion::new(0.0064148707, 0.57733834, 0.57733834, 0.57733834)
+        );
+        assert_approx_eq!(Quaternion::dot(eq, eq), 1.0);
     }
 
     #[test]
@@ -252,20 +617,19 @@ mod tests {
     }
 
     #[test]
-    #[ignore]
     fn quaternion_can_be_constructed_from_euler_vector3() {
-        // TODO: This is broken..
         let v = Vector3 {
-            x: 2.6734982,
-            y: -FRAC_PI_2,
-            z: 2.6734982,
+            x: 30.0,
+            y: 45.0,
+            z: 60.0,
         };
         let q = Quaternion::new_from_euler(v);
 
-        assert_eq!(q.x, 90.0);
-        assert_eq!(q.y, 90.0);
-        assert_eq!(q.z, 90.0);
-        assert_eq!(q.w, 1.0);
+        assert_approx_eq!(q.euler_angles(), v, DEGREES_EPSILON);
+        assert_approx_eq!(
+            Quaternion::new_from_euler(Vector3::new(90.0, 0.0, 0.0)) * Vector3::forward(),
+            Vector3::down(),
+        );
     }
 
     #[test]
@@ -283,33 +647,29 @@ mod tests {
     fn quaternion_can_be_set_to_a_new_value_from_euler() {
         let mut q = Quaternion::new(1.0, 90.0, 90.0, 90.0);
         let v = Vector3 {
-            x: 2.6734982,
-            y: -FRAC_PI_2,
-            z: 2.6734982,
+            x: 0.0,
+            y: 90.0,
+            z: 0.0,
         };
         q.set_from_euler(v);
 
-        assert_eq!(q.x, 0.3190371);
-        assert_eq!(q.y, 0.6310431);
-        assert_eq!(q.z, 0.3190371);
-        assert_eq!(q.w, -0.6310431);
+        assert_approx_eq!(q.x, 0.0);
+        assert_approx_eq!(q.y, FRAC_1_SQRT_2);
+        assert_approx_eq!(q.z, 0.0);
+        assert_approx_eq!(q.w, FRAC_1_SQRT_2);
     }
 
     #[test]
-    #[ignore]
     fn can_calculate_the_angle_between_two_quaternions() {
-        // TODO: This doesn't work, qv being returned as NaN
-        let q0 = Quaternion::new(1.0, 180.0, 180.0, 180.0);
-        let q1 = Quaternion::new(0.5, 90.0, 90.0, 90.0);
-
-        let qv = Quaternion::angle(q0, q1);
+        let q0 = Quaternion::angle_axis(30.0, Vector3::up());
+        let q1 = Quaternion::angle_axis(120.0, Vector3::up());
 
-        assert_eq!(qv, 90.0);
+        assert_approx_eq!(Quaternion::angle(q0, q1), 90.0, DEGREES_EPSILON);
+        assert_eq!(Quaternion::angle(q0, q0), 0.0);
     }
 
     #[test]
     fn can_calculate_the_rotation_of_a_angle_about_an_axis() {
-        // TODO: This doesn't work, qv being returned as NaN
         let v = Vector3 {
             x: 2.6734982,
             y: -FRAC_PI_2,
@@ -317,34 +677,169 @@ mod tests {
         };
 
         let q = Quaternion::angle_axis(180.0, v);
+        let axis = v.normalized();
 
-        assert_eq!(q.x, 481.22968);
-        assert_eq!(q.y, -282.74335);
-        assert_eq!(q.z, 481.22968);
-        assert_eq!(q.w, -0.5984601);
+        assert_approx_eq!(q.x, axis.x);
+        assert_approx_eq!(q.y, axis.y);
+        assert_approx_eq!(q.z, axis.z);
+        assert_approx_eq!(q.w, 0.0);
+    }
+
+    #[test]
+    fn can_convert_a_rotation_to_angle_axis() {
+        let q = Quaternion::angle_axis(72.0, Vector3::new(0.0, 0.0, -2.0));
+
+        let (angle, axis) = q.to_angle_axis();
+
+        assert_approx_eq!(angle, 72.0, DEGREES_EPSILON);
+        assert_approx_eq!(axis, Vector3::back());
+        assert_eq!(
+            Quaternion::identity().to_angle_axis(),
+            (0.0, Vector3::right())
+        );
+    }
+
+    #[test]
+    fn rotating_a_vector_matches_unity_handedness() {
+        let q = Quaternion::angle_axis(90.0, Vector3::up());
+
+        assert_approx_eq!(q * Vector3::forward(), Vector3::right());
+        assert_approx_eq!(q * Vector3::right(), Vector3::back());
+    }
+
+    #[test]
+    fn multiplying_quaternions_applies_the_right_hand_side_first() {
+        let yaw = Quaternion::angle_axis(90.0, Vector3::up());
+        let pitch = Quaternion::angle_axis(90.0, Vector3::right());
+
+        // Pitch turns forward to down, then yaw leaves down alone
+        assert_approx_eq!((yaw * pitch) * Vector3::forward(), Vector3::down());
+        // Yaw turns forward to right, then pitch leaves right alone
+        assert_approx_eq!((pitch * yaw) * Vector3::forward(), Vector3::right());
+    }
+
+    #[test]
+    fn inverse_undoes_a_rotation() {
+        let q = Quaternion::angle_axis(40.0, Vector3::new(1.0, 2.0, 3.0));
+        let v = Vector3::new(3.0, -1.0, 2.0);
+
+        assert_approx_eq!(q.inverse() * (q * v), v);
+        assert_eq!(q.conjugate(), Quaternion::new(q.w, -q.x, -q.y, -q.z));
+        assert_approx_eq!(Quaternion::dot(q.inverse(), q.conjugate()), 1.0);
+    }
+
+    #[test]
+    fn from_to_rotation_turns_one_direction_onto_another() {
+        let from = Vector3::new(1.0, 2.0, 3.0);
+        let to = Vector3::new(-2.0, 0.5, 1.0);
+
+        let q = Quaternion::from_to_rotation(from, to);
+
+        assert_approx_eq!(q * from.normalized(), to.normalized());
+        let opposite = Quaternion::from_to_rotation(Vector3::right(), Vector3::left());
+        assert_approx_eq!(opposite * Vector3::right(), Vector3::left());
+    }
+
+    #[test]
+    fn look_rotation_points_forward_along_view() {
+        let view = Vector3::new(1.0, 0.0, 1.0);
+        let mut q = Quaternion::identity();
+        q.set_look_rotation(view);
+
+        assert_approx_eq!(q * Vector3::forward(), view.normalized());
+        assert_approx_eq!(q * Vector3::up(), Vector3::up());
+        assert_eq!(
+            Quaternion::look_rotation(Vector3::forward()),
+            Quaternion::identity()
+        );
+
+        let down = Quaternion::look_rotation(Vector3::down());
+        assert_approx_eq!(down * Vector3::forward(), Vector3::down());
+    }
+
+    #[test]
+    fn look_rotation_with_up_keeps_the_up_hint() {
+        let q = Quaternion::look_rotation_with_up(Vector3::right(), Vector3::back());
+
+        assert_approx_eq!(q * Vector3::forward(), Vector3::right());
+        assert_approx_eq!(q * Vector3::up(), Vector3::back());
+    }
+
+    #[test]
+    fn slerp_moves_at_constant_angular_speed() {
+        let a = Quaternion::identity();
+        let b = Quaternion::angle_axis(90.0, Vector3::up());
+
+        assert_approx_eq!(
+            Quaternion::angle(a, Quaternion::slerp(a, b, 0.25)),
+            22.5,
+            DEGREES_EPSILON
+        );
+        assert_approx_eq!(Quaternion::dot(Quaternion::slerp(a, b, 2.0), b), 1.0);
+        assert_approx_eq!(
+            Quaternion::angle(a, Quaternion::nlerp(a, b, 0.5)),
+            45.0,
+            DEGREES_EPSILON
+        );
+    }
+
+    #[test]
+    fn slerp_takes_the_shortest_path() {
+        let a = Quaternion::angle_axis(10.0, Vector3::up());
+        let b = Quaternion::angle_axis(350.0, Vector3::up());
+
+        let mid = Quaternion::slerp(a, b, 0.5);
+
+        assert_approx_eq!(
+            Quaternion::angle(mid, Quaternion::identity()),
+            0.0,
+            DEGREES_EPSILON
+        );
+    }
+
+    #[test]
+    fn rotate_towards_is_limited_by_max_degrees() {
+        let from = Quaternion::identity();
+        let to = Quaternion::angle_axis(90.0, Vector3::up());
+
+        assert_approx_eq!(
+            Quaternion::angle(from, Quaternion::rotate_towards(from, to, 30.0)),
+            30.0,
+            DEGREES_EPSILON
+        );
+        assert_eq!(Quaternion::rotate_towards(from, to, 180.0), to);
+    }
+
+    #[test]
+    fn rotate_towards_backs_off_no_further_than_the_opposite_rotation() {
+        let from = Quaternion::identity();
+        let to = Quaternion::angle_axis(90.0, Vector3::up());
+
+        let away = Quaternion::rotate_towards(from, to, -30.0);
+        assert_approx_eq!(Quaternion::angle(away, to), 120.0, DEGREES_EPSILON);
+        let opposite = Quaternion::rotate_towards(from, to, -1000.0);
+        assert_approx_eq!(Quaternion::angle(opposite, to), 180.0, DEGREES_EPSILON);
+        assert_approx_eq!(Quaternion::angle(from, opposite), 90.0, DEGREES_EPSILON);
     }
 
     #[test]
     fn can_get_quaternion_attributes_by_index() {
-        // TODO: This doesn't work, qv being returned as NaN
-        let q = Quaternion::new(0.5, 90.0, 90.0, 90.0);
+        let q = Quaternion::new(0.5, 1.0, 2.0, 3.0);
 
-        assert_eq!(q[0], 90.0);
-        assert_eq!(q[1], 90.0);
-        assert_eq!(q[2], 90.0);
-        assert_eq!(q[3], 0.5);
+        assert_eq!(q[0], 0.5);
+        assert_eq!(q[1], 1.0);
+        assert_eq!(q[2], 2.0);
+        assert_eq!(q[3], 3.0);
     }
 
     #[test]
     #[should_panic(expected = "Index out of range, got 4.")]
     fn getting_quaternion_attributes_by_index_panics_if_out_of_range() {
-        // TODO: This doesn't work, qv being returned as NaN
-        let q = Quaternion::new(0.5, 90.0, 90.0, 90.0)[4];
+        let _ = Quaternion::new(0.5, 90.0, 90.0, 90.0)[4];
     }
 
     #[test]
     fn can_get_quaternion_attributes_as_str() {
-        // TODO: This doesn't work, qv being returned as NaN
         let q = Quaternion::new(0.5, 90.0, 90.0, 90.0);
         let qs = format!("{}", q);
 
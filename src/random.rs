@@ -0,0 +1,69 @@
+// Random numbers
+//
+// Gameplay should draw random numbers from the `Random` resource rather than
+// `rand::thread_rng`, so that a run seeded the same way makes the same
+// choices, and a replay reproduces the session it was recorded from.
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+/// Resource generating random numbers from a known seed.
+///
+/// Implements `RngCore`, so the methods of `rand::Rng` work on it:
+/// `random.gen_range(0..6)`.
+#[derive(Clone, Debug)]
+pub struct Random {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// The seed the generator started from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl RngCore for Random {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn the_same_seed_gives_the_same_numbers() {
+        let mut a = Random::new(7);
+        let mut b = Random::new(7);
+        let rolls: Vec<u32> = (0..8).map(|_| a.gen_range(0..100)).collect();
+
+        assert_eq!(
+            rolls,
+            (0..8).map(|_| b.gen_range(0..100)).collect::<Vec<_>>()
+        );
+        assert_ne!(Random::new(8).next_u64(), Random::new(7).next_u64());
+    }
+}
@@ -0,0 +1,369 @@
+// Replay
+//
+// A recording holds everything that can make two runs of the game differ: the
+// seed of `Random`, the timestep settings, and for every frame how long it
+// took and the window events and input applied at its start. Playing it back feeds the same
+// frame times and input through the same game loop, so the simulation runs the
+// same ticks with the same input and ends up in the same state. Each frame also
+// keeps a checksum of the world, so playback can tell where a run diverged,
+// e.g. because a system read the wall clock or `thread_rng`.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use legion::*;
+use serde::{Deserialize, Serialize};
+
+use crate::component::{Transform, Velocity};
+use crate::display_manager::DisplayEvent;
+use crate::input_manager::InputEvent;
+use crate::physics::AngularVelocity;
+
+/// Whether `GameManager` records the session, plays one back, or neither.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayMode {
+    Off,
+    /// Record the session, writing it to the path when the game loop stops.
+    Record(PathBuf),
+    /// Play back the session recorded at the path instead of reading input.
+    Playback(PathBuf),
+}
+
+/// One frame of a recording. Its frame number is its index.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    pub frame_time: Duration,
+    pub display: Vec<DisplayEvent>,
+    pub input: Vec<InputEvent>,
+    /// `checksum` of the world at the end of the frame.
+    pub checksum: u64,
+}
+
+/// A recorded session, stored with bincode.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub seed: u64,
+    pub fixed_step: Duration,
+    pub max_catch_up_steps: u32,
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn new(seed: u64, fixed_step: Duration, max_catch_up_steps: u32) -> Recording {
+        Recording {
+            seed,
+            fixed_step,
+            max_catch_up_steps,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Recording, ReplayError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|source| ReplayError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        bincode::deserialize(&bytes).map_err(ReplayError::Decode)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        let path = path.as_ref();
+        let bytes = bincode::serialize(self).map_err(ReplayError::Encode)?;
+        fs::write(path, bytes).map_err(|source| ReplayError::Write {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+/// Errors raised while loading or saving a `Recording`.
+#[derive(Debug)]
+pub enum ReplayError {
+    /// The recording couldn't be read.
+    Read { path: PathBuf, source: io::Error },
+    /// The recording couldn't be written.
+    Write { path: PathBuf, source: io::Error },
+    /// The file isn't a recording.
+    Decode(bincode::Error),
+    /// The recording couldn't be encoded.
+    Encode(bincode::Error),
+    /// The replay mode was set after the game started.
+    AlreadyStarted,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Read { path, source } => {
+                write!(f, "Couldn't read recording {}: {}", path.display(), source)
+            }
+            ReplayError::Write { path, source } => {
+                write!(f, "Couldn't write recording {}: {}", path.display(), source)
+            }
+            ReplayError::Decode(source) => write!(f, "Invalid recording: {}", source),
+            ReplayError::Encode(source) => write!(f, "Couldn't encode recording: {}", source),
+            ReplayError::AlreadyStarted => {
+                write!(f, "The replay mode must be set before startup()")
+            }
+        }
+    }
+}
+
+impl Error for ReplayError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReplayError::Read { source, .. } | ReplayError::Write { source, .. } => Some(source),
+            ReplayError::Decode(source) | ReplayError::Encode(source) => Some(source),
+            ReplayError::AlreadyStarted => None,
+        }
+    }
+}
+
+/// FNV-1a hash of the transforms and velocities in the world.
+///
+/// Floats are hashed by their bits, so the checksum only matches if the
+/// simulation ran exactly the same way.
+pub fn checksum(world: &World) -> u64 {
+    let mut hash = Fnv1a::new();
+    let mut query = <(&Transform, Option<&Velocity>, Option<&AngularVelocity>)>::query();
+    for (transform, velocity, angular) in query.iter(world) {
+        let rotation = transform.rotation;
+        hash.write_f32s(&[
+            transform.position.x,
+            transform.position.y,
+            transform.position.z,
+        ]);
+        hash.write_f32s(&[rotation.w, rotation.x, rotation.y, rotation.z]);
+        hash.write_f32s(&[transform.scale.x, transform.scale.y, transform.scale.z]);
+        if let Some(v) = velocity {
+            hash.write_f32s(&[v.dx, v.dy, v.dz]);
+        }
+        if let Some(AngularVelocity(w)) = angular {
+            hash.write_f32s(&[w.x, w.y, w.z]);
+        }
+    }
+    hash.finish()
+}
+
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    fn new() -> Fnv1a {
+        Fnv1a(Fnv1a::OFFSET)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(Fnv1a::PRIME);
+        }
+    }
+
+    fn write_f32s(&mut self, values: &[f32]) {
+        for value in values {
+            self.write(&value.to_bits().to_le_bytes());
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// What `GameManager` is doing with the current session.
+pub(crate) enum Replay {
+    Off,
+    Recording {
+        path: PathBuf,
+        recording: Recording,
+    },
+    Playing {
+        recording: Recording,
+        frame: usize,
+        desync: Option<usize>,
+    },
+}
+
+impl Replay {
+    /// The frame time, window events and input to run this frame with, or
+    /// `None` once a playback has run out of frames. Live window events and
+    /// input are dropped during playback.
+    pub fn frame(
+        &mut self,
+        frame_time: Duration,
+        display: Vec<DisplayEvent>,
+        input: Vec<InputEvent>,
+    ) -> Option<(Duration, Vec<DisplayEvent>, Vec<InputEvent>)> {
+        match self {
+            Replay::Off => Some((frame_time, display, input)),
+            Replay::Recording { recording, .. } => {
+                recording.frames.push(RecordedFrame {
+                    frame_time,
+                    display: display.clone(),
+                    input: input.clone(),
+                    checksum: 0,
+                });
+                Some((frame_time, display, input))
+            }
+            Replay::Playing {
+                recording, frame, ..
+            } => {
+                let recorded = recording.frames.get(*frame)?;
+                Some((
+                    recorded.frame_time,
+                    recorded.display.clone(),
+                    recorded.input.clone(),
+                ))
+            }
+        }
+    }
+
+    /// Record or check the checksum of the world at the end of the frame.
+    /// Returns the frame number if playback has just diverged from the
+    /// recording.
+    pub fn end_frame(&mut self, checksum: u64) -> Option<usize> {
+        match self {
+            Replay::Off => None,
+            Replay::Recording { recording, .. } => {
+                if let Some(last) = recording.frames.last_mut() {
+                    last.checksum = checksum;
+                }
+                None
+            }
+            Replay::Playing {
+                recording,
+                frame,
+                desync,
+            } => {
+                let expected = recording.frames.get(*frame).map(|f| f.checksum);
+                let current = *frame;
+                *frame += 1;
+                if desync.is_none() && matches!(expected, Some(e) if e != checksum) {
+                    *desync = Some(current);
+                    return *desync;
+                }
+                None
+            }
+        }
+    }
+
+    /// The first frame at which playback diverged from the recording.
+    pub fn desync(&self) -> Option<usize> {
+        match self {
+            Replay::Playing { desync, .. } => *desync,
+            _ => None,
+        }
+    }
+
+    /// Write out a recording, if one is being made.
+    pub fn finish(self) -> Result<(), ReplayError> {
+        match self {
+            Replay::Recording { path, recording } => recording.save(path),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Vector2;
+
+    fn frame(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn recordings_round_trip_through_bincode() {
+        let mut recording = Recording::new(42, frame(16), 5);
+        recording.frames.push(RecordedFrame {
+            frame_time: frame(17),
+            display: vec![DisplayEvent::Focused(false)],
+            input: vec![InputEvent::CursorMoved {
+                position: Vector2::new(3.0, 4.0),
+            }],
+            checksum: 9,
+        });
+        let path = std::env::temp_dir().join(format!("gears-replay-{}.bin", std::process::id()));
+
+        recording.save(&path).unwrap();
+        let loaded = Recording::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap(), recording);
+        assert!(matches!(
+            Recording::load(&path),
+            Err(ReplayError::Read { .. })
+        ));
+    }
+
+    #[test]
+    fn playback_repeats_the_recorded_frames() {
+        let mut replay = Replay::Recording {
+            path: PathBuf::new(),
+            recording: Recording::new(1, frame(10), 5),
+        };
+        let display = vec![DisplayEvent::Resized {
+            width: 640,
+            height: 480,
+        }];
+        let input = vec![InputEvent::CursorLeft];
+        assert_eq!(
+            replay.frame(frame(12), display.clone(), input.clone()),
+            Some((frame(12), display.clone(), input.clone()))
+        );
+        replay.end_frame(100);
+        replay.frame(frame(8), Vec::new(), Vec::new());
+        replay.end_frame(200);
+
+        let recording = match replay {
+            Replay::Recording { recording, .. } => recording,
+            _ => unreachable!(),
+        };
+        let mut replay = Replay::Playing {
+            recording,
+            frame: 0,
+            desync: None,
+        };
+
+        let live = vec![DisplayEvent::Focused(true)];
+        assert_eq!(
+            replay.frame(frame(50), live.clone(), Vec::new()),
+            Some((frame(12), display, input))
+        );
+        assert_eq!(replay.end_frame(100), None);
+        assert_eq!(
+            replay.frame(frame(50), live, Vec::new()),
+            Some((frame(8), Vec::new(), Vec::new()))
+        );
+        assert_eq!(replay.end_frame(201), Some(1));
+        assert_eq!(replay.desync(), Some(1));
+        assert_eq!(replay.frame(frame(50), Vec::new(), Vec::new()), None);
+    }
+
+    #[test]
+    fn checksums_change_with_the_world() {
+        let mut world = World::default();
+        let empty = checksum(&world);
+        let entity = world.push((Transform::identity(), Velocity::new(1.0, 0.0, 0.0)));
+        let before = checksum(&world);
+
+        assert_ne!(empty, before);
+        assert_eq!(checksum(&world), before);
+        world
+            .entry(entity)
+            .unwrap()
+            .get_component_mut::<Transform>()
+            .unwrap()
+            .position
+            .x = 0.5;
+        assert_ne!(checksum(&world), before);
+    }
+}
@@ -0,0 +1,257 @@
+// SIMD kernels
+//
+// 128-bit implementations of the math types, used in place of the scalar code
+// when the `simd` feature is enabled. Vector3, Vector4 and Quaternion
+// operators load their fields into one register each, matrices one register
+// per column. Results match the scalar paths up to float rounding.
+//
+// `batch` goes further for whole slices, setting up anything shared by every
+// entity once per slice.
+
+use wide::f32x4;
+
+use super::matrix::Matrix4x4;
+use super::quaternion::Quaternion;
+use super::vector::{Vector3, Vector4};
+
+/// Types whose fields fit one register, with unused lanes zeroed.
+pub(crate) trait Lanes: Copy {
+    fn to_lanes(self) -> f32x4;
+    fn from_lanes(lanes: f32x4) -> Self;
+}
+
+impl Lanes for Vector3 {
+    fn to_lanes(self) -> f32x4 {
+        f32x4::from([self.x, self.y, self.z, 0.0])
+    }
+
+    fn from_lanes(lanes: f32x4) -> Vector3 {
+        let [x, y, z, _]: [f32; 4] = lanes.into();
+        Vector3::new(x, y, z)
+    }
+}
+
+impl Lanes for Vector4 {
+    fn to_lanes(self) -> f32x4 {
+        f32x4::from([self.x, self.y, self.z, self.w])
+    }
+
+    fn from_lanes(lanes: f32x4) -> Vector4 {
+        let [x, y, z, w]: [f32; 4] = lanes.into();
+        Vector4::new(x, y, z, w)
+    }
+}
+
+/// Lanes in w, x, y, z order, as `Columns::from_rotation` expects.
+impl Lanes for Quaternion {
+    fn to_lanes(self) -> f32x4 {
+        f32x4::from([self.w, self.x, self.y, self.z])
+    }
+
+    fn from_lanes(lanes: f32x4) -> Quaternion {
+        let [w, x, y, z]: [f32; 4] = lanes.into();
+        Quaternion::new(w, x, y, z)
+    }
+}
+
+pub(crate) fn lanes<T: Lanes>(value: T) -> f32x4 {
+    value.to_lanes()
+}
+
+pub(crate) fn from_lanes<T: Lanes>(lanes: f32x4) -> T {
+    T::from_lanes(lanes)
+}
+
+/// Sum of the products of each pair of fields.
+pub(crate) fn dot<T: Lanes>(a: T, b: T) -> f32 {
+    (a.to_lanes() * b.to_lanes()).reduce_add()
+}
+
+/// `Vector3::cross`, multiplying the rotated fields of both vectors at once.
+pub(crate) fn cross(a: Vector3, b: Vector3) -> Vector3 {
+    let a_yzx = f32x4::from([a.y, a.z, a.x, 0.0]);
+    let a_zxy = f32x4::from([a.z, a.x, a.y, 0.0]);
+    let b_yzx = f32x4::from([b.y, b.z, b.x, 0.0]);
+    let b_zxy = f32x4::from([b.z, b.x, b.y, 0.0]);
+    Vector3::from_lanes(a_yzx * b_zxy - a_zxy * b_yzx)
+}
+
+/// The columns of a 4x4 linear map, one per register.
+#[derive(Clone, Copy)]
+pub(crate) struct Columns([f32x4; 4]);
+
+impl Columns {
+    pub fn from_matrix(m: &Matrix4x4) -> Columns {
+        Columns([
+            f32x4::from(m.m[0]),
+            f32x4::from(m.m[1]),
+            f32x4::from(m.m[2]),
+            f32x4::from(m.m[3]),
+        ])
+    }
+
+    /// The map `b -> a * b` over quaternions, with lanes in w, x, y, z order.
+    pub fn from_rotation(a: Quaternion) -> Columns {
+        Columns([
+            f32x4::from([a.w, a.x, a.y, a.z]),
+            f32x4::from([-a.x, a.w, a.z, -a.y]),
+            f32x4::from([-a.y, -a.z, a.w, a.x]),
+            f32x4::from([-a.z, a.y, -a.x, a.w]),
+        ])
+    }
+
+    /// Returns the linear combination of the columns weighted by `v`.
+    pub fn apply(&self, v: [f32; 4]) -> [f32; 4] {
+        let c = &self.0;
+        (c[0] * v[0] + c[1] * v[1] + c[2] * v[2] + c[3] * v[3]).into()
+    }
+
+    /// Transforms a position, assuming the map is affine.
+    pub fn transform_point(&self, p: Vector3) -> Vector3 {
+        let c = &self.0;
+        Vector3::from_lanes(c[0] * p.x + c[1] * p.y + c[2] * p.z + c[3])
+    }
+
+    /// Combines two rotations, as `Quaternion * Quaternion`.
+    pub fn rotate(&self, b: Quaternion) -> Quaternion {
+        let c = &self.0;
+        Quaternion::from_lanes(c[0] * b.w + c[1] * b.x + c[2] * b.y + c[3] * b.z)
+    }
+}
+
+/// `a * b`, combining whole columns at a time.
+pub(crate) fn mat4_mul(a: &Matrix4x4, b: &Matrix4x4) -> Matrix4x4 {
+    let columns = Columns::from_matrix(a);
+    let mut result = Matrix4x4::ZERO;
+    for (out, column) in result.m.iter_mut().zip(b.m.iter()) {
+        *out = columns.apply(*column);
+    }
+    result
+}
+
+/// Creates a translation, rotation and scaling matrix, as `Matrix4x4::from_trs`.
+pub(crate) fn trs(translation: Vector3, rotation: Quaternion, scale: Vector3) -> Matrix4x4 {
+    let (w, x, y, z) = (rotation.w, rotation.x, rotation.y, rotation.z);
+    let (x2, y2, z2) = (x + x, y + y, z + z);
+    let c0 = f32x4::from([
+        1.0 - (y * y2 + z * z2),
+        x * y2 + w * z2,
+        x * z2 - w * y2,
+        0.0,
+    ]);
+    let c1 = f32x4::from([
+        x * y2 - w * z2,
+        1.0 - (x * x2 + z * z2),
+        y * z2 + w * x2,
+        0.0,
+    ]);
+    let c2 = f32x4::from([
+        x * z2 + w * y2,
+        y * z2 - w * x2,
+        1.0 - (x * x2 + y * y2),
+        0.0,
+    ]);
+    Matrix4x4 {
+        m: [
+            (c0 * scale.x).into(),
+            (c1 * scale.y).into(),
+            (c2 * scale.z).into(),
+            [translation.x, translation.y, translation.z, 1.0],
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    // The scalar formulas the operators use without the `simd` feature
+
+    fn scalar_cross(a: Vector3, b: Vector3) -> Vector3 {
+        Vector3::new(
+            a.y * b.z - a.z * b.y,
+            a.z * b.x - a.x * b.z,
+            a.x * b.y - a.y * b.x,
+        )
+    }
+
+    fn scalar_product(a: Quaternion, b: Quaternion) -> Quaternion {
+        Quaternion::new(
+            a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+            a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+            a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+            a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        )
+    }
+
+    fn vectors() -> Vec<Vector3> {
+        (0..16)
+            .map(|i| {
+                let i = i as f32;
+                Vector3::new(i * 1.5 - 7.0, (i * 0.7).sin() * 40.0, 3.0 - i * i * 0.25)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn vector_operators_match_the_scalar_formulas() {
+        let vectors = vectors();
+        for (a, b) in vectors.iter().zip(vectors.iter().rev()) {
+            let (a, b) = (*a, *b);
+            assert_eq!(a + b, Vector3::new(a.x + b.x, a.y + b.y, a.z + b.z));
+            assert_eq!(a - b, Vector3::new(a.x - b.x, a.y - b.y, a.z - b.z));
+            assert_eq!(a * 2.5, Vector3::new(a.x * 2.5, a.y * 2.5, a.z * 2.5));
+            assert_eq!(a * b, Vector3::new(a.x * b.x, a.y * b.y, a.z * b.z));
+            assert_eq!(a / 4.0, Vector3::new(a.x / 4.0, a.y / 4.0, a.z / 4.0));
+            assert_approx_eq!(Vector3::dot(a, b), a.x * b.x + a.y * b.y + a.z * b.z, 1e-3);
+            assert_approx_eq!(Vector3::cross(a, b), scalar_cross(a, b), 1e-3);
+
+            let (a4, b4) = (a.extend(a.x), b.extend(-2.0));
+            assert_eq!(
+                a4 - b4,
+                Vector4::new(a4.x - b4.x, a4.y - b4.y, a4.z - b4.z, a4.w - b4.w)
+            );
+        }
+    }
+
+    #[test]
+    fn quaternion_operators_match_the_scalar_formulas() {
+        let rotations: Vec<Quaternion> = vectors()
+            .into_iter()
+            .enumerate()
+            .map(|(i, axis)| Quaternion::angle_axis(i as f32 * 23.0, axis))
+            .collect();
+        for (a, b) in rotations.iter().zip(rotations.iter().rev()) {
+            let (a, b) = (*a, *b);
+            assert_approx_eq!(a * b, scalar_product(a, b));
+            assert_approx_eq!(
+                Quaternion::dot(a, b),
+                a.w * b.w + a.x * b.x + a.y * b.y + a.z * b.z
+            );
+
+            let v = Vector3::new(b.x, b.y, b.z) * 10.0;
+            let u = Vector3::new(a.x, a.y, a.z);
+            let t = scalar_cross(u, v) * 2.0;
+            assert_approx_eq!(a * v, v + t * a.w + scalar_cross(u, t), 1e-4);
+        }
+    }
+
+    #[test]
+    fn matrix_product_matches_the_scalar_formula() {
+        let a = Matrix4x4::from_trs(
+            Vector3::new(1.0, -2.0, 3.0),
+            Quaternion::angle_axis(30.0, Vector3::new(1.0, 1.0, 0.0)),
+            Vector3::new(2.0, 0.5, 1.0),
+        );
+        let b = Matrix4x4::perspective(60.0, 16.0 / 9.0, 0.1, 100.0);
+        let mut expected = Matrix4x4::ZERO;
+        for col in 0..4 {
+            for row in 0..4 {
+                expected.m[col][row] = (0..4).map(|k| a.m[k][row] * b.m[col][k]).sum();
+            }
+        }
+
+        assert_approx_eq!(a * b, expected, 1e-4);
+    }
+}
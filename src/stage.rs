@@ -0,0 +1,384 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::error::Error;
+use std::fmt;
+
+use legion::systems::{Builder, ParallelRunnable, Runnable};
+use legion::Schedule;
+
+/// Stages of a frame, executed in declaration order.
+///
+/// `PreUpdate`, `Update` and `PostUpdate` run once per simulation tick, `Render`
+/// runs once per frame after the simulation has caught up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    PreUpdate,
+    Update,
+    PostUpdate,
+    Render,
+}
+
+impl Stage {
+    /// Stages run on every simulation tick.
+    pub const SIMULATION: [Stage; 3] = [Stage::PreUpdate, Stage::Update, Stage::PostUpdate];
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Returned when the systems in a stage can't be put in a consistent order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// A `before` or `after` constraint names a label no system in the stage has.
+    UnknownLabel { stage: Stage, label: &'static str },
+    /// The ordering constraints in the stage contradict each other.
+    Cycle { stage: Stage },
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScheduleError::UnknownLabel { stage, label } => {
+                write!(f, "Unknown system label \"{}\" in stage {}", label, stage)
+            }
+            ScheduleError::Cycle { stage } => {
+                write!(f, "Ordering constraints in stage {} form a cycle", stage)
+            }
+        }
+    }
+}
+
+impl Error for ScheduleError {}
+
+/// A system together with its ordering constraints.
+///
+/// ```ignore
+/// SystemDescriptor::new(movement_system())
+///     .label("movement")
+///     .after("input")
+///     .before("collision")
+/// ```
+pub struct SystemDescriptor {
+    add: Box<dyn FnOnce(&mut Builder)>,
+    label: Option<&'static str>,
+    before: Vec<&'static str>,
+    after: Vec<&'static str>,
+}
+
+impl SystemDescriptor {
+    /// Describe a system that may run in parallel with others.
+    pub fn new<S: ParallelRunnable + 'static>(system: S) -> SystemDescriptor {
+        SystemDescriptor::from_fn(Box::new(move |builder: &mut Builder| {
+            builder.add_system(system);
+        }))
+    }
+
+    /// Describe a system that must run on the main thread.
+    pub fn thread_local<S: Runnable + 'static>(system: S) -> SystemDescriptor {
+        SystemDescriptor::from_fn(Box::new(move |builder: &mut Builder| {
+            builder.add_thread_local(system);
+        }))
+    }
+
+    fn from_fn(add: Box<dyn FnOnce(&mut Builder)>) -> SystemDescriptor {
+        SystemDescriptor {
+            add,
+            label: None,
+            before: Vec::new(),
+            after: Vec::new(),
+        }
+    }
+
+    /// Name the system so other systems can be ordered against it.
+    pub fn label(mut self, label: &'static str) -> SystemDescriptor {
+        self.label = Some(label);
+        self
+    }
+
+    /// Run this system before every system labelled `label`.
+    pub fn before(mut self, label: &'static str) -> SystemDescriptor {
+        self.before.push(label);
+        self
+    }
+
+    /// Run this system after every system labelled `label`.
+    pub fn after(mut self, label: &'static str) -> SystemDescriptor {
+        self.after.push(label);
+        self
+    }
+}
+
+enum StageItem {
+    System(SystemDescriptor),
+    Flush,
+}
+
+/// Collects systems per stage and builds them into legion schedules.
+///
+/// Within a stage systems keep the order they were added in, unless a
+/// `before` or `after` constraint says otherwise. A flush applies the command
+/// buffers of everything added before it and acts as a barrier that
+/// constraints can't move systems across. Every stage ends with a flush.
+#[derive(Default)]
+pub struct ScheduleBuilder {
+    stages: BTreeMap<Stage, Vec<StageItem>>,
+}
+
+impl ScheduleBuilder {
+    pub fn new() -> ScheduleBuilder {
+        ScheduleBuilder::default()
+    }
+
+    /// Add a system to `stage`.
+    pub fn add_system<S: ParallelRunnable + 'static>(
+        &mut self,
+        stage: Stage,
+        system: S,
+    ) -> &mut Self {
+        self.add_system_with(stage, SystemDescriptor::new(system))
+    }
+
+    /// Add a system that must run on the main thread to `stage`.
+    pub fn add_thread_local_system<S: Runnable + 'static>(
+        &mut self,
+        stage: Stage,
+        system: S,
+    ) -> &mut Self {
+        self.add_system_with(stage, SystemDescriptor::thread_local(system))
+    }
+
+    /// Add a system with ordering constraints to `stage`.
+    pub fn add_system_with(&mut self, stage: Stage, descriptor: SystemDescriptor) -> &mut Self {
+        self.stages
+            .entry(stage)
+            .or_default()
+            .push(StageItem::System(descriptor));
+        self
+    }
+
+    /// Add a flush point to `stage`.
+    pub fn add_flush(&mut self, stage: Stage) -> &mut Self {
+        self.stages
+            .entry(stage)
+            .or_default()
+            .push(StageItem::Flush);
+        self
+    }
+
+    /// Build a schedule running `stages` in order, draining their systems.
+    ///
+    /// If the constraints in a stage can't be satisfied, the error is returned
+    /// and the stage's systems are left in place.
+    pub fn build(&mut self, stages: &[Stage]) -> Result<Schedule, ScheduleError> {
+        let mut orders = Vec::new();
+        for stage in stages {
+            let items = self.stages.get(stage).map(|i| i.as_slice()).unwrap_or(&[]);
+            orders.push(order_stage(*stage, items)?);
+        }
+
+        let mut builder = Schedule::builder();
+        for (stage, order) in stages.iter().zip(orders) {
+            let mut items: Vec<Option<StageItem>> = self
+                .stages
+                .remove(stage)
+                .unwrap_or_default()
+                .into_iter()
+                .map(Some)
+                .collect();
+            for index in order {
+                match items[index].take() {
+                    Some(StageItem::System(descriptor)) => (descriptor.add)(&mut builder),
+                    Some(StageItem::Flush) => {
+                        builder.flush();
+                    }
+                    None => {}
+                }
+            }
+            builder.flush();
+        }
+        Ok(builder.build())
+    }
+}
+
+/// Return the order the items of a stage should run in.
+fn order_stage(stage: Stage, items: &[StageItem]) -> Result<Vec<usize>, ScheduleError> {
+    let nodes: Vec<Node> = items
+        .iter()
+        .map(|item| match item {
+            StageItem::System(d) => Node {
+                label: d.label,
+                before: &d.before,
+                after: &d.after,
+                barrier: false,
+            },
+            StageItem::Flush => Node {
+                label: None,
+                before: &[],
+                after: &[],
+                barrier: true,
+            },
+        })
+        .collect();
+    sort_nodes(stage, &nodes)
+}
+
+struct Node<'a> {
+    label: Option<&'static str>,
+    before: &'a [&'static str],
+    after: &'a [&'static str],
+    barrier: bool,
+}
+
+/// Topologically sort `nodes`, preferring insertion order wherever the
+/// constraints allow it.
+fn sort_nodes(stage: Stage, nodes: &[Node]) -> Result<Vec<usize>, ScheduleError> {
+    let mut labels: HashMap<&'static str, Vec<usize>> = HashMap::new();
+    for (i, node) in nodes.iter().enumerate() {
+        if let Some(label) = node.label {
+            labels.entry(label).or_default().push(i);
+        }
+    }
+
+    let mut edges: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    for (i, node) in nodes.iter().enumerate() {
+        for label in node.before {
+            let targets = labels
+                .get(label)
+                .ok_or(ScheduleError::UnknownLabel { stage, label })?;
+            edges[i].extend(targets.iter().filter(|&&t| t != i));
+        }
+        for label in node.after {
+            let sources = labels
+                .get(label)
+                .ok_or(ScheduleError::UnknownLabel { stage, label })?;
+            for &s in sources.iter().filter(|&&s| s != i) {
+                edges[s].push(i);
+            }
+        }
+        if node.barrier {
+            for j in 0..nodes.len() {
+                if j < i {
+                    edges[j].push(i);
+                } else if j > i {
+                    edges[i].push(j);
+                }
+            }
+        }
+    }
+
+    let mut incoming = vec![0; nodes.len()];
+    for targets in &edges {
+        for &t in targets {
+            incoming[t] += 1;
+        }
+    }
+
+    let mut ready: BinaryHeap<Reverse<usize>> = incoming
+        .iter()
+        .enumerate()
+        .filter(|(_, &n)| n == 0)
+        .map(|(i, _)| Reverse(i))
+        .collect();
+    let mut order = Vec::with_capacity(nodes.len());
+    while let Some(Reverse(i)) = ready.pop() {
+        order.push(i);
+        for &t in &edges[i] {
+            incoming[t] -= 1;
+            if incoming[t] == 0 {
+                ready.push(Reverse(t));
+            }
+        }
+    }
+
+    if order.len() == nodes.len() {
+        Ok(order)
+    } else {
+        Err(ScheduleError::Cycle { stage })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system<'a>(
+        label: Option<&'static str>,
+        before: &'a [&'static str],
+        after: &'a [&'static str],
+    ) -> Node<'a> {
+        Node {
+            label,
+            before,
+            after,
+            barrier: false,
+        }
+    }
+
+    fn flush<'a>() -> Node<'a> {
+        Node {
+            label: None,
+            before: &[],
+            after: &[],
+            barrier: true,
+        }
+    }
+
+    #[test]
+    fn unconstrained_systems_keep_insertion_order() {
+        let nodes = [system(None, &[], &[]), system(None, &[], &[])];
+
+        assert_eq!(sort_nodes(Stage::Update, &nodes), Ok(vec![0, 1]));
+    }
+
+    #[test]
+    fn after_constraint_moves_system_later() {
+        let nodes = [
+            system(Some("movement"), &[], &["input"]),
+            system(Some("input"), &[], &[]),
+        ];
+
+        assert_eq!(sort_nodes(Stage::Update, &nodes), Ok(vec![1, 0]));
+    }
+
+    #[test]
+    fn before_constraint_moves_system_earlier() {
+        let nodes = [
+            system(Some("render"), &[], &[]),
+            system(Some("cull"), &["render"], &[]),
+        ];
+
+        assert_eq!(sort_nodes(Stage::Render, &nodes), Ok(vec![1, 0]));
+    }
+
+    #[test]
+    fn constraints_cannot_cross_a_flush() {
+        let nodes = [
+            system(Some("a"), &[], &["b"]),
+            flush(),
+            system(Some("b"), &[], &[]),
+        ];
+
+        assert_eq!(
+            sort_nodes(Stage::Update, &nodes),
+            Err(ScheduleError::Cycle {
+                stage: Stage::Update
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_labels_are_reported() {
+        let nodes = [system(None, &[], &["missing"])];
+
+        assert_eq!(
+            sort_nodes(Stage::PreUpdate, &nodes),
+            Err(ScheduleError::UnknownLabel {
+                stage: Stage::PreUpdate,
+                label: "missing"
+            })
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use std::time::{Duration, Instant};
+
+/// Wall clock used to measure how long each frame of the game loop takes.
+pub struct Time {
+    _delta: Instant,
+}
+
+impl Time {
+    pub fn new() -> Time {
+        Time {
+            _delta: Instant::now(),
+        }
+    }
+
+    /// Return the time elapsed since the last call to `delta` and reset the clock.
+    pub fn delta(&mut self) -> Duration {
+        let elapsed = self._delta.elapsed();
+        self._delta = Instant::now();
+        elapsed
+    }
+
+    /// Return the time elapsed since the last call to `delta` without resetting the clock.
+    pub fn split(&self) -> Duration {
+        self._delta.elapsed()
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Time::new()
+    }
+}
+
+/// Fixed timestep accumulator driving the simulation.
+///
+/// Frame time is fed in with `accumulate` and handed back out in whole steps of
+/// `step`, so every simulation tick integrates over exactly the same duration.
+/// Whatever is left over becomes `alpha`, the fraction of a step the renderer
+/// should interpolate by.
+pub struct FixedTime {
+    step: Duration,
+    max_steps: u32,
+    accumulator: Duration,
+    alpha: f32,
+    tick: u64,
+}
+
+impl FixedTime {
+    /// Create a timestep of `step`, running at most `max_steps` ticks per frame.
+    pub fn new(step: Duration, max_steps: u32) -> FixedTime {
+        assert!(
+            step > Duration::new(0, 0),
+            "Fixed timestep must be non-zero"
+        );
+        FixedTime {
+            step,
+            max_steps: max_steps.max(1),
+            accumulator: Duration::new(0, 0),
+            alpha: 0.0,
+            tick: 0,
+        }
+    }
+
+    /// Create a timestep ticking `hz` times per second.
+    pub fn from_hz(hz: u32, max_steps: u32) -> FixedTime {
+        FixedTime::new(Duration::from_secs(1) / hz.max(1), max_steps)
+    }
+
+    /// Duration of a single simulation tick.
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// Duration of a single simulation tick in seconds.
+    pub fn step_secs(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    /// Fraction of a step left in the accumulator, in the range [0, 1).
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Number of simulation ticks run so far.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Add a frame's worth of time and return how many ticks should be run.
+    ///
+    /// At most `max_steps` ticks are returned. Any time beyond that is dropped
+    /// rather than carried over, so one slow frame can't cause every following
+    /// frame to run even more ticks. The second value is the time dropped.
+    pub fn accumulate(&mut self, frame_time: Duration) -> (u32, Duration) {
+        self.accumulator += frame_time;
+
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_steps {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+
+        let mut dropped = Duration::new(0, 0);
+        if self.accumulator >= self.step {
+            // Keep the partial step so interpolation stays smooth.
+            let remainder =
+                Duration::from_nanos((self.accumulator.as_nanos() % self.step.as_nanos()) as u64);
+            dropped = self.accumulator - remainder;
+            self.accumulator = remainder;
+        }
+
+        self.alpha = self.accumulator.as_secs_f32() / self.step.as_secs_f32();
+        (steps, dropped)
+    }
+
+    /// Record that a tick has been run.
+    pub fn advance(&mut self) {
+        self.tick += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_time_runs_whole_steps_and_keeps_remainder() {
+        let mut fixed = FixedTime::new(Duration::from_millis(10), 5);
+        let (steps, dropped) = fixed.accumulate(Duration::from_millis(25));
+
+        assert_eq!(steps, 2);
+        assert_eq!(dropped, Duration::new(0, 0));
+        assert!((fixed.alpha() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn fixed_time_carries_remainder_into_next_frame() {
+        let mut fixed = FixedTime::new(Duration::from_millis(10), 5);
+        fixed.accumulate(Duration::from_millis(6));
+        let (steps, _) = fixed.accumulate(Duration::from_millis(6));
+
+        assert_eq!(steps, 1);
+        assert!((fixed.alpha() - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn fixed_time_clamps_catch_up_steps() {
+        let mut fixed = FixedTime::new(Duration::from_millis(10), 3);
+        let (steps, dropped) = fixed.accumulate(Duration::from_millis(55));
+
+        assert_eq!(steps, 3);
+        assert_eq!(dropped, Duration::from_millis(20));
+        assert!((fixed.alpha() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn fixed_time_counts_ticks() {
+        let mut fixed = FixedTime::from_hz(60, 5);
+        fixed.advance();
+        fixed.advance();
+
+        assert_eq!(fixed.tick(), 2);
+    }
+}
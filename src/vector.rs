@@ -1,57 +1,749 @@
-use std::ops::Add;
+// Vectors
+// Implements: https://docs.unity3d.com/ScriptReference/Vector2.html
+//             https://docs.unity3d.com/ScriptReference/Vector3.html
+//             https://docs.unity3d.com/ScriptReference/Vector4.html
+//             https://docs.unity3d.com/ScriptReference/Vector2Int.html
+//             https://docs.unity3d.com/ScriptReference/Vector3Int.html
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "simd")]
+use super::simd;
+
+/// Below this squared length a vector is treated as zero.
+const EPSILON_SQR: f32 = 1e-15;
+
+/// Applies `$op` to each component of `$a` and `$b`, where `$b` is a vector
+/// or, after `scalar`, a number. `lanes` vectors do it in one SIMD register
+/// when the `simd` feature is enabled.
+macro_rules! componentwise {
+    (fields, $name:ident, $a:expr, $op:tt, scalar $b:expr, $($field:ident),+) => {
+        $name { $($field: $a.$field $op $b),+ }
+    };
+    (fields, $name:ident, $a:expr, $op:tt, $b:expr, $($field:ident),+) => {
+        $name { $($field: $a.$field $op $b.$field),+ }
+    };
+    (lanes, $name:ident, $a:expr, $op:tt, scalar $b:expr, $($field:ident),+) => {{
+        #[cfg(feature = "simd")]
+        {
+            simd::from_lanes::<$name>(simd::lanes($a) $op $b)
+        }
+        #[cfg(not(feature = "simd"))]
+        {
+            componentwise!(fields, $name, $a, $op, scalar $b, $($field),+)
+        }
+    }};
+    (lanes, $name:ident, $a:expr, $op:tt, $b:expr, $($field:ident),+) => {{
+        #[cfg(feature = "simd")]
+        {
+            simd::from_lanes::<$name>(simd::lanes($a) $op simd::lanes($b))
+        }
+        #[cfg(not(feature = "simd"))]
+        {
+            componentwise!(fields, $name, $a, $op, $b, $($field),+)
+        }
+    }};
+}
+
+/// Arithmetic operators and Display shared by every vector type. Pass
+/// `lanes` for vectors that fit a SIMD register, `fields` otherwise.
+macro_rules! impl_vector_ops {
+    ($name:ident, $scalar:ty, $mode:ident, $($field:ident),+) => {
+        impl Add for $name {
+            type Output = Self;
+
+            /// Add two Vectors, return new Vector.
+            fn add(self, other: Self) -> Self {
+                componentwise!($mode, $name, self, +, other, $($field),+)
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+
+            /// Subtract one Vector from another, return new Vector.
+            fn sub(self, other: Self) -> Self {
+                componentwise!($mode, $name, self, -, other, $($field),+)
+            }
+        }
+
+        impl Mul<$scalar> for $name {
+            type Output = Self;
+
+            /// Multiply each component by a scalar, return new Vector.
+            fn mul(self, s: $scalar) -> Self {
+                componentwise!($mode, $name, self, *, scalar s, $($field),+)
+            }
+        }
+
+        impl Mul<$name> for $scalar {
+            type Output = $name;
+
+            /// Multiply each component by a scalar, return new Vector.
+            fn mul(self, v: $name) -> $name {
+                v * self
+            }
+        }
+
+        impl Mul for $name {
+            type Output = Self;
+
+            /// Multiply two Vectors component-wise, return new Vector.
+            fn mul(self, other: Self) -> Self {
+                componentwise!($mode, $name, self, *, other, $($field),+)
+            }
+        }
+
+        impl Div<$scalar> for $name {
+            type Output = Self;
+
+            /// Divide each component by a scalar, return new Vector.
+            fn div(self, s: $scalar) -> Self {
+                componentwise!($mode, $name, self, /, scalar s, $($field),+)
+            }
+        }
+
+        impl Div for $name {
+            type Output = Self;
+
+            /// Divide two Vectors component-wise, return new Vector.
+            fn div(self, other: Self) -> Self {
+                componentwise!($mode, $name, self, /, other, $($field),+)
+            }
+        }
+
+        impl Neg for $name {
+            type Output = Self;
+
+            /// Negate each component, return new Vector.
+            fn neg(self) -> Self {
+                $name { $($field: -self.$field),+ }
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, other: Self) {
+                *self = *self + other;
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, other: Self) {
+                *self = *self - other;
+            }
+        }
+
+        impl MulAssign<$scalar> for $name {
+            fn mul_assign(&mut self, s: $scalar) {
+                *self = *self * s;
+            }
+        }
+
+        impl MulAssign for $name {
+            fn mul_assign(&mut self, other: Self) {
+                *self = *self * other;
+            }
+        }
+
+        impl DivAssign<$scalar> for $name {
+            fn div_assign(&mut self, s: $scalar) {
+                *self = *self / s;
+            }
+        }
+
+        impl DivAssign for $name {
+            fn div_assign(&mut self, other: Self) {
+                *self = *self / other;
+            }
+        }
+
+        impl fmt::Display for $name {
+            /// Returns a formatted string of the Vector
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let components = [$(self.$field.to_string()),+];
+                write!(f, "{{{}}}", components.join(", "))
+            }
+        }
+    };
+}
+
+/// Length, interpolation and projection methods shared by the f32 vectors.
+macro_rules! impl_float_vector {
+    ($name:ident, $mode:ident, $($field:ident),+) => {
+        // "Properties"
+        impl $name {
+            /// Return magnitude of vector.
+            pub fn magnitude(&self) -> f32 {
+                self.sqr_magnitude().sqrt()
+            }
+
+            /// Return squared magnitude of vector.
+            pub fn sqr_magnitude(&self) -> f32 {
+                $name::dot(*self, *self)
+            }
+
+            /// Return this vector with a magnitude of 1, or zero if it's too short to normalize.
+            pub fn normalized(&self) -> $name {
+                let mut v = *self;
+                v.normalize();
+                v
+            }
+        }
+
+        // Public Methods
+        impl $name {
+            /// Normalize vector.
+            pub fn normalize(&mut self) {
+                let length = self.magnitude();
+                if length > 0.0 {
+                    $(self.$field /= length;)+
+                }
+            }
+
+            /// Scale vector.
+            pub fn scale(&mut self, s: f32) {
+                $(self.$field *= s;)+
+            }
+        }
+
+        // Static Methods
+        impl $name {
+            /// Returns the dot product of two vectors.
+            pub fn dot(a: $name, b: $name) -> f32 {
+                let products = componentwise!($mode, $name, a, *, b, $($field),+);
+                0.0 $(+ products.$field)+
+            }
+
+            /// Returns the distance between a and b.
+            pub fn distance(a: $name, b: $name) -> f32 {
+                (a - b).magnitude()
+            }
+
+            /// Returns the angle in degrees between from and to.
+            pub fn angle(from: $name, to: $name) -> f32 {
+                let denominator = (from.sqr_magnitude() * to.sqr_magnitude()).sqrt();
+                if denominator < EPSILON_SQR {
+                    return 0.0;
+                }
+                let dot = ($name::dot(from, to) / denominator).clamp(-1.0, 1.0);
+                dot.acos().to_degrees()
+            }
+
+            /// Linearly interpolates between a and b by t, with t clamped to [0, 1].
+            pub fn lerp(a: $name, b: $name, t: f32) -> $name {
+                $name::lerp_unclamped(a, b, t.clamp(0.0, 1.0))
+            }
+
+            /// Linearly interpolates between a and b by t.
+            pub fn lerp_unclamped(a: $name, b: $name, t: f32) -> $name {
+                a + (b - a) * t
+            }
+
+            /// Projects a vector onto another vector.
+            pub fn project(vector: $name, on_normal: $name) -> $name {
+                let sqr_magnitude = on_normal.sqr_magnitude();
+                if sqr_magnitude < EPSILON_SQR {
+                    return $name::ZERO;
+                }
+                on_normal * ($name::dot(vector, on_normal) / sqr_magnitude)
+            }
+
+            /// Projects a vector onto a plane defined by a normal orthogonal to the plane.
+            pub fn project_on_plane(vector: $name, plane_normal: $name) -> $name {
+                vector - $name::project(vector, plane_normal)
+            }
+
+            /// Reflects a vector off the plane defined by a normal.
+            pub fn reflect(in_direction: $name, in_normal: $name) -> $name {
+                in_direction - in_normal * (2.0 * $name::dot(in_normal, in_direction))
+            }
+
+            /// Spherically interpolates between a and b by t, with t clamped to [0, 1].
+            ///
+            /// The vectors are treated as directions, and the magnitude of the result
+            /// is interpolated linearly between the magnitudes of a and b.
+            pub fn slerp(a: $name, b: $name, t: f32) -> $name {
+                let t = t.clamp(0.0, 1.0);
+                let mag_a = a.magnitude();
+                let mag_b = b.magnitude();
+                if mag_a * mag_a < EPSILON_SQR || mag_b * mag_b < EPSILON_SQR {
+                    return $name::lerp_unclamped(a, b, t);
+                }
+
+                let from = a / mag_a;
+                let to = b / mag_b;
+                let dot = $name::dot(from, to).clamp(-1.0, 1.0);
+                let direction = if dot > 0.9995 {
+                    $name::lerp_unclamped(from, to, t).normalized()
+                } else {
+                    // Component of `to` orthogonal to `from`, or if they're opposite,
+                    // of the axis `from` points along least
+                    let mut ortho = to - from * dot;
+                    if ortho.sqr_magnitude() < 1e-8 {
+                        let mut axis = $name::ZERO;
+                        let mut smallest = f32::INFINITY;
+                        $(
+                            if from.$field.abs() < smallest {
+                                smallest = from.$field.abs();
+                                axis = $name::ZERO;
+                                axis.$field = 1.0;
+                            }
+                        )+
+                        ortho = axis - from * $name::dot(from, axis);
+                    }
+                    let ortho = ortho.normalized();
+                    let theta = dot.acos() * t;
+                    from * theta.cos() + ortho * theta.sin()
+                };
+                direction * (mag_a + (mag_b - mag_a) * t)
+            }
+
+            /// Returns a copy of vector with its magnitude clamped to max_length.
+            pub fn clamp_magnitude(vector: $name, max_length: f32) -> $name {
+                if vector.sqr_magnitude() > max_length * max_length {
+                    vector.normalized() * max_length
+                } else {
+                    vector
+                }
+            }
+
+            /// Returns a vector made from the smallest components of two vectors.
+            pub fn min(a: $name, b: $name) -> $name {
+                $name { $($field: a.$field.min(b.$field)),+ }
+            }
+
+            /// Returns a vector made from the largest components of two vectors.
+            pub fn max(a: $name, b: $name) -> $name {
+                $name { $($field: a.$field.max(b.$field)),+ }
+            }
+        }
+    };
+}
+
+/// Length and rounding methods shared by the i32 vectors.
+macro_rules! impl_int_vector {
+    ($name:ident, $float:ident, $($field:ident),+) => {
+        // "Properties"
+        impl $name {
+            /// Return magnitude of vector.
+            pub fn magnitude(&self) -> f32 {
+                (self.sqr_magnitude() as f32).sqrt()
+            }
+
+            /// Return squared magnitude of vector, widened so it can't overflow.
+            pub fn sqr_magnitude(&self) -> u64 {
+                0 $(+ u64::from(self.$field.unsigned_abs()).pow(2))+
+            }
+        }
+
+        // Public Methods
+        impl $name {
+            /// Clamps each component to the range given by min and max.
+            pub fn clamp(&mut self, min: $name, max: $name) {
+                $(self.$field = self.$field.max(min.$field).min(max.$field);)+
+            }
+        }
+
+        // Static Methods
+        impl $name {
+            /// Returns the distance between a and b.
+            pub fn distance(a: $name, b: $name) -> f32 {
+                (a - b).magnitude()
+            }
+
+            /// Returns a vector made from the smallest components of two vectors.
+            pub fn min(a: $name, b: $name) -> $name {
+                $name { $($field: a.$field.min(b.$field)),+ }
+            }
+
+            /// Returns a vector made from the largest components of two vectors.
+            pub fn max(a: $name, b: $name) -> $name {
+                $name { $($field: a.$field.max(b.$field)),+ }
+            }
+
+            /// Converts a float vector by rounding each component down.
+            pub fn floor_to_int(v: $float) -> $name {
+                $name { $($field: v.$field.floor() as i32),+ }
+            }
+
+            /// Converts a float vector by rounding each component up.
+            pub fn ceil_to_int(v: $float) -> $name {
+                $name { $($field: v.$field.ceil() as i32),+ }
+            }
+
+            /// Converts a float vector by rounding each component to the nearest integer.
+            pub fn round_to_int(v: $float) -> $name {
+                $name { $($field: v.$field.round() as i32),+ }
+            }
+        }
+
+        impl From<$name> for $float {
+            fn from(v: $name) -> $float {
+                $float { $($field: v.$field as f32),+ }
+            }
+        }
+    };
+}
+
+/// Representation of 2D vectors and points.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vector2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Representation of 3D vectors and points.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Vector3 {
     pub x: f32,
     pub y: f32,
     pub z: f32,
 }
 
-impl Vector3 {
-    pub fn up() -> f32 {
-        0.0
+/// Representation of four-dimensional vectors, e.g. homogeneous coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vector4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+/// Representation of 2D vectors and points using integers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Vector2i {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Representation of 3D vectors and points using integers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Vector3i {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl_vector_ops!(Vector2, f32, fields, x, y);
+impl_vector_ops!(Vector3, f32, lanes, x, y, z);
+impl_vector_ops!(Vector4, f32, lanes, x, y, z, w);
+impl_vector_ops!(Vector2i, i32, fields, x, y);
+impl_vector_ops!(Vector3i, i32, fields, x, y, z);
+
+impl_float_vector!(Vector2, fields, x, y);
+impl_float_vector!(Vector3, lanes, x, y, z);
+impl_float_vector!(Vector4, lanes, x, y, z, w);
+
+impl_int_vector!(Vector2i, Vector2, x, y);
+impl_int_vector!(Vector3i, Vector3, x, y, z);
+
+// Vector2
+impl Vector2 {
+    pub const ZERO: Vector2 = Vector2::new(0.0, 0.0);
+    pub const ONE: Vector2 = Vector2::new(1.0, 1.0);
+    pub const UP: Vector2 = Vector2::new(0.0, 1.0);
+    pub const DOWN: Vector2 = Vector2::new(0.0, -1.0);
+    pub const LEFT: Vector2 = Vector2::new(-1.0, 0.0);
+    pub const RIGHT: Vector2 = Vector2::new(1.0, 0.0);
+
+    /// Constructs new Vector2 with given x, y components
+    pub const fn new(x: f32, y: f32) -> Vector2 {
+        Vector2 { x, y }
     }
 
-    /// Return magnitude of vector.
-    fn magnitude(&self) -> f32 {
-        let mag = self.x * self.x + self.y * self.y + self.z * self.z;
-        mag.sqrt()
+    /// Shorthand for writing Vector2 { x: 0, y: 0 }
+    pub fn zero() -> Vector2 {
+        Vector2::ZERO
     }
 
-    /// Normalize vector.
-    fn normalize(&mut self) {
-        let length = self.magnitude();
-        if length > 0.0 {
-            self.x /= length;
-            self.y /= length;
-            self.z /= length;
+    /// Shorthand for writing Vector2 { x: 1, y: 1 }
+    pub fn one() -> Vector2 {
+        Vector2::ONE
+    }
+
+    /// Shorthand for writing Vector2 { x: 0, y: 1 }
+    pub fn up() -> Vector2 {
+        Vector2::UP
+    }
+
+    /// Shorthand for writing Vector2 { x: 0, y: -1 }
+    pub fn down() -> Vector2 {
+        Vector2::DOWN
+    }
+
+    /// Shorthand for writing Vector2 { x: -1, y: 0 }
+    pub fn left() -> Vector2 {
+        Vector2::LEFT
+    }
+
+    /// Shorthand for writing Vector2 { x: 1, y: 0 }
+    pub fn right() -> Vector2 {
+        Vector2::RIGHT
+    }
+
+    /// Set x and y components of an existing Vector2.
+    pub fn set(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    /// Returns this vector rotated 90 degrees counter-clockwise.
+    pub fn perpendicular(&self) -> Vector2 {
+        Vector2::new(-self.y, self.x)
+    }
+
+    /// Returns the signed angle in degrees between from and to, positive counter-clockwise.
+    pub fn signed_angle(from: Vector2, to: Vector2) -> f32 {
+        let angle = Vector2::angle(from, to);
+        if from.x * to.y - from.y * to.x < 0.0 {
+            -angle
+        } else {
+            angle
         }
     }
 
-    /// Scale vector.
-    fn scale(&mut self, s: f32) {
-        self.x *= s;
-        self.y *= s;
-        self.z *= s;
+    /// Returns a Vector3 with this vector's components and the given z.
+    pub fn extend(&self, z: f32) -> Vector3 {
+        Vector3::new(self.x, self.y, z)
+    }
+}
+
+// Vector3 "Static Properties"
+impl Vector3 {
+    pub const ZERO: Vector3 = Vector3::new(0.0, 0.0, 0.0);
+    pub const ONE: Vector3 = Vector3::new(1.0, 1.0, 1.0);
+    pub const UP: Vector3 = Vector3::new(0.0, 1.0, 0.0);
+    pub const DOWN: Vector3 = Vector3::new(0.0, -1.0, 0.0);
+    pub const LEFT: Vector3 = Vector3::new(-1.0, 0.0, 0.0);
+    pub const RIGHT: Vector3 = Vector3::new(1.0, 0.0, 0.0);
+    pub const FORWARD: Vector3 = Vector3::new(0.0, 0.0, 1.0);
+    pub const BACK: Vector3 = Vector3::new(0.0, 0.0, -1.0);
+
+    /// Shorthand for writing Vector3 { x: 0, y: 0, z: 0 }
+    pub fn zero() -> Vector3 {
+        Vector3::ZERO
+    }
+
+    /// Shorthand for writing Vector3 { x: 1, y: 1, z: 1 }
+    pub fn one() -> Vector3 {
+        Vector3::ONE
+    }
+
+    /// Shorthand for writing Vector3 { x: 0, y: 1, z: 0 }
+    pub fn up() -> Vector3 {
+        Vector3::UP
+    }
+
+    /// Shorthand for writing Vector3 { x: 0, y: -1, z: 0 }
+    pub fn down() -> Vector3 {
+        Vector3::DOWN
+    }
+
+    /// Shorthand for writing Vector3 { x: -1, y: 0, z: 0 }
+    pub fn left() -> Vector3 {
+        Vector3::LEFT
+    }
+
+    /// Shorthand for writing Vector3 { x: 1, y: 0, z: 0 }
+    pub fn right() -> Vector3 {
+        Vector3::RIGHT
+    }
+
+    /// Shorthand for writing Vector3 { x: 0, y: 0, z: 1 }
+    pub fn forward() -> Vector3 {
+        Vector3::FORWARD
+    }
+
+    /// Shorthand for writing Vector3 { x: 0, y: 0, z: -1 }
+    pub fn back() -> Vector3 {
+        Vector3::BACK
     }
 }
 
-impl Add for Vector3 {
-    type Output = Self;
+// Vector3
+impl Vector3 {
+    /// Constructs new Vector3 with given x, y, z components
+    pub const fn new(x: f32, y: f32, z: f32) -> Vector3 {
+        Vector3 { x, y, z }
+    }
+
+    /// Set x, y and z components of an existing Vector3.
+    pub fn set(&mut self, x: f32, y: f32, z: f32) {
+        self.x = x;
+        self.y = y;
+        self.z = z;
+    }
+
+    /// Returns a Vector4 with this vector's components and the given w.
+    pub fn extend(&self, w: f32) -> Vector4 {
+        Vector4::new(self.x, self.y, self.z, w)
+    }
+
+    /// Returns the x and y components as a Vector2.
+    pub fn truncate(&self) -> Vector2 {
+        Vector2::new(self.x, self.y)
+    }
 
-    /// Add two Vectors, return new Vector.
-    fn add(self, other: Self) -> Self {
-        Vector3 {
-            x: self.x + other.x,
-            y: self.y + other.y,
-            z: self.z + other.z,
+    /// Returns the cross product of two vectors.
+    pub fn cross(a: Vector3, b: Vector3) -> Vector3 {
+        #[cfg(feature = "simd")]
+        {
+            simd::cross(a, b)
         }
+        #[cfg(not(feature = "simd"))]
+        {
+            Vector3 {
+                x: a.y * b.z - a.z * b.y,
+                y: a.z * b.x - a.x * b.z,
+                z: a.x * b.y - a.y * b.x,
+            }
+        }
+    }
+}
+
+// Vector4
+impl Vector4 {
+    pub const ZERO: Vector4 = Vector4::new(0.0, 0.0, 0.0, 0.0);
+    pub const ONE: Vector4 = Vector4::new(1.0, 1.0, 1.0, 1.0);
+
+    /// Constructs new Vector4 with given x, y, z, w components
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Vector4 {
+        Vector4 { x, y, z, w }
+    }
+
+    /// Shorthand for writing Vector4 { x: 0, y: 0, z: 0, w: 0 }
+    pub fn zero() -> Vector4 {
+        Vector4::ZERO
+    }
+
+    /// Shorthand for writing Vector4 { x: 1, y: 1, z: 1, w: 1 }
+    pub fn one() -> Vector4 {
+        Vector4::ONE
+    }
+
+    /// Set x, y, z and w components of an existing Vector4.
+    pub fn set(&mut self, x: f32, y: f32, z: f32, w: f32) {
+        self.x = x;
+        self.y = y;
+        self.z = z;
+        self.w = w;
+    }
+
+    /// Returns the x, y and z components as a Vector3.
+    pub fn truncate(&self) -> Vector3 {
+        Vector3::new(self.x, self.y, self.z)
+    }
+}
+
+// Vector2i
+impl Vector2i {
+    pub const ZERO: Vector2i = Vector2i::new(0, 0);
+    pub const ONE: Vector2i = Vector2i::new(1, 1);
+    pub const UP: Vector2i = Vector2i::new(0, 1);
+    pub const DOWN: Vector2i = Vector2i::new(0, -1);
+    pub const LEFT: Vector2i = Vector2i::new(-1, 0);
+    pub const RIGHT: Vector2i = Vector2i::new(1, 0);
+
+    /// Constructs new Vector2i with given x, y components
+    pub const fn new(x: i32, y: i32) -> Vector2i {
+        Vector2i { x, y }
+    }
+
+    /// Set x and y components of an existing Vector2i.
+    pub fn set(&mut self, x: i32, y: i32) {
+        self.x = x;
+        self.y = y;
+    }
+}
+
+// Vector3i
+impl Vector3i {
+    pub const ZERO: Vector3i = Vector3i::new(0, 0, 0);
+    pub const ONE: Vector3i = Vector3i::new(1, 1, 1);
+    pub const UP: Vector3i = Vector3i::new(0, 1, 0);
+    pub const DOWN: Vector3i = Vector3i::new(0, -1, 0);
+    pub const LEFT: Vector3i = Vector3i::new(-1, 0, 0);
+    pub const RIGHT: Vector3i = Vector3i::new(1, 0, 0);
+    pub const FORWARD: Vector3i = Vector3i::new(0, 0, 1);
+    pub const BACK: Vector3i = Vector3i::new(0, 0, -1);
+
+    /// Constructs new Vector3i with given x, y, z components
+    pub const fn new(x: i32, y: i32, z: i32) -> Vector3i {
+        Vector3i { x, y, z }
+    }
+
+    /// Set x, y and z components of an existing Vector3i.
+    pub fn set(&mut self, x: i32, y: i32, z: i32) {
+        self.x = x;
+        self.y = y;
+        self.z = z;
+    }
+}
+
+// Conversions
+impl From<Vector2> for Vector3 {
+    /// Extends with z = 0.
+    fn from(v: Vector2) -> Vector3 {
+        v.extend(0.0)
+    }
+}
+
+impl From<Vector3> for Vector2 {
+    /// Drops z.
+    fn from(v: Vector3) -> Vector2 {
+        v.truncate()
+    }
+}
+
+impl From<Vector3> for Vector4 {
+    /// Extends with w = 0, i.e. as a direction.
+    fn from(v: Vector3) -> Vector4 {
+        v.extend(0.0)
+    }
+}
+
+impl From<Vector4> for Vector3 {
+    /// Drops w.
+    fn from(v: Vector4) -> Vector3 {
+        v.truncate()
+    }
+}
+
+impl From<Vector2> for Vector4 {
+    /// Extends with z = 0 and w = 0.
+    fn from(v: Vector2) -> Vector4 {
+        Vector4::new(v.x, v.y, 0.0, 0.0)
+    }
+}
+
+impl From<Vector4> for Vector2 {
+    /// Drops z and w.
+    fn from(v: Vector4) -> Vector2 {
+        Vector2::new(v.x, v.y)
+    }
+}
+
+impl From<Vector2i> for Vector3i {
+    /// Extends with z = 0.
+    fn from(v: Vector2i) -> Vector3i {
+        Vector3i::new(v.x, v.y, 0)
+    }
+}
+
+impl From<Vector3i> for Vector2i {
+    /// Drops z.
+    fn from(v: Vector3i) -> Vector2i {
+        Vector2i::new(v.x, v.y)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::assert_approx_eq;
 
     #[test]
     fn can_construct_vector3() {
@@ -68,10 +760,11 @@ mod tests {
 
     #[test]
     fn vector3_as_default_up() {
-        assert_eq!(Vector3::up(), 0.0);
+        assert_eq!(Vector3::up(), Vector3::new(0.0, 1.0, 0.0));
     }
 
     #[test]
+    #[allow(clippy::excessive_precision)]
     fn can_calculate_magnitude_of_vector3() {
         let v = Vector3 {
             x: 1.45698,
@@ -80,10 +773,11 @@ mod tests {
         };
         let vn = v.magnitude();
 
-        assert_eq!(vn, 42.88181);
+        assert_approx_eq!(vn, 42.88181);
     }
 
     #[test]
+    #[allow(clippy::excessive_precision)]
     fn can_normalise_vector3() {
         let mut v = Vector3 {
             x: 1.45698,
@@ -92,9 +786,8 @@ mod tests {
         };
         v.normalize();
 
-        assert_eq!(v.x, 0.033976644);
-        assert_eq!(v.y, 0.99472016);
-        assert_eq!(v.z, -0.096837014);
+        assert_approx_eq!(v, Vector3::new(0.033976644, 0.99472016, -0.096837014));
+        assert_approx_eq!(v.magnitude(), 1.0);
     }
 
     #[test]
@@ -130,4 +823,242 @@ mod tests {
         assert_eq!(v2.y, 2.0);
         assert_eq!(v2.z, 2.0);
     }
+
+    #[test]
+    fn can_subtract_two_vector3() {
+        let v = Vector3::new(3.0, 2.0, 1.0) - Vector3::new(1.0, 1.0, 1.0);
+
+        assert_eq!(v, Vector3::new(2.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn can_multiply_and_divide_vector3() {
+        let v = Vector3::new(1.0, 2.0, 3.0);
+
+        assert_eq!(v * 2.0, Vector3::new(2.0, 4.0, 6.0));
+        assert_eq!(2.0 * v, Vector3::new(2.0, 4.0, 6.0));
+        assert_eq!(v * v, Vector3::new(1.0, 4.0, 9.0));
+        assert_eq!(v / 2.0, Vector3::new(0.5, 1.0, 1.5));
+        assert_eq!(v / v, Vector3::ONE);
+        assert_eq!(-v, Vector3::new(-1.0, -2.0, -3.0));
+    }
+
+    #[test]
+    fn can_assign_operators_to_vector3() {
+        let mut v = Vector3::ONE;
+        v += Vector3::ONE;
+        v *= 3.0;
+        v -= Vector3::new(1.0, 2.0, 3.0);
+        v /= Vector3::new(5.0, 2.0, 3.0);
+
+        assert_eq!(v, Vector3::new(1.0, 2.0, 1.0));
+    }
+
+    #[test]
+    fn can_calculate_dot_and_cross_of_vector3() {
+        assert_eq!(Vector3::dot(Vector3::new(1.0, 2.0, 3.0), Vector3::ONE), 6.0);
+        assert_eq!(
+            Vector3::cross(Vector3::RIGHT, Vector3::UP),
+            Vector3::FORWARD
+        );
+        assert_eq!(
+            Vector3::cross(Vector3::UP, Vector3::FORWARD),
+            Vector3::RIGHT
+        );
+    }
+
+    #[test]
+    fn can_calculate_distance_and_angle_between_vector3() {
+        assert_eq!(
+            Vector3::distance(Vector3::ZERO, Vector3::new(3.0, 4.0, 0.0)),
+            5.0
+        );
+        assert_approx_eq!(Vector3::angle(Vector3::RIGHT, Vector3::UP), 90.0);
+        assert_approx_eq!(Vector3::angle(Vector3::RIGHT, Vector3::LEFT), 180.0);
+        assert_eq!(Vector3::angle(Vector3::ZERO, Vector3::UP), 0.0);
+    }
+
+    #[test]
+    fn can_lerp_vector3() {
+        let a = Vector3::ZERO;
+        let b = Vector3::new(2.0, 4.0, 6.0);
+
+        assert_eq!(Vector3::lerp(a, b, 0.5), Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(Vector3::lerp(a, b, 2.0), b);
+        assert_eq!(Vector3::lerp_unclamped(a, b, 2.0), b * 2.0);
+    }
+
+    #[test]
+    fn can_slerp_vector3() {
+        let v = Vector3::slerp(Vector3::RIGHT, Vector3::FORWARD * 3.0, 0.5);
+        let half = std::f32::consts::FRAC_1_SQRT_2 * 2.0;
+
+        assert_approx_eq!(v, Vector3::new(half, 0.0, half));
+
+        let opposite = Vector3::slerp(Vector3::RIGHT, Vector3::LEFT, 0.5);
+        assert_approx_eq!(opposite.magnitude(), 1.0);
+        assert_approx_eq!(Vector3::dot(opposite, Vector3::RIGHT), 0.0);
+    }
+
+    #[test]
+    fn can_project_and_reflect_vector3() {
+        let v = Vector3::new(1.0, 1.0, 0.0);
+
+        assert_eq!(Vector3::project(v, Vector3::RIGHT * 2.0), Vector3::RIGHT);
+        assert_eq!(Vector3::project(v, Vector3::ZERO), Vector3::ZERO);
+        assert_eq!(Vector3::project_on_plane(v, Vector3::UP), Vector3::RIGHT);
+        assert_eq!(
+            Vector3::reflect(Vector3::new(1.0, -1.0, 0.0), Vector3::UP),
+            Vector3::new(1.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn can_clamp_magnitude_of_vector3() {
+        assert_eq!(
+            Vector3::clamp_magnitude(Vector3::new(0.0, 10.0, 0.0), 2.0),
+            Vector3::new(0.0, 2.0, 0.0)
+        );
+        assert_eq!(Vector3::clamp_magnitude(Vector3::ONE, 2.0), Vector3::ONE);
+    }
+
+    #[test]
+    fn can_take_min_and_max_of_vector3() {
+        let a = Vector3::new(1.0, 5.0, -1.0);
+        let b = Vector3::new(2.0, 3.0, -2.0);
+
+        assert_eq!(Vector3::min(a, b), Vector3::new(1.0, 3.0, -2.0));
+        assert_eq!(Vector3::max(a, b), Vector3::new(2.0, 5.0, -1.0));
+    }
+
+    #[test]
+    fn vector3_directions_are_unity_conventions() {
+        assert_eq!(Vector3::forward(), Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(Vector3::back(), -Vector3::forward());
+        assert_eq!(Vector3::left(), -Vector3::right());
+        assert_eq!(Vector3::down(), -Vector3::up());
+        assert_eq!(Vector3::zero() + Vector3::one(), Vector3::ONE);
+    }
+
+    #[test]
+    fn vector3_displays_components() {
+        assert_eq!(Vector3::new(1.0, 2.5, -3.0).to_string(), "{1, 2.5, -3}");
+    }
+
+    #[test]
+    fn vector2_shares_vector3_operations() {
+        let v = Vector2::new(3.0, 4.0);
+
+        assert_eq!(v.magnitude(), 5.0);
+        assert_eq!(v.normalized(), Vector2::new(0.6, 0.8));
+        assert_eq!(v + Vector2::ONE, Vector2::new(4.0, 5.0));
+        assert_eq!(-v * 2.0, Vector2::new(-6.0, -8.0));
+        assert_eq!(Vector2::dot(v, Vector2::RIGHT), 3.0);
+        assert_eq!(Vector2::lerp(Vector2::ZERO, v, 0.5), Vector2::new(1.5, 2.0));
+        assert_eq!(
+            Vector2::reflect(Vector2::new(1.0, -1.0), Vector2::UP),
+            Vector2::ONE
+        );
+    }
+
+    #[test]
+    fn vector2_signed_angle_is_counter_clockwise() {
+        assert_approx_eq!(Vector2::signed_angle(Vector2::RIGHT, Vector2::UP), 90.0);
+        assert_approx_eq!(Vector2::signed_angle(Vector2::RIGHT, Vector2::DOWN), -90.0);
+        assert_eq!(Vector2::RIGHT.perpendicular(), Vector2::UP);
+    }
+
+    #[test]
+    fn vector4_shares_vector3_operations() {
+        let v = Vector4::new(1.0, 2.0, 2.0, 4.0);
+
+        assert_eq!(v.magnitude(), 5.0);
+        assert_eq!(v / 2.0, Vector4::new(0.5, 1.0, 1.0, 2.0));
+        assert_eq!(Vector4::dot(v, Vector4::ONE), 9.0);
+        assert_eq!(
+            Vector4::max(v, Vector4::ONE * 3.0),
+            Vector4::new(3.0, 3.0, 3.0, 4.0)
+        );
+    }
+
+    #[test]
+    fn can_convert_between_vector_sizes() {
+        let v2 = Vector2::new(1.0, 2.0);
+        let v3: Vector3 = v2.into();
+        let v4 = v3.extend(1.0);
+
+        assert_eq!(v3, Vector3::new(1.0, 2.0, 0.0));
+        assert_eq!(v4, Vector4::new(1.0, 2.0, 0.0, 1.0));
+        assert_eq!(Vector3::from(v4), v3);
+        assert_eq!(Vector2::from(v4), v2);
+        assert_eq!(Vector4::from(v2), Vector4::new(1.0, 2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn can_slerp_and_project_vector2_and_vector4() {
+        let v = Vector2::slerp(Vector2::RIGHT, Vector2::UP * 3.0, 0.5);
+        let half = std::f32::consts::FRAC_1_SQRT_2 * 2.0;
+        assert_approx_eq!(v, Vector2::new(half, half));
+
+        let turned = Vector4::slerp(Vector4::new(1.0, 0.0, 0.0, 0.0), -Vector4::ONE, 0.5);
+        assert_approx_eq!(turned.magnitude(), 1.5);
+        let opposite = Vector2::slerp(Vector2::RIGHT, Vector2::LEFT, 0.5);
+        assert_approx_eq!(Vector2::dot(opposite, Vector2::RIGHT), 0.0);
+
+        let v4 = Vector4::new(1.0, 2.0, 3.0, 4.0);
+        let w = Vector4::new(0.0, 0.0, 0.0, 2.0);
+        assert_eq!(
+            Vector4::project_on_plane(v4, w),
+            Vector4::new(1.0, 2.0, 3.0, 0.0)
+        );
+        assert_eq!(
+            Vector2::project_on_plane(Vector2::ONE, Vector2::UP),
+            Vector2::RIGHT
+        );
+    }
+
+    #[test]
+    fn integer_vectors_support_grid_math() {
+        let mut v = Vector2i::new(3, 4) * 2 - Vector2i::ONE;
+        assert_eq!(v, Vector2i::new(5, 7));
+        assert_eq!(Vector2i::new(3, 4).sqr_magnitude(), 25);
+        assert_eq!(
+            Vector3i::new(i32::MIN, i32::MAX, 46341).sqr_magnitude(),
+            (1 << 62) + (i32::MAX as u64).pow(2) + 46341 * 46341
+        );
+        assert_eq!(Vector2i::distance(Vector2i::ZERO, Vector2i::new(3, 4)), 5.0);
+
+        v.clamp(Vector2i::ZERO, Vector2i::new(6, 6));
+        assert_eq!(v, Vector2i::new(5, 6));
+
+        let c = Vector3i::new(-1, 2, 3);
+        assert_eq!(Vector3i::min(c, Vector3i::ZERO), Vector3i::new(-1, 0, 0));
+        assert_eq!(Vector2i::from(c), Vector2i::new(-1, 2));
+        assert_eq!(Vector3::from(c), Vector3::new(-1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn float_vectors_round_to_integer_vectors() {
+        let v = Vector3::new(1.5, -1.5, 2.2);
+
+        assert_eq!(Vector3i::floor_to_int(v), Vector3i::new(1, -2, 2));
+        assert_eq!(Vector3i::ceil_to_int(v), Vector3i::new(2, -1, 3));
+        assert_eq!(Vector3i::round_to_int(v), Vector3i::new(2, -2, 2));
+        assert_eq!(
+            Vector2i::floor_to_int(Vector2::new(0.9, -0.1)),
+            Vector2i::new(0, -1)
+        );
+    }
+
+    #[test]
+    fn vectors_round_trip_through_bincode() {
+        let v3 = Vector3::new(1.0, -2.0, 3.5);
+        let v2i = Vector2i::new(-4, 9);
+
+        let bytes = bincode::serialize(&(v3, v2i)).unwrap();
+        let (d3, d2i): (Vector3, Vector2i) = bincode::deserialize(&bytes).unwrap();
+
+        assert_eq!(d3, v3);
+        assert_eq!(d2i, v2i);
+    }
 }
@@ -0,0 +1,364 @@
+// The full game loop, run on the headless display backend as CI runs it.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use gears::action::{ActionState, Button};
+use gears::component::{Transform, Velocity};
+use gears::display_backend::{BackendKind, Framebuffer};
+use gears::display_manager::{DisplayConfig, DisplayEvent, DisplayManager};
+use gears::event::{EventReader, Events};
+use gears::game_manager::GameManager;
+use gears::game_state::{GameControl, GameState};
+use gears::input_manager::{Input, InputEvent, InputManager};
+use gears::log_manager::LogManager;
+use gears::random::Random;
+use gears::replay::{ReplayError, ReplayMode};
+use gears::stage::{ScheduleError, Stage, SystemDescriptor};
+use gears::time::FixedTime;
+use legion::world::SubWorld;
+use legion::*;
+use rand::Rng;
+use winit::event::{ElementState, VirtualKeyCode};
+
+#[system]
+fn stop_after_ten_ticks(#[resource] time: &FixedTime, #[resource] control: &mut GameControl) {
+    if time.tick() >= 10 {
+        control.request_stop();
+    }
+}
+
+/// Light the pixel under every entity, and stop once something was drawn.
+#[system(for_each)]
+fn plot(
+    transform: &Transform,
+    #[resource] framebuffer: &mut Framebuffer,
+    #[resource] control: &mut GameControl,
+) {
+    let (x, y) = (transform.position.x as u32, transform.position.y as u32);
+    framebuffer.set(x, y, 0xffff_ffff);
+    control.request_stop();
+}
+
+/// Count the ticks seeing space and the jump action go down, stopping after
+/// three ticks.
+#[system]
+fn count_jumps(
+    #[state] keys: &mut Arc<AtomicUsize>,
+    #[state] jumps: &mut Arc<AtomicUsize>,
+    #[resource] input: &Input,
+    #[resource] actions: &ActionState,
+    #[resource] time: &FixedTime,
+    #[resource] control: &mut GameControl,
+) {
+    if input.keys.just_pressed(VirtualKeyCode::Space) {
+        keys.fetch_add(1, Ordering::Relaxed);
+    }
+    if actions.just_pressed("jump") {
+        jumps.fetch_add(1, Ordering::Relaxed);
+    }
+    if time.tick() >= 2 {
+        control.request_stop();
+    }
+}
+
+/// Steer every entity at random, so runs only match if `Random` does.
+#[system(for_each)]
+fn wander(velocity: &mut Velocity, #[resource] random: &mut Random) {
+    *velocity = Velocity::new(random.gen_range(-1.0..1.0), 0.0, 0.0);
+}
+
+/// Lift every entity when the window loses focus, so runs only match if the
+/// window events do.
+#[system]
+#[write_component(Transform)]
+fn lift_on_focus_loss(
+    world: &mut SubWorld,
+    #[state] reader: &mut EventReader<DisplayEvent>,
+    #[resource] events: &Events<DisplayEvent>,
+) {
+    if reader
+        .iter(events)
+        .any(|e| *e == DisplayEvent::Focused(false))
+    {
+        for transform in <&mut Transform>::query().iter_mut(world) {
+            transform.position.y += 5.0;
+        }
+    }
+}
+
+/// Pause on the first frame and stop on the thirtieth, sending an input event
+/// every frame and keeping the most events held once paused in `most_held`.
+#[system]
+fn pause_and_send_input(
+    #[state] frame: &mut usize,
+    #[state] most_held: &mut Arc<AtomicUsize>,
+    #[resource] control: &mut GameControl,
+    #[resource] events: &mut Events<InputEvent>,
+) {
+    *frame += 1;
+    match *frame {
+        1 => control.request_pause(),
+        30 => control.request_stop(),
+        _ => {}
+    }
+    events.send(InputEvent::CursorLeft);
+    if *frame > 3 {
+        most_held.fetch_max(events.len(), Ordering::Relaxed);
+    }
+}
+
+fn headless_display(log_manager: &LogManager) -> DisplayManager<'_> {
+    let config = DisplayConfig {
+        width: 64,
+        height: 32,
+        backend: Some(BackendKind::Headless { framebuffer: true }),
+        ..DisplayConfig::default()
+    };
+    let mut display_manager = DisplayManager::with_config(log_manager, config);
+    display_manager.startup();
+    display_manager
+}
+
+#[test]
+fn the_game_loop_runs_without_a_display() {
+    let log_manager = LogManager::new();
+    let mut world = World::default();
+    let entity = world.push((Transform::identity(), Velocity::new(1.0, 0.0, 0.0)));
+
+    let mut game_manager = GameManager::new(&log_manager, &mut world);
+    game_manager
+        .attach_display(headless_display(&log_manager))
+        .add_system(Stage::Update, stop_after_ten_ticks_system());
+    game_manager.startup().unwrap();
+    game_manager.run();
+
+    assert_eq!(game_manager.state(), GameState::Stopped);
+    let display_manager = game_manager.display_manager().unwrap();
+    assert!(display_manager.frames() > 0);
+    assert_eq!(display_manager.size(), (64, 32));
+    let framebuffer = display_manager.framebuffer().unwrap();
+    assert_eq!((framebuffer.width(), framebuffer.height()), (64, 32));
+    game_manager.shutdown();
+
+    let transform = *world
+        .entry(entity)
+        .unwrap()
+        .get_component::<Transform>()
+        .unwrap();
+    assert!(transform.position.x > 0.15);
+}
+
+#[test]
+fn render_systems_draw_into_the_framebuffer() {
+    let log_manager = LogManager::new();
+    let mut world = World::default();
+    let mut transform = Transform::identity();
+    transform.position.x = 3.0;
+    transform.position.y = 2.0;
+    world.push((transform,));
+
+    let mut game_manager = GameManager::new(&log_manager, &mut world);
+    game_manager
+        .attach_display(headless_display(&log_manager))
+        .add_system(Stage::Render, plot_system());
+    game_manager.startup().unwrap();
+    game_manager.run();
+
+    let display_manager = game_manager.display_manager().unwrap();
+    let framebuffer = display_manager.framebuffer().unwrap();
+    assert_eq!((framebuffer.width(), framebuffer.height()), (64, 32));
+    assert_eq!(framebuffer.get(3, 2), Some(0xffff_ffff));
+    assert_eq!(framebuffer.pixels().iter().filter(|&&p| p != 0).count(), 1);
+    game_manager.shutdown();
+}
+
+#[test]
+fn closing_the_display_stops_the_game_loop() {
+    let log_manager = LogManager::new();
+    let mut world = World::default();
+    let mut display_manager = headless_display(&log_manager);
+    display_manager.inject(DisplayEvent::CloseRequested);
+
+    let mut game_manager = GameManager::new(&log_manager, &mut world);
+    game_manager.attach_display(display_manager);
+    game_manager.startup().unwrap();
+    game_manager.run();
+
+    assert_eq!(game_manager.state(), GameState::Stopped);
+    assert_eq!(game_manager.display_manager().unwrap().frames(), 1);
+    game_manager.shutdown();
+}
+
+/// Run the wandering game until ten ticks in, returning where the entity ends
+/// up and whether playback diverged from the recording.
+fn run_wandering(
+    mode: ReplayMode,
+    display: Vec<DisplayEvent>,
+    input: Vec<InputEvent>,
+) -> (Transform, Option<usize>) {
+    let log_manager = LogManager::new();
+    let mut world = World::default();
+    let entity = world.push((Transform::identity(), Velocity::new(1.0, 0.0, 0.0)));
+    let mut display_manager = headless_display(&log_manager);
+    for event in display {
+        display_manager.inject(event);
+    }
+    let mut input_manager = InputManager::new(&log_manager);
+    for event in input {
+        input_manager.inject(event);
+    }
+
+    let mut game_manager = GameManager::new(&log_manager, &mut world);
+    game_manager.set_replay_mode(mode).unwrap();
+    game_manager
+        .attach_display(display_manager)
+        .attach_input(input_manager)
+        .add_system(Stage::Update, wander_system())
+        .add_system(Stage::Update, lift_on_focus_loss_system(EventReader::new()))
+        .add_system(Stage::Update, stop_after_ten_ticks_system());
+    game_manager.startup().unwrap();
+    game_manager.run();
+    let desync = game_manager.replay_desync();
+    game_manager.shutdown();
+
+    let transform = *world
+        .entry(entity)
+        .unwrap()
+        .get_component::<Transform>()
+        .unwrap();
+    (transform, desync)
+}
+
+#[test]
+fn playing_back_a_recording_matches_it() {
+    let path = std::env::temp_dir().join(format!("gears-loop-{}.bin", std::process::id()));
+    let (recorded, _) = run_wandering(
+        ReplayMode::Record(path.clone()),
+        vec![DisplayEvent::Focused(false)],
+        vec![InputEvent::FocusLost, InputEvent::CursorLeft],
+    );
+    // The window keeps focus this time, so only the recording can lift it
+    let (played, desync) =
+        run_wandering(ReplayMode::Playback(path.clone()), Vec::new(), Vec::new());
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(recorded.position.y >= 5.0);
+    assert_eq!(desync, None);
+    assert_eq!(played, recorded);
+}
+
+#[test]
+fn presses_in_frames_without_ticks_reach_the_next_tick() {
+    let log_manager = LogManager::new();
+    let mut world = World::default();
+    let mut input_manager = InputManager::new(&log_manager);
+    input_manager
+        .actions_mut()
+        .bind_button("jump", Button::Key(VirtualKeyCode::Space));
+    input_manager.inject(InputEvent::Key {
+        key: VirtualKeyCode::Space,
+        state: ElementState::Pressed,
+    });
+    let keys = Arc::new(AtomicUsize::new(0));
+    let jumps = Arc::new(AtomicUsize::new(0));
+
+    let mut game_manager = GameManager::new(&log_manager, &mut world);
+    // The first frames are far shorter than a tick, so run none
+    game_manager.fixed_step = std::time::Duration::from_millis(50);
+    game_manager.target_time = std::time::Duration::from_millis(1);
+    game_manager.attach_input(input_manager).add_system(
+        Stage::Update,
+        count_jumps_system(keys.clone(), jumps.clone()),
+    );
+    game_manager.startup().unwrap();
+    game_manager.run();
+    game_manager.shutdown();
+
+    assert_eq!(keys.load(Ordering::Relaxed), 1);
+    assert_eq!(jumps.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn events_do_not_pile_up_while_paused() {
+    let log_manager = LogManager::new();
+    let mut world = World::default();
+    let mut input_manager = InputManager::new(&log_manager);
+    for _ in 0..100 {
+        input_manager.inject(InputEvent::FocusLost);
+    }
+    let most_held = Arc::new(AtomicUsize::new(0));
+
+    let mut game_manager = GameManager::new(&log_manager, &mut world);
+    game_manager.target_time = std::time::Duration::from_millis(1);
+    game_manager.attach_input(input_manager).add_system(
+        Stage::Render,
+        pause_and_send_input_system(0, most_held.clone()),
+    );
+    game_manager.startup().unwrap();
+    game_manager.run();
+
+    assert_eq!(game_manager.state(), GameState::Stopped);
+    // This frame's event and the last one's
+    assert_eq!(most_held.load(Ordering::Relaxed), 2);
+    game_manager.shutdown();
+}
+
+#[test]
+fn startup_fails_on_an_ordering_cycle() {
+    let log_manager = LogManager::new();
+    let mut world = World::default();
+
+    let mut game_manager = GameManager::new(&log_manager, &mut world);
+    game_manager
+        .add_system_with(
+            Stage::Update,
+            SystemDescriptor::new(stop_after_ten_ticks_system())
+                .label("stop")
+                .before("wander"),
+        )
+        .add_system_with(
+            Stage::Update,
+            SystemDescriptor::new(wander_system())
+                .label("wander")
+                .before("stop"),
+        );
+
+    assert!(matches!(
+        game_manager.startup(),
+        Err(ScheduleError::Cycle {
+            stage: Stage::Update
+        })
+    ));
+}
+
+#[test]
+fn the_replay_mode_cannot_change_once_started() {
+    let log_manager = LogManager::new();
+    let mut world = World::default();
+
+    let mut game_manager = GameManager::new(&log_manager, &mut world);
+    game_manager.startup().unwrap();
+
+    assert!(matches!(
+        game_manager.set_replay_mode(ReplayMode::Record(std::env::temp_dir().join("unused.bin"))),
+        Err(ReplayError::AlreadyStarted)
+    ));
+    game_manager.shutdown();
+}
+
+#[test]
+fn stops_requested_before_startup_stop_the_first_frame() {
+    let log_manager = LogManager::new();
+    let mut world = World::default();
+
+    let mut game_manager = GameManager::new(&log_manager, &mut world);
+    game_manager.attach_display(headless_display(&log_manager));
+    game_manager.request_stop();
+    game_manager.startup().unwrap();
+    game_manager.run();
+
+    assert_eq!(game_manager.state(), GameState::Stopped);
+    assert_eq!(game_manager.display_manager().unwrap().frames(), 1);
+    game_manager.shutdown();
+}
@@ -0,0 +1,123 @@
+// Invariants the math types must hold for any input, checked with proptest.
+
+use gears::angle::Degrees;
+use gears::approx::ApproxEq;
+use gears::assert_approx_eq;
+use gears::matrix::Matrix4x4;
+use gears::quaternion::{Quaternion, RotationOrder};
+use gears::vector::Vector3;
+use proptest::prelude::*;
+
+const ORDERS: [RotationOrder; 6] = [
+    RotationOrder::XYZ,
+    RotationOrder::XZY,
+    RotationOrder::YXZ,
+    RotationOrder::YZX,
+    RotationOrder::ZXY,
+    RotationOrder::ZYX,
+];
+
+fn vector3() -> impl Strategy<Value = Vector3> {
+    (-1000.0f32..1000.0, -1000.0f32..1000.0, -1000.0f32..1000.0)
+        .prop_map(|(x, y, z)| Vector3::new(x, y, z))
+}
+
+/// Vectors long enough that normalizing them is well defined.
+fn direction() -> impl Strategy<Value = Vector3> {
+    vector3().prop_filter("too short to normalize", |v| v.magnitude() > 1e-3)
+}
+
+fn rotation() -> impl Strategy<Value = Quaternion> {
+    (direction(), -360.0f32..360.0).prop_map(|(axis, angle)| Quaternion::angle_axis(angle, axis))
+}
+
+fn scale() -> impl Strategy<Value = Vector3> {
+    (0.1f32..10.0, 0.1f32..10.0, 0.1f32..10.0).prop_map(|(x, y, z)| Vector3::new(x, y, z))
+}
+
+/// Two quaternions represent the same rotation if they're equal up to sign.
+fn same_rotation(a: Quaternion, b: Quaternion) -> bool {
+    Quaternion::dot(a, b).abs().approx_eq_eps(&1.0, 1e-4)
+}
+
+proptest! {
+    #[test]
+    fn normalize_gives_unit_length(v in direction()) {
+        assert_approx_eq!(v.normalized().magnitude(), 1.0);
+    }
+
+    #[test]
+    fn cross_product_is_perpendicular(a in direction(), b in direction()) {
+        let c = Vector3::cross(a.normalized(), b.normalized());
+
+        assert_approx_eq!(Vector3::dot(c, a.normalized()), 0.0, 1e-4);
+        assert_approx_eq!(Vector3::dot(c, b.normalized()), 0.0, 1e-4);
+    }
+
+    #[test]
+    fn quaternion_times_inverse_is_identity(q in rotation()) {
+        assert_approx_eq!(q * q.inverse(), Quaternion::identity());
+        assert_approx_eq!(q.inverse() * q, Quaternion::identity());
+    }
+
+    #[test]
+    fn rotation_preserves_length(q in rotation(), v in vector3()) {
+        let rotated = q * v;
+
+        assert!(rotated.magnitude().approx_eq_eps(&v.magnitude(), v.magnitude() * 1e-5 + 1e-4));
+    }
+
+    #[test]
+    fn rotating_by_a_product_rotates_by_each_in_turn(a in rotation(), b in rotation(), v in direction()) {
+        let v = v.normalized();
+
+        assert_approx_eq!((a * b) * v, a * (b * v), 1e-4);
+    }
+
+    #[test]
+    fn euler_angles_round_trip(
+        x in -180.0f32..180.0,
+        y in -180.0f32..180.0,
+        z in -180.0f32..180.0,
+        order in proptest::sample::select(ORDERS.to_vec()),
+    ) {
+        let q = Quaternion::from_euler(Degrees(x), Degrees(y), Degrees(z), order);
+        let (rx, ry, rz) = q.to_euler(order);
+
+        prop_assert!(same_rotation(Quaternion::from_euler(rx, ry, rz, order), q));
+    }
+
+    #[test]
+    fn default_euler_angles_round_trip(q in rotation()) {
+        prop_assert!(same_rotation(Quaternion::new_from_euler(q.euler_angles()), q));
+    }
+
+    #[test]
+    fn slerp_hits_both_ends(a in rotation(), b in rotation()) {
+        prop_assert!(same_rotation(Quaternion::slerp(a, b, 0.0), a));
+        prop_assert!(same_rotation(Quaternion::slerp(a, b, 1.0), b));
+    }
+
+    #[test]
+    fn rotation_matrix_round_trips(q in rotation()) {
+        let (_, back, _) = Matrix4x4::from_rotation(q).decompose();
+
+        prop_assert!(same_rotation(back, q));
+    }
+
+    #[test]
+    fn trs_matrix_times_inverse_is_identity(t in vector3(), r in rotation(), s in scale()) {
+        let m = Matrix4x4::from_trs(t, r, s);
+
+        assert_approx_eq!(m * m.inverse().unwrap(), Matrix4x4::IDENTITY, 1e-3);
+    }
+
+    #[test]
+    fn trs_matrix_decomposes_to_its_parts(t in vector3(), r in rotation(), s in scale()) {
+        let (dt, dr, ds) = Matrix4x4::from_trs(t, r, s).decompose();
+
+        assert_approx_eq!(dt, t);
+        assert_approx_eq!(ds, s, 1e-4);
+        prop_assert!(same_rotation(dr, r));
+    }
+}